@@ -0,0 +1,8 @@
+fn main() {
+    // Same window and translations as slint_demo
+    slint_build::compile_with_config(
+        "../src/bin/slint_demo/main.slint",
+        slint_build::CompilerConfiguration::new().with_bundled_translations("../lang"),
+    )
+    .unwrap();
+}
@@ -0,0 +1,22 @@
+//! slint_demo as an Android app
+//!
+//! Apps can't open the serial ports of an OTG adapter directly, a USB serial app on the phone
+//! forwards it over TCP instead and the sensor is started on e.g. "tcp://127.0.0.1:4001".
+
+#[path = "../../src/bin/slint_demo/main.rs"]
+mod slint_demo;
+
+#[unsafe(no_mangle)]
+fn android_main(app: slint::android::AndroidApp) {
+    // CSV logs are written to the working directory
+    if let Some(dir) = app.internal_data_path() {
+        let _ = std::env::set_current_dir(dir);
+    }
+
+    let result = slint::android::init(app)
+        .map_err(anyhow::Error::from)
+        .and_then(|_| slint_demo::run());
+    if let Err(e) = result {
+        eprintln!("slint_demo stopped: {e}");
+    }
+}
@@ -13,5 +13,22 @@ fn main() {
             .compile()
             .unwrap();
     }
-    slint_build::compile("src/bin/slint_demo/main.slint").unwrap();
+    // Translations are compiled in, see lang/
+    slint_build::compile_with_config(
+        "src/bin/slint_demo/main.slint",
+        slint_build::CompilerConfiguration::new().with_bundled_translations("lang"),
+    )
+    .unwrap();
+
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    // Use the bundled protoc so no system install is needed
+    unsafe {
+        env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+    }
+    tonic_prost_build::compile_protos("proto/envsensor.proto").unwrap();
 }
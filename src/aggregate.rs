@@ -0,0 +1,243 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeDelta};
+use serde::{Deserialize, Serialize};
+use strum::AsRefStr;
+
+use crate::logger::{LogLock, TimestampFormat};
+use crate::sensor::{SampleData, SensorChannel, SensorData, SensorType, Unit};
+
+/// How often the aggregates of all periods are published
+pub const PUBLISH_INTERVAL: TimeDelta = TimeDelta::minutes(1);
+
+/// Averaging period of an aggregate
+#[derive(AsRefStr, Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Period {
+    #[strum(serialize = "1min")]
+    OneMinute,
+    #[strum(serialize = "15min")]
+    FifteenMinutes,
+    #[strum(serialize = "1h")]
+    OneHour,
+}
+
+impl Period {
+    pub const ALL: [Period; 3] = [Period::OneMinute, Period::FifteenMinutes, Period::OneHour];
+
+    pub fn duration(self) -> TimeDelta {
+        match self {
+            Period::OneMinute => TimeDelta::minutes(1),
+            Period::FifteenMinutes => TimeDelta::minutes(15),
+            Period::OneHour => TimeDelta::hours(1),
+        }
+    }
+}
+
+/// Mean and maximum of one channel over a period
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChannelAggregate {
+    pub ty: SensorType,
+    pub unit: Unit,
+    pub mean: f32,
+    pub max: f32,
+}
+
+/// Rolling statistics of all channels over the `period` up to `timestamp`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Aggregate {
+    pub timestamp: DateTime<Local>,
+    pub period: Period,
+    /// Samples the statistics are based on, fewer than the period holds early in a session
+    pub count: usize,
+    pub data: Vec<ChannelAggregate>,
+}
+
+impl Aggregate {
+    /// The means as a sample at the end of the period
+    pub fn means(&self) -> SampleData {
+        SampleData {
+            timestamp: self.timestamp,
+            data: self
+                .data
+                .iter()
+                .map(|d| SensorData::new(d.ty, d.mean, d.unit))
+                .collect(),
+        }
+    }
+}
+
+/// Rolling means and maxima of the processed samples over each `Period`
+pub struct Aggregator {
+    samples: VecDeque<SampleData>,
+    last_publish: Option<DateTime<Local>>,
+}
+
+impl Aggregator {
+    pub fn new() -> Self {
+        Self {
+            samples: VecDeque::new(),
+            last_publish: None,
+        }
+    }
+
+    /// Take in a sample, returns the aggregates of all periods once `PUBLISH_INTERVAL` has
+    /// passed since the last ones
+    pub fn push(&mut self, sample: &SampleData) -> Vec<Aggregate> {
+        let now = sample.timestamp;
+        self.samples.push_back(sample.clone());
+
+        let longest = Period::OneHour.duration();
+        while self
+            .samples
+            .front()
+            .is_some_and(|s| now - s.timestamp >= longest)
+        {
+            self.samples.pop_front();
+        }
+
+        let last = *self.last_publish.get_or_insert(now);
+        if now - last < PUBLISH_INTERVAL {
+            return Vec::new();
+        }
+        self.last_publish = Some(now);
+
+        Period::ALL
+            .iter()
+            .map(|&period| self.aggregate(period, now))
+            .collect()
+    }
+
+    fn aggregate(&self, period: Period, now: DateTime<Local>) -> Aggregate {
+        let samples = self
+            .samples
+            .iter()
+            .filter(|s| now - s.timestamp < period.duration())
+            .collect::<Vec<_>>();
+        let channels = samples.last().map_or(&[][..], |s| &s.data[..]);
+
+        let data = channels
+            .iter()
+            .enumerate()
+            .map(|(n, d)| {
+                let values = samples
+                    .iter()
+                    .filter_map(|s| s.data.get(n).map(|d| d.value));
+                let (sum, max) =
+                    values.fold((0.0, f32::MIN), |(sum, max), v| (sum + v, max.max(v)));
+                ChannelAggregate {
+                    ty: d.ty,
+                    unit: d.unit,
+                    mean: sum / samples.len() as f32,
+                    max,
+                }
+            })
+            .collect();
+
+        Aggregate {
+            timestamp: now,
+            period,
+            count: samples.len(),
+            data,
+        }
+    }
+}
+
+impl Default for Aggregator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// CSV of the aggregates next to a sample log, one row per period and publication
+pub struct SummaryCsv {
+    file: BufWriter<File>,
+    timestamp: TimestampFormat,
+    /// Keeps `apply_retention` of other sessions away
+    _lock: LogLock,
+}
+
+impl SummaryCsv {
+    /// "<log stem>_summary.csv" next to `log`
+    pub fn path(log: &Path) -> PathBuf {
+        let stem = log.file_stem().unwrap_or_default().to_string_lossy();
+        log.with_file_name(format!("{stem}_summary.csv"))
+    }
+
+    pub fn create(
+        log: &Path,
+        channels: &[SensorChannel],
+        timestamp: TimestampFormat,
+    ) -> Result<Self> {
+        let path = Self::path(log);
+        let mut file = BufWriter::new(File::create(&path)?);
+
+        let columns = channels
+            .iter()
+            .map(|ch| {
+                let name = format!("{}({})", ch.name(), ch.unit.as_ref());
+                format!("{name} mean,{name} max")
+            })
+            .collect::<Vec<_>>();
+        writeln!(file, "Timestamp,Period,Samples,{}", columns.join(","))?;
+
+        Ok(Self {
+            file,
+            timestamp,
+            _lock: LogLock::acquire(&path)?,
+        })
+    }
+
+    /// Append `aggregate`, flushed right away as there is one row per period and minute
+    pub fn write(&mut self, aggregate: &Aggregate) -> Result<()> {
+        let values = aggregate
+            .data
+            .iter()
+            .map(|d| format!("{:.3},{}", d.mean, d.max))
+            .collect::<Vec<_>>();
+        writeln!(
+            self.file,
+            "{},{},{},{}",
+            self.timestamp.format(&aggregate.timestamp),
+            aggregate.period.as_ref(),
+            aggregate.count,
+            values.join(",")
+        )?;
+        self.file.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolling_means() {
+        let start = Local::now();
+        let mut aggregator = Aggregator::new();
+        let mut published = Vec::new();
+
+        // One sample every 10 s for 20 minutes, the value is the minute
+        for n in 0..=120 {
+            let sample =
+                SampleData::single(start, n * 10, SensorType::CO, (n / 6) as f32, Unit::PPM);
+            published.extend(aggregator.push(&sample));
+        }
+
+        // Once a minute, for every period
+        assert_eq!(published.len(), 20 * Period::ALL.len());
+
+        let last = &published[published.len() - 3..];
+        assert_eq!(last[0].period, Period::OneMinute);
+        assert_eq!(last[0].count, 6);
+        assert_eq!(last[0].data[0].max, 20.0);
+        assert_eq!(last[1].count, 90);
+        assert_eq!(last[2].count, 121);
+        assert_eq!(last[2].data[0].mean, 1160.0 / 121.0);
+    }
+}
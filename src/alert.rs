@@ -0,0 +1,364 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::convert::convert;
+use crate::logger::{LogLock, TimestampFormat};
+use crate::sensor::{SampleData, SensorChannel, SensorType, Unit};
+
+/// Alarm limits of one sensor type, converted to the unit of each channel of the type
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Threshold {
+    pub sensor_type: SensorType,
+    /// Unit of the limits, the one of the first channel of the type when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<Unit>,
+    pub low: Option<f32>,
+    pub high: Option<f32>,
+    /// How far a value has to come back within a limit to end a breach, so a noisy reading
+    /// near the limit doesn't alert on every sample
+    #[serde(default)]
+    pub hysteresis: f32,
+}
+
+/// Which side of a threshold a value is on
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Breach {
+    Above,
+    Below,
+}
+
+impl Threshold {
+    pub fn new(sensor_type: SensorType) -> Self {
+        Self {
+            sensor_type,
+            unit: None,
+            low: None,
+            high: None,
+            hysteresis: 0.0,
+        }
+    }
+
+    /// The limit a value is beyond on the `breach` side
+    pub fn limit(&self, breach: Breach) -> Option<f32> {
+        match breach {
+            Breach::Above => self.high,
+            Breach::Below => self.low,
+        }
+    }
+
+    /// `None` while the value is within the limits
+    pub fn check(&self, value: f32) -> Option<Breach> {
+        if self.high.is_some_and(|high| value > high) {
+            Some(Breach::Above)
+        } else if self.low.is_some_and(|low| value < low) {
+            Some(Breach::Below)
+        } else {
+            None
+        }
+    }
+
+    /// Like `check`, but a `current` breach only ends once the value is `hysteresis` within
+    /// the limit
+    pub fn check_from(&self, value: f32, current: Option<Breach>) -> Option<Breach> {
+        match current {
+            Some(Breach::Above) if self.high.is_some_and(|high| value > high - self.hysteresis) => {
+                Some(Breach::Above)
+            }
+            Some(Breach::Below) if self.low.is_some_and(|low| value < low + self.hysteresis) => {
+                Some(Breach::Below)
+            }
+            _ => self.check(value),
+        }
+    }
+
+    /// The limits converted to `unit`, `None` when there's no conversion between the units
+    pub fn in_unit(&self, unit: Unit) -> Option<Self> {
+        let from = self.unit.unwrap_or(unit);
+        let to = |v: f32| {
+            if from == unit {
+                Some(v)
+            } else {
+                convert(v, self.sensor_type, from, unit)
+            }
+        };
+
+        Some(Self {
+            unit: Some(unit),
+            low: match self.low {
+                Some(low) => Some(to(low)?),
+                None => None,
+            },
+            high: match self.high {
+                Some(high) => Some(to(high)?),
+                None => None,
+            },
+            hysteresis: to(self.hysteresis)?,
+            ..self.clone()
+        })
+    }
+}
+
+/// Threshold configured for a channel of type `ty` in `unit`, if any, with the limits in `unit`
+pub fn threshold_for(thresholds: &[Threshold], ty: SensorType, unit: Unit) -> Option<Threshold> {
+    thresholds
+        .iter()
+        .find(|t| t.sensor_type == ty)
+        .and_then(|t| t.in_unit(unit))
+}
+
+/// `thresholds` with the unit set where it isn't, to the one of the first of `channels` of
+/// their type
+pub fn with_units(thresholds: &[Threshold], channels: &[SensorChannel]) -> Vec<Threshold> {
+    thresholds
+        .iter()
+        .map(|t| Threshold {
+            unit: t.unit.or_else(|| {
+                channels
+                    .iter()
+                    .find(|ch| ch.sensor_type == t.sensor_type)
+                    .map(|ch| ch.unit)
+            }),
+            ..t.clone()
+        })
+        .collect()
+}
+
+/// A channel crossed one of its limits, or came back within them
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Alert {
+    pub timestamp: DateTime<Local>,
+    pub sensor_type: SensorType,
+    pub unit: Unit,
+    pub value: f32,
+    /// Side of the limit the value is now on, `None` once it's back within the limits
+    pub breach: Option<Breach>,
+    /// The limit crossed, `None` once back within the limits
+    pub limit: Option<f32>,
+    /// Start of the breach this crossing ends, when back within the limits or on the other side
+    #[serde(default)]
+    pub breached_since: Option<DateTime<Local>>,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self.breach {
+            Some(Breach::Above) => "above its high limit",
+            Some(Breach::Below) => "below its low limit",
+            None => "back within its limits",
+        };
+        write!(
+            f,
+            "{} {state}: {:.2} {}",
+            self.sensor_type.as_ref(),
+            self.value,
+            self.unit.as_ref()
+        )
+    }
+}
+
+/// Turns samples into alerts, one when a channel leaves its limits and one when it returns
+pub struct AlertMonitor {
+    thresholds: Vec<Threshold>,
+    /// Current breach of each channel and since when
+    state: Vec<Option<(Breach, DateTime<Local>)>>,
+}
+
+impl AlertMonitor {
+    pub fn new(thresholds: Vec<Threshold>) -> Self {
+        Self {
+            thresholds,
+            state: Vec::new(),
+        }
+    }
+
+    pub fn check(&mut self, sample: &SampleData) -> Vec<Alert> {
+        if self.state.len() != sample.data.len() {
+            let channels: Vec<_> = sample.data.iter().map(|d| d.channel()).collect();
+            self.thresholds = with_units(&self.thresholds, &channels);
+        }
+        self.state.resize(sample.data.len(), None);
+
+        let mut alerts = Vec::new();
+        for (d, state) in sample.data.iter().zip(&mut self.state) {
+            let threshold = threshold_for(&self.thresholds, d.ty, d.unit);
+            let current = state.map(|(b, _)| b);
+            let breach = threshold
+                .as_ref()
+                .and_then(|t| t.check_from(d.value, current));
+            if breach != current {
+                let breached_since = state.map(|(_, since)| since);
+                *state = breach.map(|b| (b, sample.timestamp));
+                alerts.push(Alert {
+                    timestamp: sample.timestamp,
+                    sensor_type: d.ty,
+                    unit: d.unit,
+                    value: d.value,
+                    breach,
+                    limit: threshold.zip(breach).and_then(|(t, b)| t.limit(b)),
+                    breached_since,
+                });
+            }
+        }
+
+        alerts
+    }
+}
+
+/// CSV of the threshold crossings next to a sample log, for compliance reports
+pub struct EventsCsv {
+    file: BufWriter<File>,
+    timestamp: TimestampFormat,
+    /// Keeps `apply_retention` of other sessions away
+    _lock: LogLock,
+}
+
+impl EventsCsv {
+    /// "<log stem>_events.csv" next to `log`
+    pub fn path(log: &Path) -> PathBuf {
+        let stem = log.file_stem().unwrap_or_default().to_string_lossy();
+        log.with_file_name(format!("{stem}_events.csv"))
+    }
+
+    pub fn create(log: &Path, timestamp: TimestampFormat) -> Result<Self> {
+        let path = Self::path(log);
+        let mut file = BufWriter::new(File::create(&path)?);
+        writeln!(
+            file,
+            "Timestamp,Channel,Unit,Crossing,Value,Limit,Exceedance(s)"
+        )?;
+
+        Ok(Self {
+            file,
+            timestamp,
+            _lock: LogLock::acquire(&path)?,
+        })
+    }
+
+    /// Append `alert`, with the length of the breach it ends if any
+    pub fn write(&mut self, alert: &Alert) -> Result<()> {
+        let crossing = match alert.breach {
+            Some(Breach::Above) => "Above",
+            Some(Breach::Below) => "Below",
+            None => "Within",
+        };
+        let limit = alert.limit.map(|l| l.to_string()).unwrap_or_default();
+        let exceedance = alert
+            .breached_since
+            .map(|since| format!("{:.1}", (alert.timestamp - since).as_seconds_f64()))
+            .unwrap_or_default();
+        writeln!(
+            self.file,
+            "{},{},{},{crossing},{},{limit},{exceedance}",
+            self.timestamp.format(&alert.timestamp),
+            alert.sensor_type.as_ref(),
+            alert.unit.as_ref(),
+            alert.value
+        )?;
+        self.file.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaches() {
+        let threshold = Threshold {
+            high: Some(50.0),
+            ..Threshold::new(SensorType::CO)
+        };
+
+        assert_eq!(threshold.check(50.0), None);
+        assert_eq!(threshold.check(50.5), Some(Breach::Above));
+        assert_eq!(threshold.check(-1.0), None);
+
+        let threshold = Threshold {
+            low: Some(18.0),
+            ..threshold
+        };
+        assert_eq!(threshold.check(17.0), Some(Breach::Below));
+    }
+
+    #[test]
+    fn hysteresis() {
+        let threshold = Threshold {
+            high: Some(50.0),
+            hysteresis: 2.0,
+            ..Threshold::new(SensorType::CO)
+        };
+
+        assert_eq!(threshold.check_from(51.0, None), Some(Breach::Above));
+        assert_eq!(
+            threshold.check_from(49.0, Some(Breach::Above)),
+            Some(Breach::Above)
+        );
+        assert_eq!(threshold.check_from(47.5, Some(Breach::Above)), None);
+        assert_eq!(threshold.check_from(49.0, None), None);
+    }
+
+    #[test]
+    fn alerts_on_change_only() {
+        let mut monitor = AlertMonitor::new(vec![Threshold {
+            high: Some(50.0),
+            ..Threshold::new(SensorType::CO)
+        }]);
+        let sample = |value| SampleData::single(Local::now(), 0, SensorType::CO, value, Unit::PPM);
+
+        let breaches = [10.0, 60.0, 70.0, 40.0].map(|v| {
+            monitor
+                .check(&sample(v))
+                .iter()
+                .map(|a| (a.breach, a.limit, a.breached_since.is_some()))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(
+            breaches,
+            [
+                vec![],
+                vec![(Some(Breach::Above), Some(50.0), false)],
+                vec![],
+                vec![(None, None, true)]
+            ]
+        );
+    }
+
+    #[test]
+    fn limits_in_channel_unit() {
+        let thresholds = [Threshold {
+            unit: Some(Unit::PPM),
+            high: Some(50.0),
+            ..Threshold::new(SensorType::CO)
+        }];
+
+        let ppm = threshold_for(&thresholds, SensorType::CO, Unit::PPM).unwrap();
+        assert_eq!(ppm.high, Some(50.0));
+        let mg = threshold_for(&thresholds, SensorType::CO, Unit::MgPerM3).unwrap();
+        assert_eq!(
+            mg.high,
+            convert(50.0, SensorType::CO, Unit::PPM, Unit::MgPerM3)
+        );
+        assert!(threshold_for(&thresholds, SensorType::CO, Unit::Celsius).is_none());
+
+        // Without a unit, in the one of the first channel of the type
+        let thresholds = with_units(
+            &[Threshold {
+                high: Some(50.0),
+                ..Threshold::new(SensorType::CO)
+            }],
+            &[
+                SensorChannel::new(SensorType::CO, Unit::MgPerM3),
+                SensorChannel::new(SensorType::CO, Unit::PPM),
+            ],
+        );
+        assert_eq!(thresholds[0].unit, Some(Unit::MgPerM3));
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::sensor::{SampleData, SensorType, Unit};
+
+/// Detection of unusual readings against the recent behaviour of each channel
+///
+/// A value is anomalous when it is more than `sigmas` standard deviations away from the mean
+/// of the preceding `window` valid samples of its channel. Unlike thresholds this needs no
+/// limits and catches events such as sudden PM bursts at any level. Channels that were
+/// constant over the window aren't checked.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct AnomalyConfig {
+    pub window: usize,
+    pub sigmas: f32,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self {
+            // Five minutes at the default poll interval
+            window: 300,
+            sigmas: 4.0,
+        }
+    }
+}
+
+/// A channel left its usual range
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Anomaly {
+    pub timestamp: DateTime<Local>,
+    pub sensor_type: SensorType,
+    pub unit: Unit,
+    pub value: f32,
+    /// Mean of the window before the value
+    pub mean: f32,
+    /// Standard deviation of the window before the value
+    pub stddev: f32,
+}
+
+impl Anomaly {
+    /// Signed distance from the mean in standard deviations
+    pub fn z_score(&self) -> f32 {
+        (self.value - self.mean) / self.stddev
+    }
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let z = self.z_score();
+        write!(
+            f,
+            "Unusual {} reading: {:.2} {} is {:.1}σ {} the recent mean of {:.2}",
+            self.sensor_type.as_ref(),
+            self.value,
+            self.unit.as_ref(),
+            z.abs(),
+            if z > 0.0 { "above" } else { "below" },
+            self.mean
+        )
+    }
+}
+
+/// Rolling window of one channel with running sums
+#[derive(Default)]
+struct Baseline {
+    values: VecDeque<f32>,
+    sum: f64,
+    sum_sq: f64,
+    /// Reported already, quiet until the channel is back within the limit
+    flagged: bool,
+}
+
+impl Baseline {
+    fn push(&mut self, value: f32, window: usize) {
+        self.values.push_back(value);
+        self.sum += value as f64;
+        self.sum_sq += (value as f64).powi(2);
+        while self.values.len() > window {
+            let old = self.values.pop_front().unwrap_or_default() as f64;
+            self.sum -= old;
+            self.sum_sq -= old * old;
+        }
+    }
+
+    /// Mean and standard deviation
+    fn stats(&self) -> (f32, f32) {
+        let n = self.values.len() as f64;
+        let mean = self.sum / n;
+        let variance = (self.sum_sq / n - mean * mean).max(0.0);
+        (mean as f32, variance.sqrt() as f32)
+    }
+}
+
+/// Turns samples into anomalies, one when a channel leaves its usual range
+pub struct AnomalyMonitor {
+    config: AnomalyConfig,
+    baselines: Vec<Baseline>,
+}
+
+impl AnomalyMonitor {
+    pub fn new(config: AnomalyConfig) -> Self {
+        Self {
+            config,
+            baselines: Vec::new(),
+        }
+    }
+
+    pub fn check(&mut self, sample: &SampleData) -> Vec<Anomaly> {
+        let window = self.config.window.max(2);
+        self.baselines
+            .resize_with(sample.data.len(), Baseline::default);
+
+        let mut anomalies = Vec::new();
+        for (d, baseline) in sample.data.iter().zip(&mut self.baselines) {
+            // Warm-up, interpolated, out of range or NaN values neither trigger nor teach, one
+            // NaN in the running sums would silence the channel for good
+            if !d.quality.is_valid() || !d.value.is_finite() {
+                continue;
+            }
+
+            if baseline.values.len() == window {
+                let (mean, stddev) = baseline.stats();
+                let anomalous =
+                    stddev > f32::EPSILON && (d.value - mean).abs() > self.config.sigmas * stddev;
+                if anomalous && !baseline.flagged {
+                    anomalies.push(Anomaly {
+                        timestamp: sample.timestamp,
+                        sensor_type: d.ty,
+                        unit: d.unit,
+                        value: d.value,
+                        mean,
+                        stddev,
+                    });
+                }
+                baseline.flagged = anomalous;
+            }
+            baseline.push(d.value, window);
+        }
+
+        anomalies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_is_reported_once() {
+        let mut monitor = AnomalyMonitor::new(AnomalyConfig {
+            window: 20,
+            sigmas: 4.0,
+        });
+        let start = Local::now();
+
+        // Noise of ±1 around 10, a NaN reading, then a burst to 40 for three samples
+        let values = (0..40).map(|n| if n % 2 == 0 { 9.0 } else { 11.0 }).chain([
+            f32::NAN,
+            40.0,
+            45.0,
+            42.0,
+            10.0,
+            11.0,
+        ]);
+        let anomalies = values
+            .enumerate()
+            .flat_map(|(n, value)| {
+                monitor.check(&SampleData::single(
+                    start,
+                    n as i64,
+                    SensorType::PM2_5,
+                    value,
+                    Unit::UgPerM3,
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].value, 40.0);
+        assert!((anomalies[0].z_score() - 30.0).abs() < 1e-3);
+    }
+}
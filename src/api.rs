@@ -0,0 +1,290 @@
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use anyhow::{Result, anyhow};
+use bus::Bus;
+use serde::Serialize;
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::config::Config;
+use crate::history::{DEFAULT_CAPACITY, History};
+use crate::sensor::{AppMsg, Sensor, SensorModel};
+use crate::serial_port_list;
+use crate::session::parse_timestamp;
+
+/// REST API settings
+#[derive(Clone, Debug)]
+pub struct ApiConfig {
+    pub addr: String,
+    /// Number of samples kept for /history
+    pub history: usize,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            addr: "0.0.0.0:8080".to_string(),
+            history: DEFAULT_CAPACITY,
+        }
+    }
+}
+
+struct Running {
+    sensor: Sensor,
+    model: SensorModel,
+    port: String,
+}
+
+struct ApiState {
+    config: Config,
+    running: Option<Running>,
+    history: History,
+    status: String,
+}
+
+type JsonResponse = (u16, serde_json::Value);
+
+/// Live view of the readings for browsers on the LAN, served at /
+const DASHBOARD: &str = include_str!("../asset/dashboard.html");
+
+/// Split "a=1&b=2" into a map, undoing percent-encoding
+///
+/// '+' is kept as is so unencoded RFC 3339 offsets still parse
+fn parse_query(url: &str) -> HashMap<String, String> {
+    let Some((_, query)) = url.split_once('?') else {
+        return HashMap::new();
+    };
+
+    query
+        .split('&')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| (percent_decode(k), percent_decode(v)))
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn to_json<T: Serialize>(value: T) -> serde_json::Value {
+    serde_json::to_value(value).unwrap_or_default()
+}
+
+fn error(code: u16, msg: impl std::fmt::Display) -> JsonResponse {
+    (code, json!({ "error": msg.to_string() }))
+}
+
+impl ApiState {
+    /// Pull everything the sensor thread has sent so far
+    fn drain(&mut self) {
+        let Some(running) = &mut self.running else {
+            return;
+        };
+        // Checked first, so whatever the sensor sent before it stopped is drained below
+        let stopped = running.sensor.is_stopped();
+
+        while let Some(msg) = running.sensor.try_recv() {
+            match msg {
+                AppMsg::Status(s) | AppMsg::Error(s) => self.status = s,
+                AppMsg::Alert(alert) => self.status = alert.to_string(),
+                AppMsg::Anomaly(anomaly) => self.status = anomaly.to_string(),
+                AppMsg::Traffic(_) | AppMsg::Marker(_) | AppMsg::Aggregate(_) => {}
+                AppMsg::Sample(sample) => self.history.push(sample),
+            }
+        }
+
+        // Ended by itself, on an error or at the end of a timed run, so it can be started again
+        if stopped {
+            self.running = None;
+        }
+    }
+
+    fn sensors(&self) -> JsonResponse {
+        (
+            200,
+            json!({
+                "models": SensorModel::all(),
+                "ports": serial_port_list(),
+                "running": self.running.as_ref().map(|r| json!({
+                    "model": r.model,
+                    "port": r.port,
+                })),
+                "status": self.status,
+                // The dashboard streams samples from here, when set
+                "ws": self.config.ws.as_ref().map(|ws| &ws.addr),
+            }),
+        )
+    }
+
+    fn latest(&self) -> JsonResponse {
+        match self.history.latest() {
+            Some(sample) => (200, to_json(sample)),
+            None => error(404, "No sample yet"),
+        }
+    }
+
+    fn history(&self, query: &HashMap<String, String>) -> JsonResponse {
+        let bound = |key| query.get(key).map(|s| parse_timestamp(s)).transpose();
+
+        match (bound("from"), bound("to")) {
+            (Ok(from), Ok(to)) => (
+                200,
+                to_json(self.history.range(from, to).collect::<Vec<_>>()),
+            ),
+            (Err(e), _) | (_, Err(e)) => error(400, e),
+        }
+    }
+
+    fn start(&mut self, query: &HashMap<String, String>) -> JsonResponse {
+        if self.running.is_some() {
+            return error(409, "A sensor is already running");
+        }
+
+        let model = match query.get("model").map(|m| SensorModel::from_str(m)) {
+            Some(Ok(model)) => model,
+            Some(Err(_)) => return error(400, "Unknown model"),
+            None => return error(400, "Missing model"),
+        };
+        let Some(port) = query.get("port") else {
+            return error(400, "Missing port");
+        };
+
+        let mut bus = Bus::new(10);
+        let rx = bus.add_rx();
+
+        let result = Sensor::new(&model, port, rx, self.config.clone())
+            .and_then(|sensor| sensor.start(bus).map(|_| sensor));
+
+        match result {
+            Ok(sensor) => {
+                self.history.clear();
+                self.running = Some(Running {
+                    sensor,
+                    model,
+                    port: port.clone(),
+                });
+                (200, json!({ "started": model }))
+            }
+            Err(e) => error(500, e),
+        }
+    }
+
+    fn stop(&mut self) -> JsonResponse {
+        match self.running.take() {
+            Some(r) => {
+                r.sensor.stop();
+                (200, json!({ "stopped": r.model }))
+            }
+            None => error(409, "No sensor is running"),
+        }
+    }
+
+    fn mark(&mut self, query: &HashMap<String, String>) -> JsonResponse {
+        let Some(label) = query.get("label").filter(|l| !l.trim().is_empty()) else {
+            return error(400, "Missing label");
+        };
+
+        match &self.running {
+            Some(r) => {
+                r.sensor.mark(label);
+                (200, json!({ "marked": label }))
+            }
+            None => error(409, "No sensor is running"),
+        }
+    }
+
+    fn handle(&mut self, request: Request) -> Result<()> {
+        let url = request.url().to_string();
+        let path = url.split('?').next().unwrap_or_default();
+        let query = parse_query(&url);
+
+        if request.method() == &Method::Get && path == "/" {
+            let content_type =
+                Header::from_bytes("Content-Type", "text/html; charset=utf-8").unwrap();
+            request.respond(Response::from_string(DASHBOARD).with_header(content_type))?;
+            return Ok(());
+        }
+
+        let (code, body) = match (request.method(), path) {
+            (Method::Get, "/sensors") => self.sensors(),
+            (Method::Get, "/latest") => self.latest(),
+            (Method::Get, "/history") => self.history(&query),
+            (Method::Post, "/start") => self.start(&query),
+            (Method::Post, "/stop") => self.stop(),
+            (Method::Post, "/mark") => self.mark(&query),
+            _ => error(404, "Not found"),
+        };
+
+        let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+        request.respond(
+            Response::from_string(body.to_string())
+                .with_status_code(code)
+                .with_header(content_type),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Serve the REST API until the process exits, sensors are started with `config`
+pub fn serve(api: ApiConfig, config: Config) -> Result<()> {
+    let server = Server::http(&api.addr).map_err(|e| anyhow!(e))?;
+
+    let mut state = ApiState {
+        config,
+        running: None,
+        history: History::new(api.history),
+        status: String::from("Ready"),
+    };
+
+    loop {
+        // A client that hangs up mid-request mustn't take the server down with it
+        match server.recv_timeout(Duration::from_millis(100)) {
+            Ok(Some(request)) => {
+                if let Err(e) = state.handle(request) {
+                    eprintln!("API client error: {e}");
+                }
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("API receive error: {e}"),
+        }
+
+        state.drain();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_string() {
+        let query = parse_query("/history?from=2025-11-25T13%3A00%3A00%2B01%3A00&to=x");
+
+        assert_eq!(query["from"], "2025-11-25T13:00:00+01:00");
+        assert_eq!(query["to"], "x");
+        assert!(parse_query("/history").is_empty());
+    }
+}
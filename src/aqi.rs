@@ -0,0 +1,319 @@
+use serde::Serialize;
+
+use crate::convert::convert;
+use crate::history::History;
+use crate::sensor::{SensorData, SensorType, Unit};
+
+/// Hourly means weighed by the NowCast
+const NOWCAST_HOURS: usize = 12;
+
+/// Smallest NowCast weight factor of particulate matter
+const NOWCAST_MIN_WEIGHT: f32 = 0.5;
+
+/// US EPA air quality category
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum Category {
+    Good,
+    Moderate,
+    UnhealthyForSensitive,
+    Unhealthy,
+    VeryUnhealthy,
+    Hazardous,
+}
+
+impl Category {
+    pub const ALL: [Category; 6] = [
+        Category::Good,
+        Category::Moderate,
+        Category::UnhealthyForSensitive,
+        Category::Unhealthy,
+        Category::VeryUnhealthy,
+        Category::Hazardous,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Category::Good => "Good",
+            Category::Moderate => "Moderate",
+            Category::UnhealthyForSensitive => "Unhealthy for sensitive groups",
+            Category::Unhealthy => "Unhealthy",
+            Category::VeryUnhealthy => "Very unhealthy",
+            Category::Hazardous => "Hazardous",
+        }
+    }
+
+    /// The official color of the category
+    pub fn rgb(&self) -> [u8; 3] {
+        match self {
+            Category::Good => [0, 228, 0],
+            Category::Moderate => [255, 255, 0],
+            Category::UnhealthyForSensitive => [255, 126, 0],
+            Category::Unhealthy => [255, 0, 0],
+            Category::VeryUnhealthy => [143, 63, 151],
+            Category::Hazardous => [126, 0, 35],
+        }
+    }
+
+    /// Index range of the category
+    pub fn range(&self) -> (u32, u32) {
+        INDEX[*self as usize]
+    }
+
+    fn of(index: u32) -> Self {
+        Category::ALL
+            .into_iter()
+            .find(|c| index <= c.range().1)
+            .unwrap_or(Category::Hazardous)
+    }
+}
+
+const INDEX: [(u32, u32); 6] = [
+    (0, 50),
+    (51, 100),
+    (101, 150),
+    (151, 200),
+    (201, 300),
+    (301, 500),
+];
+
+/// Concentration breakpoints of a pollutant
+struct Table {
+    /// Unit the breakpoints are given in
+    unit: Unit,
+    /// Concentrations are truncated to this many decimals first
+    decimals: i32,
+    /// Concentration range of each category
+    breakpoints: [(f32, f32); 6],
+}
+
+/// PM1, particle counts and the weather channels have no index
+fn table(ty: SensorType) -> Option<Table> {
+    let (unit, decimals, breakpoints) = match ty {
+        SensorType::PM2_5 => (
+            Unit::UgPerM3,
+            1,
+            [
+                (0.0, 9.0),
+                (9.1, 35.4),
+                (35.5, 55.4),
+                (55.5, 125.4),
+                (125.5, 225.4),
+                (225.5, 325.4),
+            ],
+        ),
+        SensorType::PM10 => (
+            Unit::UgPerM3,
+            0,
+            [
+                (0.0, 54.0),
+                (55.0, 154.0),
+                (155.0, 254.0),
+                (255.0, 354.0),
+                (355.0, 424.0),
+                (425.0, 604.0),
+            ],
+        ),
+        SensorType::CO => (
+            Unit::PPM,
+            1,
+            [
+                (0.0, 4.4),
+                (4.5, 9.4),
+                (9.5, 12.4),
+                (12.5, 15.4),
+                (15.5, 30.4),
+                (30.5, 50.4),
+            ],
+        ),
+        SensorType::NO2 => (
+            Unit::PPB,
+            0,
+            [
+                (0.0, 53.0),
+                (54.0, 100.0),
+                (101.0, 360.0),
+                (361.0, 649.0),
+                (650.0, 1249.0),
+                (1250.0, 2049.0),
+            ],
+        ),
+        SensorType::PM1
+        | SensorType::Temperature
+        | SensorType::Humidity
+        | SensorType::DewPoint
+        | SensorType::AbsoluteHumidity
+        | SensorType::PM1Dry
+        | SensorType::PM2_5Dry
+        | SensorType::PM10Dry
+        | SensorType::ParticleCount => return None,
+    };
+
+    Some(Table {
+        unit,
+        decimals,
+        breakpoints,
+    })
+}
+
+/// Index of one pollutant, `None` for pollutants without an index or unconvertible units
+///
+/// Concentrations beyond the table count as the top of the scale.
+pub fn sub_index(ty: SensorType, value: f32, unit: Unit) -> Option<u32> {
+    let table = table(ty)?;
+    let factor = 10_f32.powi(table.decimals);
+    let c = (convert(value, ty, unit, table.unit)? * factor).trunc() / factor;
+
+    let Some((n, &(lo, hi))) = table
+        .breakpoints
+        .iter()
+        .enumerate()
+        .find(|(_, (_, hi))| c <= *hi)
+    else {
+        return Some(500);
+    };
+    let (i_lo, i_hi) = INDEX[n];
+    let c = c.max(lo);
+
+    Some((((i_hi - i_lo) as f32 / (hi - lo)) * (c - lo) + i_lo as f32).round() as u32)
+}
+
+/// Air quality index, the highest of its pollutants
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Aqi {
+    pub index: u32,
+    pub category: Category,
+    /// Pollutant responsible for the index
+    pub pollutant: SensorType,
+}
+
+/// The highest of the sub-indices of `pollutants`
+fn highest(pollutants: impl Iterator<Item = (u32, SensorType)>) -> Option<Aqi> {
+    pollutants
+        .max_by_key(|(index, _)| *index)
+        .map(|(index, pollutant)| Aqi {
+            index,
+            category: Category::of(index),
+            pollutant,
+        })
+}
+
+/// Index of a sample from its instantaneous values
+pub fn aqi(data: &[SensorData]) -> Option<Aqi> {
+    highest(
+        data.iter()
+            .filter_map(|d| Some((sub_index(d.ty, d.value, d.unit)?, d.ty))),
+    )
+}
+
+/// EPA NowCast of hourly mean concentrations, `hourly[0]` is the most recent hour
+///
+/// Hours without data are `None`, two of the three most recent ones are needed.
+pub fn nowcast(hourly: &[Option<f32>]) -> Option<f32> {
+    let hourly = &hourly[..hourly.len().min(NOWCAST_HOURS)];
+    if hourly.iter().take(3).flatten().count() < 2 {
+        return None;
+    }
+
+    let (min, max) = hourly
+        .iter()
+        .flatten()
+        .fold((f32::MAX, f32::MIN), |(lo, hi), &c| (lo.min(c), hi.max(c)));
+    let weight = if max > 0.0 {
+        (min / max).max(NOWCAST_MIN_WEIGHT)
+    } else {
+        1.0
+    };
+
+    let (sum, weights) = hourly
+        .iter()
+        .enumerate()
+        .filter_map(|(n, c)| Some((weight.powi(n as i32), (*c)?)))
+        .fold((0.0, 0.0), |(sum, weights), (w, c)| {
+            (sum + w * c, weights + w)
+        });
+    Some(sum / weights)
+}
+
+/// Mean of channel `idx` in each of the `NOWCAST_HOURS` hours up to the newest sample
+fn hourly_means(history: &History, idx: usize) -> Vec<Option<f32>> {
+    let mut sums = [(0.0, 0); NOWCAST_HOURS];
+    if let Some(latest) = history.latest() {
+        for s in history.iter().rev() {
+            let hour = ((latest.timestamp - s.timestamp).num_seconds() / 3600) as usize;
+            let (Some((sum, count)), Some(d)) = (sums.get_mut(hour), s.data.get(idx)) else {
+                break;
+            };
+            *sum += d.value;
+            *count += 1;
+        }
+    }
+
+    sums.iter()
+        .map(|&(sum, count)| (count > 0).then(|| sum / count as f32))
+        .collect()
+}
+
+/// Index as official apps report it, NowCast for particulate matter over the last hours of
+/// `history` and instantaneous values for the gases
+///
+/// `None` until a particulate channel has enough hours for its NowCast.
+pub fn nowcast_aqi(history: &History) -> Option<Aqi> {
+    let latest = history.latest()?;
+    let particulate = latest
+        .data
+        .iter()
+        .enumerate()
+        .filter(|(_, d)| matches!(d.ty, SensorType::PM2_5 | SensorType::PM10))
+        .filter_map(|(idx, d)| {
+            let c = nowcast(&hourly_means(history, idx))?;
+            Some((sub_index(d.ty, c, d.unit)?, d.ty))
+        })
+        .collect::<Vec<_>>();
+    if particulate.is_empty() {
+        return None;
+    }
+
+    let gases = latest
+        .data
+        .iter()
+        .filter(|d| !matches!(d.ty, SensorType::PM2_5 | SensorType::PM10))
+        .filter_map(|d| Some((sub_index(d.ty, d.value, d.unit)?, d.ty)));
+    highest(particulate.into_iter().chain(gases))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epa_sub_indices() {
+        assert_eq!(sub_index(SensorType::PM2_5, 9.0, Unit::UgPerM3), Some(50));
+        assert_eq!(
+            sub_index(SensorType::PM2_5, 35.49, Unit::UgPerM3),
+            Some(100)
+        );
+        assert_eq!(sub_index(SensorType::PM10, 154.0, Unit::UgPerM3), Some(100));
+        assert_eq!(sub_index(SensorType::NO2, 100.0, Unit::PPB), Some(100));
+        assert_eq!(sub_index(SensorType::CO, 60.0, Unit::PPM), Some(500));
+        assert_eq!(sub_index(SensorType::PM1, 10.0, Unit::UgPerM3), None);
+
+        let aqi = aqi(&[
+            SensorData::new(SensorType::PM2_5, 40.0, Unit::UgPerM3),
+            SensorData::new(SensorType::PM10, 20.0, Unit::UgPerM3),
+        ])
+        .unwrap();
+        assert_eq!(aqi.pollutant, SensorType::PM2_5);
+        assert_eq!(aqi.category, Category::UnhealthyForSensitive);
+    }
+
+    #[test]
+    fn nowcast_weighting() {
+        // Steady air averages plainly
+        assert_eq!(nowcast(&[Some(10.0); 12]), Some(10.0));
+        // A doubling weighs the recent hours with 0.5^n
+        let rising = nowcast(&[Some(20.0), Some(10.0), None]).unwrap();
+        assert!((rising - (20.0 + 0.5 * 10.0) / 1.5).abs() < 1e-4);
+        // Two of the three latest hours are needed
+        assert_eq!(nowcast(&[Some(20.0), None, None, Some(10.0)]), None);
+    }
+}
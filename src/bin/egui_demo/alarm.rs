@@ -0,0 +1,72 @@
+#[cfg(feature = "sound")]
+use std::{
+    sync::mpsc::{self, Sender},
+    thread,
+    time::Duration,
+};
+
+#[cfg(feature = "sound")]
+use rodio::{OutputStream, OutputStreamBuilder, Source, source::SineWave};
+
+#[cfg(feature = "sound")]
+const BEEPS: u32 = 3;
+#[cfg(feature = "sound")]
+const BEEP_LENGTH: Duration = Duration::from_millis(200);
+#[cfg(feature = "sound")]
+const BEEP_PERIOD: Duration = Duration::from_millis(350);
+
+/// Short beep pattern played on the default audio output, silent without the `sound` feature
+pub struct Alarm {
+    #[cfg(feature = "sound")]
+    tx: Sender<()>,
+}
+
+impl Alarm {
+    /// Whether this build can beep at all
+    pub const AVAILABLE: bool = cfg!(feature = "sound");
+
+    /// Start the playback thread, the output device is opened on the first beep
+    #[cfg(feature = "sound")]
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel::<()>();
+
+        thread::spawn(move || {
+            let mut stream: Option<OutputStream> = None;
+
+            for () in rx {
+                if stream.is_none() {
+                    match OutputStreamBuilder::open_default_stream() {
+                        Ok(mut s) => {
+                            s.log_on_drop(false);
+                            stream = Some(s);
+                        }
+                        Err(e) => eprintln!("Failed to open audio output: {e}"),
+                    }
+                }
+
+                if let Some(stream) = &stream {
+                    for n in 0..BEEPS {
+                        stream.mixer().add(
+                            SineWave::new(880.0)
+                                .take_duration(BEEP_LENGTH)
+                                .amplify(0.3)
+                                .delay(BEEP_PERIOD * n),
+                        );
+                    }
+                }
+            }
+        });
+
+        Self { tx }
+    }
+
+    #[cfg(not(feature = "sound"))]
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn beep(&self) {
+        #[cfg(feature = "sound")]
+        let _ = self.tx.send(());
+    }
+}
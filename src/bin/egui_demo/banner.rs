@@ -0,0 +1,47 @@
+use egui::{Color32, Frame, RichText, Stroke};
+
+use envsensor_demo::{gui::plot::color32, i18n::tr, theme};
+
+/// What the user chose on an error banner
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BannerAction {
+    Retry,
+    Dismiss,
+}
+
+const BANNER_COLOR: Color32 = color32(theme::ALERT);
+
+/// Full width banner with an error of `source`, the retry button only shows when `can_retry`
+pub fn error_banner(
+    ui: &mut egui::Ui,
+    source: &str,
+    error: &str,
+    can_retry: bool,
+) -> Option<BannerAction> {
+    Frame::new()
+        .fill(BANNER_COLOR.gamma_multiply(0.2))
+        .stroke(Stroke::new(1.0, BANNER_COLOR))
+        .corner_radius(4)
+        .inner_margin(6)
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("⚠").color(BANNER_COLOR).size(20.0));
+                ui.label(RichText::new(source).strong());
+                ui.label(error);
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("✕").on_hover_text(tr("Dismiss")).clicked() {
+                        return Some(BannerAction::Dismiss);
+                    }
+                    if can_retry && ui.button(tr("⟳ Retry")).clicked() {
+                        return Some(BannerAction::Retry);
+                    }
+                    None
+                })
+                .inner
+            })
+            .inner
+        })
+        .inner
+}
@@ -0,0 +1,210 @@
+use chrono::TimeDelta;
+use egui::{ComboBox, DragValue, Grid};
+
+use envsensor_demo::{
+    calibration::{Calibration, ChannelCalibration, LinearFit},
+    gui::plot::channel_picker,
+    i18n::tr,
+    sensor::SensorType,
+    station::Station,
+};
+
+#[derive(Clone, Copy, Default, PartialEq)]
+enum Step {
+    /// Pick the device under test and the reference
+    #[default]
+    Sensors,
+    /// Pick the time window and fit
+    Window,
+    /// Review and save the fit
+    Result,
+}
+
+/// Guided calibration of one channel against a reference sensor running at the same time
+pub struct CalibrationWizard {
+    step: Step,
+    /// Station and channel of the device under test
+    device: (usize, usize),
+    /// Station and channel of the reference
+    reference: (usize, usize),
+    /// Newest minutes of samples the fit is made over
+    minutes: u32,
+    fit: Option<LinearFit>,
+}
+
+impl Default for CalibrationWizard {
+    fn default() -> Self {
+        Self {
+            step: Step::default(),
+            device: (0, 0),
+            reference: (1, 0),
+            minutes: 30,
+            fit: None,
+        }
+    }
+}
+
+fn station_picker(ui: &mut egui::Ui, id: &str, stations: &[Station], idx: &mut usize) {
+    ComboBox::from_id_salt(id)
+        .selected_text(stations.get(*idx).map_or("", |s| s.name.as_str()))
+        .show_ui(ui, |ui| {
+            for (n, station) in stations.iter().enumerate() {
+                ui.selectable_value(idx, n, &station.name);
+            }
+        });
+}
+
+impl CalibrationWizard {
+    /// Type of the channel being calibrated, when both sides measure the same quantity
+    fn sensor_type(&self, stations: &[Station]) -> Option<SensorType> {
+        let channel = |(station, idx): (usize, usize)| stations.get(station)?.channels.get(idx);
+        let (device, reference) = (channel(self.device)?, channel(self.reference)?);
+        (self.device.0 != self.reference.0
+            && device.sensor_type == reference.sensor_type
+            && device.unit == reference.unit)
+            .then_some(device.sensor_type)
+    }
+
+    /// Show the current step, returns the new calibration of the channel once saved
+    ///
+    /// The fit is made on values already corrected by the device's entry in `calibration`,
+    /// the result replaces it.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        stations: &[Station],
+        calibration: &[ChannelCalibration],
+    ) -> Option<ChannelCalibration> {
+        if stations.len() < 2 {
+            ui.label(tr(
+                "Start the device under test and a reference sensor to calibrate",
+            ));
+            return None;
+        }
+        // A sensor was stopped or removed in between
+        if self.step != Step::Sensors && self.sensor_type(stations).is_none() {
+            self.step = Step::Sensors;
+        }
+
+        let mut saved = None;
+        match self.step {
+            Step::Sensors => {
+                Grid::new("calibration_sensors").show(ui, |ui| {
+                    for (id, label, (station, idx)) in [
+                        ("device", tr("Device under test"), &mut self.device),
+                        ("reference", tr("Reference"), &mut self.reference),
+                    ] {
+                        ui.label(label);
+                        station_picker(ui, &format!("{id}_station"), stations, station);
+                        let channels = &stations[(*station).min(stations.len() - 1)].channels;
+                        channel_picker(ui, &format!("{id}_channel"), channels, idx);
+                        ui.end_row();
+                    }
+                });
+
+                let matching = self.sensor_type(stations).is_some();
+                if !matching {
+                    ui.label(tr(
+                        "Pick two sensors measuring the same quantity in the same unit",
+                    ));
+                }
+                if ui
+                    .add_enabled(matching, egui::Button::new(tr("Next")))
+                    .clicked()
+                {
+                    self.step = Step::Window;
+                }
+            }
+            Step::Window => {
+                ui.horizontal(|ui| {
+                    ui.label(tr("Fit over the last"));
+                    ui.add(
+                        DragValue::new(&mut self.minutes)
+                            .range(1..=24 * 60)
+                            .suffix(" min"),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Back")).clicked() {
+                        self.step = Step::Sensors;
+                    }
+                    if ui.button(tr("Fit")).clicked() {
+                        let device = &stations[self.device.0];
+                        let reference = &stations[self.reference.0];
+                        let from = device
+                            .history
+                            .latest()
+                            .map(|s| s.timestamp - TimeDelta::minutes(self.minutes.into()));
+                        self.fit = LinearFit::between(
+                            &device.history,
+                            self.device.1,
+                            &reference.history,
+                            self.reference.1,
+                            from,
+                        );
+                        self.step = Step::Result;
+                    }
+                });
+            }
+            Step::Result => {
+                let Some(fit) = self.fit else {
+                    ui.label(tr(
+                        "Not enough overlapping samples with a varying value to fit",
+                    ));
+                    if ui.button(tr("Back")).clicked() {
+                        self.step = Step::Window;
+                    }
+                    return None;
+                };
+
+                Grid::new("calibration_fit").show(ui, |ui| {
+                    ui.label(tr("Slope"));
+                    ui.monospace(format!("{:.4}", fit.slope));
+                    ui.end_row();
+                    ui.label(tr("Offset"));
+                    ui.monospace(format!("{:+.3}", fit.offset));
+                    ui.end_row();
+                    ui.label("R²");
+                    ui.monospace(
+                        fit.r_squared
+                            .map_or("-".to_string(), |r2| format!("{r2:.4}")),
+                    );
+                    ui.end_row();
+                    ui.label("n");
+                    ui.monospace(fit.count.to_string());
+                    ui.end_row();
+                });
+
+                let station = &stations[self.device.0];
+                let target = station.device().zip(station.source_channel(self.device.1));
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Back")).clicked() {
+                        self.step = Step::Window;
+                    }
+                    if let Some(ty) = self.sensor_type(stations)
+                        && let Some(((model, device), channel)) = target
+                        && ui
+                            .button(tr("Save calibration"))
+                            .on_hover_text(tr("Applied to this device from its next start on"))
+                            .clicked()
+                    {
+                        let channel = channel.id();
+                        let unit = station.channels[self.device.1].unit;
+                        let current = calibration
+                            .iter()
+                            .find(|c| c.is_for(model, &device, &channel))
+                            .map_or(Calibration::identity(unit), |c| c.calibration);
+                        saved = Some(ChannelCalibration {
+                            model,
+                            device,
+                            channel,
+                            calibration: current.then(ty, &fit, unit),
+                        });
+                    }
+                });
+            }
+        }
+
+        saved
+    }
+}
@@ -0,0 +1,61 @@
+use chrono::{DateTime, Local};
+use egui::Grid;
+
+use envsensor_demo::{i18n::tr, station::Station, stats::Comparison};
+
+/// Bias, RMSE and correlation of every channel measured by more than one station, each
+/// against the same quantity, in the same unit and size bin, of the first station measuring it
+pub fn comparison_panel(ui: &mut egui::Ui, stations: &[Station], from: Option<DateTime<Local>>) {
+    let mut rows = Vec::new();
+    for (n, station) in stations.iter().enumerate() {
+        for (idx, ch) in station.channels.iter().enumerate() {
+            let reference = stations[..n].iter().find_map(|r| {
+                let ref_idx = r.channels.iter().position(|c| {
+                    c.sensor_type == ch.sensor_type && c.unit == ch.unit && c.bin == ch.bin
+                })?;
+                Some((r, ref_idx))
+            });
+            if let Some((reference, ref_idx)) = reference {
+                let comparison =
+                    Comparison::between(&station.history, idx, &reference.history, ref_idx, from);
+                rows.push((ch, reference, station, comparison));
+            }
+        }
+    }
+
+    if rows.is_empty() {
+        ui.label(tr(
+            "Start two sensors measuring the same quantity to compare them",
+        ));
+        return;
+    }
+
+    Grid::new("comparison").striped(true).show(ui, |ui| {
+        let headers = [tr("Channel"), tr("Reference"), tr("Sensor"), tr("Bias")];
+        for header in headers.into_iter().chain(["RMSE", "r", "n"]) {
+            ui.strong(header);
+        }
+        ui.end_row();
+
+        for (ch, reference, station, comparison) in rows {
+            ui.label(format!("{} ({})", ch.name(), ch.unit.as_ref()));
+            ui.label(&reference.name);
+            ui.label(&station.name);
+            match comparison {
+                Some(c) => {
+                    ui.monospace(format!("{:+.2}", c.bias));
+                    ui.monospace(format!("{:.2}", c.rmse));
+                    match c.correlation {
+                        Some(r) => ui.monospace(format!("{r:.3}")),
+                        None => ui.label("-"),
+                    };
+                    ui.monospace(c.count.to_string());
+                }
+                None => (0..4).for_each(|_| {
+                    ui.label("-");
+                }),
+            }
+            ui.end_row();
+        }
+    });
+}
@@ -0,0 +1,103 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Local};
+use egui::{Color32, RichText, ScrollArea};
+
+/// Messages kept, the oldest are dropped first
+const CONSOLE_LEN: usize = 1000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Level {
+    Info,
+    Alert,
+    Error,
+}
+
+impl Level {
+    fn label(&self) -> &'static str {
+        match self {
+            Level::Info => "INFO",
+            Level::Alert => "ALERT",
+            Level::Error => "ERROR",
+        }
+    }
+
+    fn color(&self, ui: &egui::Ui) -> Color32 {
+        match self {
+            Level::Info => ui.visuals().text_color(),
+            Level::Alert => Color32::from_rgb(230, 140, 0),
+            Level::Error => ui.visuals().error_fg_color,
+        }
+    }
+}
+
+struct Entry {
+    time: DateTime<Local>,
+    level: Level,
+    text: String,
+}
+
+/// Timestamped history of the status and error messages of this session
+pub struct Console {
+    entries: VecDeque<Entry>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self {
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, level: Level, text: String) {
+        if self.entries.len() == CONSOLE_LEN {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Entry {
+            time: Local::now(),
+            level,
+            text,
+        });
+    }
+
+    pub fn info(&mut self, text: String) {
+        self.push(Level::Info, text);
+    }
+
+    pub fn error(&mut self, text: String) {
+        self.push(Level::Error, text);
+    }
+
+    /// Newest message, shown in the status bar
+    pub fn latest(&self) -> &str {
+        self.entries.back().map_or("Ready", |e| &e.text)
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.strong("Console");
+            if ui.button("Clear").clicked() {
+                self.entries.clear();
+            }
+        });
+
+        ScrollArea::vertical()
+            .auto_shrink(false)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for entry in &self.entries {
+                    let color = entry.level.color(ui);
+                    ui.label(
+                        RichText::new(format!(
+                            "{} {:5} {}",
+                            entry.time.format("%H:%M:%S"),
+                            entry.level.label(),
+                            entry.text
+                        ))
+                        .monospace()
+                        .color(color),
+                    );
+                }
+            });
+    }
+}
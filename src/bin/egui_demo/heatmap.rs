@@ -0,0 +1,131 @@
+use egui::{Align2, Color32, FontId, Rect, Sense, Stroke, pos2, vec2};
+
+use envsensor_demo::{
+    gui::plot::channel_picker, history::History, i18n::tr, sensor::SensorChannel,
+    stats::DailyPattern,
+};
+
+/// Width of the day labels left of the grid
+const LABEL_WIDTH: f32 = 80.0;
+
+/// Color stops from low to high, roughly viridis
+const STOPS: [Color32; 5] = [
+    Color32::from_rgb(68, 1, 84),
+    Color32::from_rgb(59, 82, 139),
+    Color32::from_rgb(33, 145, 140),
+    Color32::from_rgb(94, 201, 98),
+    Color32::from_rgb(253, 231, 37),
+];
+
+/// Color of `t` in 0..=1
+fn color(t: f32) -> Color32 {
+    let t = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let n = (t as usize).min(STOPS.len() - 2);
+
+    STOPS[n].lerp_to_gamma(STOPS[n + 1], t - n as f32)
+}
+
+/// State of the daily pattern tab
+#[derive(Default)]
+pub struct HeatmapView {
+    /// Index of the channel shown
+    channel: usize,
+}
+
+impl HeatmapView {
+    /// Hourly means of one channel over the 1-min means of the run, a row per day, over the
+    /// buffered samples when there are none
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        channels: &[SensorChannel],
+        history: &History,
+        minute_means: &History,
+    ) {
+        if channels.is_empty() {
+            ui.label(tr("No data"));
+            return;
+        }
+        self.channel = self.channel.min(channels.len() - 1);
+
+        ui.horizontal(|ui| {
+            ui.label(tr("Channel"));
+            channel_picker(ui, "heatmap_channel", channels, &mut self.channel);
+        });
+
+        let samples = if minute_means.is_empty() {
+            ui.label(tr(
+                "Turn on rolling means in the settings to cover more than the buffered samples",
+            ));
+            history
+        } else {
+            minute_means
+        };
+
+        let ch = &channels[self.channel];
+        let pattern = DailyPattern::of(samples.iter(), self.channel);
+        let Some((min, max)) = pattern.range() else {
+            ui.label(tr("No data"));
+            return;
+        };
+        ui.label(format!("{:.2} – {:.2} {}", min, max, ch.unit.as_ref()));
+
+        let (rect, response) = ui.allocate_exact_size(ui.available_size(), Sense::hover());
+        let painter = ui.painter_at(rect);
+        let text_color = ui.visuals().text_color();
+        let font = FontId::proportional(12.0);
+
+        // Hour labels on top
+        let grid = Rect::from_min_max(rect.min + vec2(LABEL_WIDTH, 16.0), rect.max);
+        let cell = vec2(
+            grid.width() / 24.0,
+            (grid.height() / pattern.days.len() as f32).min(40.0),
+        );
+        for hour in (0..24).step_by(3) {
+            painter.text(
+                pos2(grid.left() + cell.x * hour as f32, rect.top()),
+                Align2::LEFT_TOP,
+                format!("{hour:02}"),
+                font.clone(),
+                text_color,
+            );
+        }
+
+        let span = (max - min).max(f32::EPSILON);
+        for (row, (day, means)) in pattern.days.iter().zip(&pattern.means).enumerate() {
+            let top = grid.top() + cell.y * row as f32;
+            painter.text(
+                pos2(rect.left(), top + cell.y / 2.0),
+                Align2::LEFT_CENTER,
+                day.format("%Y-%m-%d").to_string(),
+                font.clone(),
+                text_color,
+            );
+
+            for (hour, mean) in means.iter().enumerate() {
+                let Some(mean) = mean else {
+                    continue;
+                };
+                let cell_rect =
+                    Rect::from_min_size(pos2(grid.left() + cell.x * hour as f32, top), cell);
+                painter.rect_filled(cell_rect.shrink(0.5), 0.0, color((mean - min) / span));
+
+                if response
+                    .hover_pos()
+                    .is_some_and(|pos| cell_rect.contains(pos))
+                {
+                    painter.rect_stroke(
+                        cell_rect,
+                        0.0,
+                        Stroke::new(1.5, text_color),
+                        egui::StrokeKind::Inside,
+                    );
+                    response.clone().on_hover_text_at_pointer(format!(
+                        "{day} {hour:02}:00\n{mean:.2} {}",
+                        ch.unit.as_ref()
+                    ));
+                }
+            }
+        }
+    }
+}
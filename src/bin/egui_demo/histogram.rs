@@ -0,0 +1,101 @@
+use egui::Slider;
+use egui_plot::{Bar, BarChart, Plot};
+
+use envsensor_demo::{
+    gui::plot::{channel_color, channel_picker, from_x},
+    history::History,
+    i18n::tr,
+    sensor::SensorChannel,
+    stats::{ChannelStats, Histogram},
+};
+
+/// State of the histogram tab
+pub struct HistogramView {
+    /// Index of the channel shown
+    channel: usize,
+    bins: usize,
+}
+
+impl Default for HistogramView {
+    fn default() -> Self {
+        Self {
+            channel: 0,
+            bins: 30,
+        }
+    }
+}
+
+impl HistogramView {
+    /// Distribution of one channel over the time range `view` of the plot
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        channels: &[SensorChannel],
+        history: &History,
+        view: Option<(f64, f64)>,
+    ) {
+        if channels.is_empty() {
+            ui.label(tr("No data"));
+            return;
+        }
+        self.channel = self.channel.min(channels.len() - 1);
+
+        ui.horizontal(|ui| {
+            ui.label(tr("Channel"));
+            channel_picker(ui, "histogram_channel", channels, &mut self.channel);
+            ui.label(tr("Bins"));
+            ui.add(Slider::new(&mut self.bins, 5..=100));
+        });
+
+        let ch = &channels[self.channel];
+        let (from, to) = match view {
+            Some((min, max)) => (from_x(min), from_x(max)),
+            None => (None, None),
+        };
+        let values = history
+            .range(from, to)
+            .filter_map(|s| s.data.get(self.channel))
+            .map(|d| d.value)
+            .collect::<Vec<_>>();
+
+        let (Some(histogram), Some(stats)) = (
+            Histogram::of(&values, self.bins),
+            ChannelStats::from_values(values.into_iter()),
+        ) else {
+            ui.label(tr("No samples in the visible range"));
+            return;
+        };
+
+        ui.label(format!(
+            "{} {}, {} {:.2}, σ {:.2} {}",
+            stats.count,
+            tr("samples"),
+            tr("mean"),
+            stats.mean,
+            stats.stddev,
+            ch.unit.as_ref()
+        ));
+
+        let bars = histogram
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(n, &count)| {
+                Bar::new(histogram.center(n) as f64, count as f64).width(histogram.bin_width as f64)
+            })
+            .collect();
+        let n = channels[..self.channel]
+            .iter()
+            .filter(|c| c.sensor_type == ch.sensor_type)
+            .count();
+        let name = format!("{} ({})", ch.name(), ch.unit.as_ref());
+
+        Plot::new("histogram")
+            .x_axis_label(ch.unit.as_ref())
+            .y_axis_label(tr("Samples"))
+            .show(ui, |plot_ui| {
+                plot_ui
+                    .bar_chart(BarChart::new(name, bars).color(channel_color(ch.sensor_type, n)));
+            });
+    }
+}
@@ -0,0 +1,52 @@
+use egui::Grid;
+
+use envsensor_demo::sensor::{SensorChannel, SensorInfo, SensorModel};
+
+/// Details of a running sensor, fields the driver can't read show as "–"
+pub fn info_panel(
+    ui: &mut egui::Ui,
+    model: SensorModel,
+    port: &str,
+    info: Option<&SensorInfo>,
+    channels: &[SensorChannel],
+) {
+    let Some(info) = info else {
+        ui.label("Waiting for the sensor to initialize…");
+        return;
+    };
+    let or_dash = |value: Option<String>| value.unwrap_or_else(|| "–".to_string());
+
+    Grid::new("sensor_info").striped(true).show(ui, |ui| {
+        let mut row = |label: &str, value: String| {
+            ui.label(label);
+            ui.label(value);
+            ui.end_row();
+        };
+
+        row("Model", model.as_ref().to_string());
+        row("Port", port.to_string());
+        row(
+            "Gas",
+            or_dash(info.sensor_type.map(|ty| ty.as_ref().to_string())),
+        );
+        row(
+            "Channels",
+            channels
+                .iter()
+                .map(|ch| format!("{} ({})", ch.name(), ch.unit.as_ref()))
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+        row("Range", or_dash(info.range.map(|r| format!("0 – {r}"))));
+        row(
+            "Resolution",
+            or_dash(
+                info.decimals()
+                    .zip(info.scale)
+                    .map(|(decimals, scale)| format!("{decimals} decimals (1/{scale})")),
+            ),
+        );
+        row("Firmware", or_dash(info.firmware.clone()));
+        row("Serial number", or_dash(info.serial_number.clone()));
+    });
+}
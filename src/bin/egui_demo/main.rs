@@ -0,0 +1,1548 @@
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use egui::{
+    CentralPanel, Color32, ComboBox, Frame, IconData, Key, Margin, MenuBar, Modifiers, RichText,
+    ScrollArea, SidePanel, TopBottomPanel, ViewportCommand,
+};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use envsensor_demo::{
+    chart::render_chart,
+    config::Config,
+    controller::Controller,
+    convert::convert,
+    export::{export_samples, export_xlsx},
+    gui::{
+        plot::{PlotOptions, Reference, TimeWindow, YScale, from_x, plot_sensor},
+        tiles::value_tiles,
+    },
+    history::History,
+    i18n::{set_language, tr},
+    idle::IdleDim,
+    logger::LogConfig,
+    report::write_report,
+    sensor::{AppMsg, SensorModel, SerialSettings, Unit, channel_key},
+    serial_port_list,
+    session::Session,
+    station::Station,
+    theme,
+};
+
+mod alarm;
+mod banner;
+mod calibration;
+mod compare;
+mod console;
+mod heatmap;
+mod histogram;
+mod info;
+mod notify;
+mod prefs;
+mod serial;
+mod settings;
+mod stats;
+mod table;
+mod tail;
+mod thresholds;
+mod traffic;
+
+use alarm::Alarm;
+use banner::{BannerAction, error_banner};
+use calibration::CalibrationWizard;
+use compare::comparison_panel;
+use console::{Console, Level};
+use heatmap::HeatmapView;
+use histogram::HistogramView;
+use info::info_panel;
+use notify::notify;
+use prefs::{Geometry, Prefs};
+use serial::serial_settings;
+use settings::{GuiSettings, settings_dialog};
+use stats::stats_panel;
+use table::sample_table;
+use tail::LogTail;
+use traffic::TrafficView;
+
+#[derive(PartialEq)]
+enum Tab {
+    Plot,
+    Table,
+    Histogram,
+    Heatmap,
+}
+
+/// How several sensors share the central panel
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+enum Layout {
+    /// One sensor at a time, picked from a tab strip
+    Tabs,
+    /// All sensors stacked on a shared time axis
+    Grid,
+}
+
+/// Unit of the length of a timed run
+#[derive(Clone, Copy, PartialEq)]
+enum RunUnit {
+    Minutes,
+    Hours,
+}
+
+impl RunUnit {
+    fn duration(&self, length: u32) -> Duration {
+        match self {
+            RunUnit::Minutes => Duration::from_secs(u64::from(length) * 60),
+            RunUnit::Hours => Duration::from_secs(u64::from(length) * 60 * 60),
+        }
+    }
+}
+
+/// "1:02:03" or "2:03"
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
+    }
+}
+
+/// Keys that drive the app without the mouse, ignored while a text field has focus
+const SHORTCUTS: [(&str, &str); 4] = [
+    ("Space", "Start the selected sensor, or stop the active one"),
+    ("P", "Pause or resume the plot"),
+    ("M", "Drop an event marker"),
+    ("S", "Export the chart"),
+];
+
+/// Counters of a running sensor for the status bar, the last-sample age turns red once the
+/// sensor has missed a few readings
+fn acquisition_status(ui: &mut egui::Ui, station: &Station) {
+    let rate = station.history.rate(chrono::TimeDelta::minutes(1));
+    let age = station
+        .history
+        .latest()
+        .and_then(|s| (chrono::Local::now() - s.timestamp).to_std().ok());
+
+    // Right to left, so the last part first
+    if let Some(age) = age {
+        let stalled = age.as_secs_f64() > rate.map_or(5.0, |rate| (3.0 / rate).max(5.0));
+        let text = format!("{} {:.1} s", tr("Last sample"), age.as_secs_f32());
+        ui.label(if stalled {
+            RichText::new(text).color(Color32::RED).strong()
+        } else {
+            RichText::new(text)
+        });
+    }
+    if let Some(started) = station.started {
+        ui.label(format!("⏲ {}", format_duration(started.elapsed())))
+            .on_hover_text(tr("Elapsed since the start"));
+    }
+    if let Some(rate) = rate {
+        ui.label(format!("{rate:.2} Hz"))
+            .on_hover_text(tr("Samples per second over the last minute"));
+    }
+    ui.label(format!("{} {}", station.received, tr("samples")));
+}
+
+/// Extra scale of the whole UI in kiosk mode, for fingers on a small touchscreen
+const KIOSK_ZOOM: f32 = 1.5;
+
+/// Samples kept for plotting, one day at 1 Hz
+const HISTORY_LEN: usize = 24 * 60 * 60;
+
+static NO_CHANNELS: BTreeSet<String> = BTreeSet::new();
+
+/// Channels of `station` switched off in the legend, remembered for its sensor model
+fn hidden_channels<'a>(
+    hidden: &'a BTreeMap<String, BTreeSet<String>>,
+    station: &Station,
+) -> &'a BTreeSet<String> {
+    station
+        .model
+        .and_then(|model| hidden.get(model.as_ref()))
+        .unwrap_or(&NO_CHANNELS)
+}
+
+struct App {
+    /// Sensor and port choices and the stations, the station shown in the tabs layout is the
+    /// active one
+    controller: Controller,
+    layout: Layout,
+    show_stats: bool,
+    /// Agreement of stations measuring the same quantity
+    show_compare: bool,
+    show_info: bool,
+    /// Show the last lines of the active station's log file
+    show_tail: bool,
+    tail: LogTail,
+    show_tiles: bool,
+    /// Raise desktop notifications on alerts and sensor errors
+    notifications: bool,
+    /// Beep on threshold breaches and sensor errors
+    audible_alarm: bool,
+    alarm: Alarm,
+    tab: Tab,
+    /// Visible time range of the plot, as plot x coordinates
+    view: Option<(f64, f64)>,
+    y_scale: YScale,
+    window: TimeWindow,
+    /// Plot frozen at this time, acquisition and logging carry on
+    frozen: Option<chrono::DateTime<chrono::Local>>,
+    /// Plot scrolls with the newest samples, until the user zooms or pans
+    follow: bool,
+    /// Reset zoom and pan on the next frame
+    reset_axes: bool,
+    /// Moving average window of the plot lines, the log is not affected
+    smoothing: usize,
+    serial: SerialSettings,
+    show_serial: bool,
+    /// Modbus address of a Rydason sensor
+    rydason_address: u8,
+    /// Log the samples of sensors started from now on
+    logging: bool,
+    /// Directory new logs are written to
+    log_dir: PathBuf,
+    /// Base name of the next log, empty for the default "<start time>_<model>"
+    log_name: String,
+    notes: String,
+    /// Unit each channel is shown in, keyed by `channel_key`; the log keeps the delivered unit
+    display_units: BTreeMap<String, Unit>,
+    /// Channels switched off in the plot legend, per sensor model name
+    hidden_channels: BTreeMap<String, BTreeSet<String>>,
+    /// Label of the next event marker
+    marker_label: String,
+    /// Recording of a reference instrument overlaid on the plot
+    reference: Option<Reference>,
+    /// Stop the sensors started from now on after `run_length`
+    timed: bool,
+    run_length: u32,
+    run_unit: RunUnit,
+    console: Console,
+    show_console: bool,
+    show_traffic: bool,
+    traffic: TrafficView,
+    histogram: HistogramView,
+    heatmap: HeatmapView,
+    settings: GuiSettings,
+    /// Copy of the settings being edited while the settings dialog is open
+    settings_draft: Option<GuiSettings>,
+    /// Open while calibrating a sensor against a reference
+    calibration: Option<CalibrationWizard>,
+    /// Window position and size as of the last frame
+    geometry: Option<Geometry>,
+    /// Preferences as last written to disk
+    saved_prefs: Prefs,
+    /// Fullscreen without window decorations, larger controls and a confirmation before exit
+    kiosk: bool,
+    /// Asking whether to really exit, in kiosk mode
+    confirm_exit: bool,
+    exit_confirmed: bool,
+    /// Dims the window after a while without input
+    idle: IdleDim,
+}
+
+fn main() -> eframe::Result<()> {
+    let prefs = Prefs::load();
+    let mut controller = Controller::new(HISTORY_LEN);
+    if let Some(name) = &prefs.sensor {
+        controller.select_sensor(name);
+    }
+    if let Some(name) = &prefs.port {
+        controller.select_port(name);
+    }
+
+    let settings = GuiSettings::load();
+    set_language(settings.sensor.language);
+    let idle = IdleDim::new(settings.sensor.dim_timeout());
+    let kiosk = std::env::args().skip(1).any(|arg| arg == "--kiosk");
+
+    let app = App {
+        controller,
+        layout: prefs.layout,
+        show_stats: prefs.show_stats,
+        show_compare: prefs.show_compare,
+        show_info: prefs.show_info,
+        show_tail: prefs.show_tail,
+        tail: LogTail::default(),
+        show_tiles: prefs.show_tiles,
+        notifications: prefs.notifications,
+        audible_alarm: prefs.audible_alarm,
+        alarm: Alarm::new(),
+        tab: Tab::Plot,
+        view: None,
+        y_scale: prefs.y_scale,
+        window: prefs.window,
+        frozen: None,
+        follow: true,
+        reset_axes: false,
+        smoothing: prefs.smoothing,
+        serial: SerialSettings::default(),
+        show_serial: false,
+        rydason_address: prefs.rydason_address,
+        logging: prefs.logging,
+        log_dir: prefs.log_dir.clone(),
+        log_name: String::new(),
+        notes: String::new(),
+        marker_label: String::new(),
+        display_units: prefs.display_units.clone(),
+        hidden_channels: prefs.hidden_channels.clone(),
+        reference: None,
+        timed: false,
+        run_length: 30,
+        run_unit: RunUnit::Minutes,
+        console: Console::new(),
+        show_console: false,
+        show_traffic: false,
+        traffic: TrafficView::default(),
+        histogram: HistogramView::default(),
+        heatmap: HeatmapView::default(),
+        settings,
+        settings_draft: None,
+        calibration: None,
+        geometry: prefs.geometry,
+        saved_prefs: prefs,
+        kiosk,
+        confirm_exit: false,
+        exit_confirmed: false,
+        idle,
+    };
+
+    let rgba = image::load_from_memory_with_format(theme::ICON_PNG, image::ImageFormat::Png)
+        .unwrap()
+        .into_rgba8();
+    let (w, h) = (rgba.width(), rgba.height());
+
+    let mut viewport = egui::ViewportBuilder::default().with_icon(Arc::new(IconData {
+        rgba: rgba.into_raw(),
+        width: w,
+        height: h,
+    }));
+    if kiosk {
+        viewport = viewport.with_fullscreen(true).with_decorations(false);
+    } else if let Some(geometry) = &app.geometry {
+        viewport = geometry.apply(viewport);
+    }
+
+    let options = eframe::NativeOptions {
+        viewport,
+        ..Default::default()
+    };
+
+    eframe::run_native(
+        "EnvSensor Demo",
+        options,
+        Box::new(|cc| {
+            cc.egui_ctx.set_theme(app.settings.theme);
+            add_cjk_font(&cc.egui_ctx);
+            if kiosk {
+                cc.egui_ctx.set_zoom_factor(KIOSK_ZOOM);
+            }
+            Ok(Box::new(app))
+        }),
+    )
+}
+
+/// Input that wakes a dimmed window
+fn is_user_input(event: &egui::Event) -> bool {
+    matches!(
+        event,
+        egui::Event::PointerMoved(_)
+            | egui::Event::PointerButton { .. }
+            | egui::Event::Touch { .. }
+            | egui::Event::MouseWheel { .. }
+            | egui::Event::Key { .. }
+    )
+}
+
+/// Dark layer over the whole window while it's dimmed, taking the clicks meant to wake it
+fn dim_overlay(ctx: &egui::Context) {
+    egui::Area::new(egui::Id::new("dim"))
+        .order(egui::Order::Tooltip)
+        .fixed_pos(egui::Pos2::ZERO)
+        .show(ctx, |ui| {
+            let rect = ctx.screen_rect();
+            ui.painter()
+                .rect_filled(rect, 0.0, Color32::from_black_alpha(235));
+            ui.allocate_rect(rect, egui::Sense::click());
+        });
+}
+
+/// The built-in fonts have no Chinese glyphs, fall back to a system font for them
+fn add_cjk_font(ctx: &egui::Context) {
+    let Some(data) = theme::cjk_font() else {
+        return;
+    };
+
+    let mut fonts = egui::FontDefinitions::default();
+    fonts.font_data.insert(
+        "cjk".to_string(),
+        Arc::new(egui::FontData::from_owned(data)),
+    );
+    for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+        fonts
+            .families
+            .entry(family)
+            .or_default()
+            .push("cjk".to_string());
+    }
+    ctx.set_fonts(fonts);
+}
+
+impl App {
+    fn prefs(&self) -> Prefs {
+        Prefs {
+            sensor: self.controller.sensor().map(|s| s.as_ref().to_string()),
+            port: self.controller.port().map(str::to_string),
+            rydason_address: self.rydason_address,
+            logging: self.logging,
+            log_dir: self.log_dir.clone(),
+            layout: self.layout,
+            window: self.window,
+            y_scale: self.y_scale,
+            show_tiles: self.show_tiles,
+            show_stats: self.show_stats,
+            show_compare: self.show_compare,
+            show_info: self.show_info,
+            show_tail: self.show_tail,
+            notifications: self.notifications,
+            audible_alarm: self.audible_alarm,
+            smoothing: self.smoothing,
+            display_units: self.display_units.clone(),
+            hidden_channels: self.hidden_channels.clone(),
+            geometry: self.geometry,
+        }
+    }
+
+    fn save_prefs(&mut self, prefs: Prefs) {
+        if let Err(e) = prefs.save() {
+            self.console
+                .error(format!("Failed to save preferences: {e}"));
+        }
+        self.saved_prefs = prefs;
+    }
+
+    /// Plot of the active station, or of all of them in the grid layout, with its toolbar
+    fn plot_area(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("Y axis"));
+            ComboBox::from_id_salt("y_scale")
+                .selected_text(tr(self.y_scale.label()))
+                .show_ui(ui, |ui| {
+                    for scale in YScale::ALL {
+                        ui.selectable_value(&mut self.y_scale, scale, tr(scale.label()));
+                    }
+                });
+
+            ui.label(tr("Window"));
+            for window in TimeWindow::ALL {
+                ui.selectable_value(&mut self.window, window, tr(window.label()));
+            }
+
+            let mut frozen = self.frozen.is_some();
+            if ui
+                .toggle_value(&mut frozen, tr("❄ Freeze"))
+                .on_hover_text(tr("Pause the plot, acquisition carries on (P)"))
+                .changed()
+            {
+                self.toggle_freeze();
+            }
+            if !self.follow && self.frozen.is_none() && ui.button(tr("▶ Resume live")).clicked() {
+                self.follow = true;
+            }
+            if ui
+                .button(tr("⟲ Reset axes"))
+                .on_hover_text(tr("Undo zoom and pan"))
+                .clicked()
+            {
+                self.reset_axes = true;
+                self.follow = true;
+            }
+
+            ui.separator();
+            ui.label(tr("Smooth"));
+            ui.add(egui::Slider::new(&mut self.smoothing, 1..=120).logarithmic(true))
+                .on_hover_text(tr("Moving average over this many samples, display only"));
+
+            ui.separator();
+            ui.add(
+                egui::TextEdit::singleline(&mut self.marker_label)
+                    .hint_text(tr("Marker label"))
+                    .desired_width(120.0),
+            );
+            let running = self
+                .controller
+                .active_station()
+                .is_some_and(Station::is_running);
+            if ui
+                .add_enabled(running, egui::Button::new(tr("📍 Mark")))
+                .on_hover_text(tr("Mark the current time on the plot and in the log (M)"))
+                .clicked()
+            {
+                self.mark_event();
+            }
+            ui.separator();
+
+            if let Some(reference) = &mut self.reference {
+                ui.label(format!("{}: {}", tr("Reference"), reference.name));
+                let mut offset = reference.offset.num_seconds();
+                if ui
+                    .add(egui::DragValue::new(&mut offset).suffix(" s"))
+                    .on_hover_text(tr("Shift the reference in time to line up the clocks"))
+                    .changed()
+                {
+                    reference.offset = chrono::TimeDelta::seconds(offset);
+                }
+                if ui
+                    .button("✕")
+                    .on_hover_text(tr("Remove the reference"))
+                    .clicked()
+                {
+                    self.reference = None;
+                }
+                ui.separator();
+            }
+
+            if ui
+                .add_enabled(
+                    self.controller
+                        .active_station()
+                        .is_some_and(|s| !s.history.is_empty()),
+                    egui::Button::new(tr("📷 Export chart")),
+                )
+                .on_hover_text(tr("Save the visible chart as an image (S)"))
+                .clicked()
+            {
+                self.export_chart();
+            }
+        });
+
+        // Limits follow the display units of each station
+        let thresholds = self
+            .controller
+            .stations
+            .iter()
+            .map(|s| s.display_thresholds(&self.settings.sensor.thresholds))
+            .collect::<Vec<_>>();
+        let options = PlotOptions {
+            normalize: false,
+            window: self.window,
+            height: None,
+            frozen: self.frozen,
+            follow: self.follow,
+            reset: std::mem::take(&mut self.reset_axes),
+            smoothing: self.smoothing,
+            thresholds: thresholds
+                .get(self.controller.active)
+                .map_or(&self.settings.sensor.thresholds, |t| t),
+            markers: self.controller.active_station().map_or(&[], |s| &s.markers),
+            reference: self.reference.as_ref(),
+            hidden: self
+                .controller
+                .active_station()
+                .map_or(&NO_CHANNELS, |s| hidden_channels(&self.hidden_channels, s)),
+        };
+
+        // Legend choices to remember, per model of the plotted stations
+        let mut hidden = Vec::new();
+        let response = match self.layout {
+            Layout::Grid if !self.controller.stations.is_empty() => {
+                let height = ui.available_height() / self.controller.stations.len() as f32 - 20.0;
+
+                let mut responses = Vec::new();
+                for (n, station) in self.controller.stations.iter().enumerate() {
+                    ui.label(&station.name);
+                    let response = plot_sensor(
+                        ui,
+                        &format!("sensor_chart_{n}"),
+                        &station.history,
+                        &station.channels,
+                        self.y_scale,
+                        PlotOptions {
+                            height: Some(height),
+                            markers: &station.markers,
+                            thresholds: &thresholds[n],
+                            hidden: hidden_channels(&self.hidden_channels, station),
+                            ..options
+                        },
+                    );
+                    hidden.push((station.model, response.inner.hidden.clone()));
+                    responses.push(response);
+                }
+                let moved = responses.iter().any(|r| r.inner.moved);
+                let mut response = responses.remove(0);
+                response.inner.moved = moved;
+                response
+            }
+            _ => match self.controller.active_station() {
+                Some(station) => {
+                    let response = plot_sensor(
+                        ui,
+                        "sensor_chart",
+                        &station.history,
+                        &station.channels,
+                        self.y_scale,
+                        options,
+                    );
+                    hidden.push((station.model, response.inner.hidden.clone()));
+                    response
+                }
+                None => plot_sensor(
+                    ui,
+                    "sensor_chart",
+                    &History::new(0),
+                    &[],
+                    self.y_scale,
+                    options,
+                ),
+            },
+        };
+
+        if response.inner.moved && self.frozen.is_none() {
+            self.follow = false;
+        }
+        for (model, channels) in hidden {
+            let Some(model) = model else {
+                continue;
+            };
+            if channels.is_empty() {
+                self.hidden_channels.remove(model.as_ref());
+            } else {
+                self.hidden_channels
+                    .insert(model.as_ref().to_string(), channels);
+            }
+        }
+
+        let bounds = response.transform.bounds();
+        self.view = Some((bounds.min()[0], bounds.max()[0]));
+    }
+
+    /// A banner for each station whose sensor failed, with retry and dismiss
+    fn error_banners(&mut self, ui: &mut egui::Ui) {
+        for station in &mut self.controller.stations {
+            let Some(error) = &station.error else {
+                continue;
+            };
+
+            match error_banner(ui, &station.name, error, station.can_retry()) {
+                Some(BannerAction::Retry) => {
+                    if let Err(e) = station.retry() {
+                        self.console
+                            .error(format!("{}: retry failed: {e}", station.name));
+                    } else {
+                        self.console.info(format!("{}: retrying", station.name));
+                    }
+                }
+                Some(BannerAction::Dismiss) => station.dismiss(),
+                None => {}
+            }
+        }
+    }
+
+    /// Tab strip with one entry per station, plus stop/close for the active one
+    fn station_tabs(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            for (idx, station) in self.controller.stations.iter().enumerate() {
+                let label = if station.is_running() {
+                    format!("● {}", station.name)
+                } else {
+                    station.name.clone()
+                };
+                ui.selectable_value(&mut self.controller.active, idx, label);
+            }
+
+            if let Some(station) = self.controller.active_station_mut() {
+                ui.separator();
+                if station.is_running() {
+                    if ui.button(tr("■ Stop")).clicked() {
+                        station.stop();
+                    }
+                } else if ui.button(tr("✕ Close")).clicked() {
+                    self.controller.close(self.controller.active);
+                }
+
+                if let Some(path) = self.controller.active_station().and_then(Station::log_path) {
+                    ui.label(RichText::new("⏺ REC").color(Color32::RED).strong());
+                    ui.label(path.display().to_string());
+                }
+            }
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.selectable_value(&mut self.layout, Layout::Grid, tr("Grid"));
+                ui.selectable_value(&mut self.layout, Layout::Tabs, tr("Tabs"));
+            });
+        });
+    }
+
+    fn toggle_freeze(&mut self) {
+        self.frozen = match self.frozen {
+            Some(_) => None,
+            None => Some(chrono::Local::now()),
+        };
+    }
+
+    /// A unit picker per channel of the active station, applied to all stations
+    fn display_units_menu(&mut self, ui: &mut egui::Ui) {
+        let Some(station) = self.controller.active_station() else {
+            ui.label(tr("No sensor"));
+            return;
+        };
+
+        let mut units = self.display_units.clone();
+        egui::Grid::new("display_units").show(ui, |ui| {
+            for idx in 0..station.channels.len() {
+                let Some(source) = station.source_channel(idx) else {
+                    continue;
+                };
+                let key = channel_key(&station.channels, idx);
+                let ty = source.sensor_type;
+                ui.label(&key);
+
+                let mut unit = units.get(&key).copied();
+                let choices = Unit::iter().filter(|&u| convert(1.0, ty, source.unit, u).is_some());
+                ComboBox::from_id_salt(("display_unit", &key))
+                    .selected_text(unit.as_ref().map_or(tr("As logged"), |u| u.as_ref()))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut unit, None, tr("As logged"));
+                        for u in choices {
+                            ui.selectable_value(&mut unit, Some(u), u.as_ref());
+                        }
+                    });
+                match unit {
+                    Some(unit) => units.insert(key, unit),
+                    None => units.remove(&key),
+                };
+                ui.end_row();
+            }
+        });
+
+        if units != self.display_units {
+            for station in &mut self.controller.stations {
+                station.set_display_units(&units);
+            }
+            self.display_units = units;
+        }
+    }
+
+    /// Hold back a close request, e.g. a stray Alt+F4, until the user confirms it
+    fn exit_guard(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.viewport().close_requested()) && !self.exit_confirmed {
+            ctx.send_viewport_cmd(ViewportCommand::CancelClose);
+            self.confirm_exit = true;
+        }
+        if !self.confirm_exit {
+            return;
+        }
+
+        let modal = egui::Modal::new(egui::Id::new("confirm_exit")).show(ctx, |ui| {
+            ui.heading(tr("Exit the application?"));
+            ui.label(tr("Running sensors are stopped and their logs closed."));
+            ui.horizontal(|ui| {
+                if ui.button(tr("Exit")).clicked() {
+                    self.exit_confirmed = true;
+                    ctx.send_viewport_cmd(ViewportCommand::Close);
+                }
+                ui.button(tr("Stay")).clicked()
+            })
+            .inner
+        });
+        if modal.inner || modal.should_close() || self.exit_confirmed {
+            self.confirm_exit = false;
+        }
+    }
+
+    /// Act on the keys in `SHORTCUTS`
+    fn shortcuts(&mut self, ctx: &egui::Context) {
+        // Typing a label or a note must not trigger anything
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let pressed = |key| ctx.input_mut(|i| i.consume_key(Modifiers::NONE, key));
+
+        if pressed(Key::Space) {
+            match self.controller.active_station_mut() {
+                Some(station) if station.is_running() => station.stop(),
+                _ => self.start_sensor(),
+            }
+        }
+        if pressed(Key::P) {
+            self.toggle_freeze();
+        }
+        if pressed(Key::M) || ctx.input_mut(|i| i.consume_key(Modifiers::COMMAND, Key::M)) {
+            self.mark_event();
+        }
+        if pressed(Key::S)
+            && self
+                .controller
+                .active_station()
+                .is_some_and(|s| !s.history.is_empty())
+        {
+            self.export_chart();
+        }
+    }
+
+    /// Drop a marker on the active sensor, named after the label field or numbered
+    fn mark_event(&mut self) {
+        let Some(station) = self.controller.active_station() else {
+            return;
+        };
+        let Some(sensor) = &station.sensor else {
+            return;
+        };
+
+        let label = match self.marker_label.trim() {
+            "" => format!("{} {}", tr("Marker"), station.markers.len() + 1),
+            label => label.to_string(),
+        };
+        sensor.mark(&label);
+        self.marker_label.clear();
+    }
+
+    /// Start the sensor chosen in the control panel as a new station
+    fn start_sensor(&mut self) {
+        let mut config = Config {
+            notes: self.notes.clone(),
+            // Frames are only formatted and sent while someone looks at them
+            capture: self.show_traffic,
+            log: LogConfig {
+                enabled: self.logging,
+                dir: self.log_dir.clone(),
+                name: Some(self.log_name.trim())
+                    .filter(|n| !n.is_empty())
+                    .map(str::to_string),
+                ..Default::default()
+            },
+            serial: SerialSettings {
+                modbus_address: Some(self.rydason_address),
+                ..self.serial.clone()
+            },
+            duration: self.timed.then(|| self.run_unit.duration(self.run_length)),
+            ..Default::default()
+        };
+        self.settings.sensor.apply(&mut config);
+
+        match self.controller.start(config) {
+            Ok(station) => {
+                station.set_display_units(&self.display_units);
+                self.frozen = None;
+                self.follow = true;
+            }
+            Err(e) => self.console.error(format!("Failed to start: {e}")),
+        }
+    }
+
+    /// Tell about the serial ports that appeared
+    fn report_ports(&mut self, added: Vec<String>) {
+        if !added.is_empty() {
+            self.console
+                .info(format!("New serial port: {}", added.join(", ")));
+        }
+    }
+
+    /// Load a previously logged CSV into the plot
+    fn open_csv(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV log", &["csv", "gz"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        match Session::load_csv(&path) {
+            Ok(session) => {
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let mut station = Station::new(name, session.samples.len().max(HISTORY_LEN));
+                station.set_display_units(&self.display_units);
+                for sample in session.samples {
+                    station.push_sample(sample);
+                }
+                station.markers = session.markers;
+                self.console.info(format!(
+                    "Loaded {} samples from {}",
+                    station.history.len(),
+                    path.display()
+                ));
+                self.controller.add_station(station);
+
+                // A recording is looked at as a whole
+                self.window = TimeWindow::All;
+                self.frozen = None;
+                self.follow = true;
+            }
+            Err(e) => self
+                .console
+                .error(format!("Failed to open {}: {e}", path.display())),
+        }
+    }
+
+    /// Load the CSV of a reference instrument to overlay on the plot
+    fn open_reference(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv", "gz"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        match Session::load_csv(&path) {
+            Ok(session) => {
+                let mut history = History::new(session.samples.len());
+                for sample in session.samples {
+                    history.push(sample);
+                }
+                self.console.info(format!(
+                    "Overlaying {} reference samples from {}",
+                    history.len(),
+                    path.display()
+                ));
+                self.reference = Some(Reference {
+                    name: path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    channels: session.channels,
+                    history,
+                    offset: chrono::TimeDelta::zero(),
+                });
+            }
+            Err(e) => self
+                .console
+                .error(format!("Failed to open {}: {e}", path.display())),
+        }
+    }
+
+    /// Save the samples currently visible in the plot
+    fn export_selection(&mut self) {
+        let Some(station) = self.controller.active_station() else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .add_filter("JSON", &["json"])
+            .set_file_name("selection.csv")
+            .save_file()
+        else {
+            return;
+        };
+
+        let (from, to) = match self.view {
+            Some((min, max)) => (from_x(min), from_x(max)),
+            None => (None, None),
+        };
+
+        match export_samples(&station.channels, station.history.range(from, to), &path) {
+            Ok(n) => self
+                .console
+                .info(format!("Exported {n} samples to {}", path.display())),
+            Err(e) => self.console.error(format!("Failed to export: {e}")),
+        }
+    }
+
+    /// Render the visible part of the active station's plot to an image for reports
+    fn export_chart(&mut self) {
+        let Some(station) = self.controller.active_station() else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("PNG image", &["png"])
+            .add_filter("SVG image", &["svg"])
+            .set_file_name("chart.png")
+            .save_file()
+        else {
+            return;
+        };
+
+        let (from, to) = match self.view {
+            Some((min, max)) => (from_x(min), from_x(max)),
+            None => (None, None),
+        };
+        let samples = || station.history.range(from, to);
+        let (Some(first), Some(last)) = (samples().next(), samples().last()) else {
+            self.console
+                .error("No samples in the visible range".to_string());
+            return;
+        };
+        let title = format!(
+            "{}, {} – {}",
+            station.name,
+            first.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            last.timestamp.format("%H:%M:%S")
+        );
+        match render_chart(&path, &title, &station.channels, samples()) {
+            Ok(()) => self
+                .console
+                .info(format!("Exported chart to {}", path.display())),
+            Err(e) => self.console.error(format!("Failed to export chart: {e}")),
+        }
+    }
+
+    fn export_xlsx(&mut self) {
+        let Some(csv) = rfd::FileDialog::new()
+            .add_filter("CSV log", &["csv"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        // The save dialog asks before replacing an existing workbook
+        let mut dialog = rfd::FileDialog::new().add_filter("Excel workbook", &["xlsx"]);
+        if let Some(dir) = csv.parent() {
+            dialog = dialog.set_directory(dir);
+        }
+        if let Some(name) = csv.with_extension("xlsx").file_name() {
+            dialog = dialog.set_file_name(name.to_string_lossy());
+        }
+        let Some(xlsx) = dialog.save_file() else {
+            return;
+        };
+
+        match Session::load_csv(&csv).and_then(|session| export_xlsx(&session, &xlsx)) {
+            Ok(()) => self.console.info(format!("Exported {}", xlsx.display())),
+            Err(e) => self.console.error(format!("Failed to export: {e}")),
+        }
+    }
+
+    fn write_report(&mut self) {
+        let Some(csv) = rfd::FileDialog::new()
+            .add_filter("CSV log", &["csv"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        match write_report(&csv, None, &self.settings.sensor.thresholds) {
+            Ok(path) => self
+                .console
+                .info(format!("Wrote report {}", path.display())),
+            Err(e) => self.console.error(format!("Failed to write report: {e}")),
+        }
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Decided before the input of this frame, so the touch waking the display does nothing
+        // else
+        let dimmed = self.idle.is_dimmed();
+
+        // Menu bar
+        TopBottomPanel::top("menu").show(ctx, |ui| {
+            MenuBar::new().ui(ui, |ui| {
+                ui.menu_button(tr("File"), |ui| {
+                    if ui.button(tr("Open CSV…")).clicked() {
+                        self.open_csv();
+                    }
+
+                    if ui
+                        .button(tr("Overlay reference CSV…"))
+                        .on_hover_text(tr("Compare with another instrument's log"))
+                        .clicked()
+                    {
+                        self.open_reference();
+                    }
+
+                    if ui.button(tr("Export XLSX…")).clicked() {
+                        self.export_xlsx();
+                    }
+
+                    if ui
+                        .button(tr("Report…"))
+                        .on_hover_text(tr("HTML summary of a CSV log"))
+                        .clicked()
+                    {
+                        self.write_report();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            self.controller
+                                .active_station()
+                                .is_some_and(|s| !s.history.is_empty()),
+                            egui::Button::new(tr("Export selection…")),
+                        )
+                        .clicked()
+                    {
+                        self.export_selection();
+                    }
+
+                    ui.separator();
+                    if ui.button(tr("Settings…")).clicked() {
+                        self.settings_draft = Some(self.settings.clone());
+                    }
+
+                    if ui.button(tr("Calibration wizard…")).clicked() {
+                        self.calibration = Some(CalibrationWizard::default());
+                    }
+
+                    // There is no title bar to close the window from
+                    if self.kiosk {
+                        ui.separator();
+                        if ui.button(tr("Exit")).clicked() {
+                            ctx.send_viewport_cmd(ViewportCommand::Close);
+                        }
+                    }
+                });
+
+                ui.menu_button(tr("View"), |ui| {
+                    ui.checkbox(&mut self.show_tiles, tr("Value tiles"));
+                    ui.checkbox(&mut self.show_stats, tr("Statistics"));
+                    ui.checkbox(&mut self.show_compare, tr("Sensor comparison"));
+                    ui.checkbox(&mut self.show_info, tr("Sensor info"));
+                    ui.checkbox(&mut self.show_tail, tr("Log tail"));
+                    ui.checkbox(&mut self.show_console, tr("Console"));
+                    ui.checkbox(&mut self.show_traffic, tr("Serial traffic"));
+                    ui.checkbox(&mut self.notifications, tr("Desktop notifications"));
+                    if Alarm::AVAILABLE {
+                        ui.checkbox(&mut self.audible_alarm, tr("Audible alarm"));
+                    }
+
+                    ui.separator();
+                    ui.menu_button(tr("Display units"), |ui| self.display_units_menu(ui));
+                });
+
+                ui.menu_button(tr("Help"), |ui| {
+                    ui.strong(tr("Keyboard shortcuts"));
+                    egui::Grid::new("shortcuts").show(ui, |ui| {
+                        for (key, action) in SHORTCUTS {
+                            ui.monospace(key);
+                            ui.label(tr(action));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        });
+
+        // Top control panel
+        TopBottomPanel::top("controls").show(ctx, |ui| {
+            Frame::default()
+                .inner_margin(Margin {
+                    left: 2,
+                    right: 2,
+                    top: 2,
+                    bottom: 2,
+                }) // top, bottom margins
+                .show(ui, |ui| {
+                    // Make text larger
+                    ui.style_mut()
+                        .text_styles
+                        .get_mut(&egui::TextStyle::Button)
+                        .unwrap()
+                        .size = 20.0;
+                    ui.style_mut()
+                        .text_styles
+                        .get_mut(&egui::TextStyle::Body)
+                        .unwrap()
+                        .size = 20.0;
+
+                    // Make widgets taller/wider
+                    ui.style_mut().spacing.interact_size.y = 30.0;
+
+                    ui.horizontal(|ui| {
+                        // Dropdown
+                        ui.label(tr("Sensor"));
+                        ComboBox::from_id_salt("sensor_dropdown")
+                            .selected_text(
+                                self.controller
+                                    .sensor()
+                                    .map(|s| s.as_ref().to_string())
+                                    .unwrap_or_default(),
+                            )
+                            .show_ui(ui, |ui| {
+                                for (idx, sensor) in self.controller.sensors.iter().enumerate() {
+                                    ui.selectable_value(
+                                        &mut self.controller.sensor_choice,
+                                        idx,
+                                        sensor.as_ref(),
+                                    );
+                                }
+                            });
+
+                        if self.controller.sensor() == Some(SensorModel::RYDASON) {
+                            ui.label(tr("Address"));
+                            ui.add(egui::DragValue::new(&mut self.rydason_address).range(1..=247));
+                        }
+
+                        ui.label(tr("Port"));
+                        ComboBox::from_id_salt("port_dropdown")
+                            .selected_text(
+                                self.controller.port().unwrap_or(tr("No available port")),
+                            )
+                            .show_ui(ui, |ui| {
+                                for (idx, port) in self.controller.ports.iter().enumerate() {
+                                    ui.selectable_value(
+                                        &mut self.controller.port_choice,
+                                        idx,
+                                        port,
+                                    );
+                                }
+                            });
+                        if ui.button("⟳").on_hover_text(tr("Refresh ports")).clicked() {
+                            let added = self.controller.set_ports(serial_port_list());
+                            self.report_ports(added);
+                        }
+                        ui.toggle_value(&mut self.show_serial, "⚙")
+                            .on_hover_text(tr("Serial settings"));
+
+                        ui.label(tr("Notes"));
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.notes)
+                                .hint_text(tr("Stored with the log"))
+                                .desired_width(200.0),
+                        );
+
+                        ui.checkbox(&mut self.logging, tr("Log"));
+                        if self.logging {
+                            if ui
+                                .button("📁")
+                                .on_hover_text(format!("Log folder: {}", self.log_dir.display()))
+                                .clicked()
+                                && let Some(dir) = rfd::FileDialog::new()
+                                    .set_directory(&self.log_dir)
+                                    .pick_folder()
+                            {
+                                self.log_dir = dir;
+                            }
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.log_name)
+                                    .hint_text(tr("File name"))
+                                    .desired_width(120.0),
+                            )
+                            .on_hover_text(tr(
+                                "Left empty, the file is named after the start time and sensor",
+                            ));
+                        }
+
+                        ui.checkbox(&mut self.timed, tr("Run for"));
+                        if self.timed {
+                            ui.add(egui::DragValue::new(&mut self.run_length).range(1..=9999));
+                            ComboBox::from_id_salt("run_unit")
+                                .width(60.0)
+                                .selected_text(match self.run_unit {
+                                    RunUnit::Minutes => "min",
+                                    RunUnit::Hours => "h",
+                                })
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        &mut self.run_unit,
+                                        RunUnit::Minutes,
+                                        "min",
+                                    );
+                                    ui.selectable_value(&mut self.run_unit, RunUnit::Hours, "h");
+                                });
+                        }
+
+                        // Every start adds a station, running ones are stopped from their tab
+                        if ui.button(tr("▶ Start")).on_hover_text("Space").clicked() {
+                            self.start_sensor();
+                        }
+                    });
+
+                    if self.show_serial {
+                        ui.horizontal(|ui| serial_settings(ui, &mut self.serial));
+                    }
+                });
+        });
+
+        self.shortcuts(ctx);
+
+        if let Some(added) = self.controller.update_ports() {
+            self.report_ports(added);
+        }
+
+        for (idx, msg) in self.controller.poll() {
+            let station = &mut self.controller.stations[idx];
+            // Only breaches beep, a channel coming back or an anomaly is just reported
+            let (status, level, alarm) = match msg {
+                AppMsg::Status(s) => (s, Level::Info, false),
+                AppMsg::Error(s) => (s, Level::Error, true),
+                AppMsg::Alert(alert) => (alert.to_string(), Level::Alert, alert.breach.is_some()),
+                AppMsg::Anomaly(anomaly) => (anomaly.to_string(), Level::Alert, false),
+                AppMsg::Marker(marker) => {
+                    let text = format!("Marker \"{}\"", marker.label);
+                    station.markers.push(marker);
+                    (text, Level::Info, false)
+                }
+                AppMsg::Traffic(frame) => {
+                    if !self.traffic.paused {
+                        station.push_traffic(frame);
+                    }
+                    continue;
+                }
+                AppMsg::Sample(_) | AppMsg::Aggregate(_) => continue,
+            };
+            if level != Level::Info && self.notifications {
+                notify(station.name.clone(), status.clone());
+            }
+            if alarm {
+                self.idle.wake();
+                if self.audible_alarm {
+                    self.alarm.beep();
+                }
+            }
+            self.console
+                .push(level, format!("{}: {status}", station.name));
+        }
+
+        if self.show_tiles && !self.controller.stations.is_empty() {
+            TopBottomPanel::top("tiles").show(ctx, |ui| match self.layout {
+                Layout::Tabs => {
+                    if let Some(s) = self.controller.active_station() {
+                        value_tiles(
+                            ui,
+                            &s.channels,
+                            &s.history,
+                            &s.display_thresholds(&self.settings.sensor.thresholds),
+                        );
+                    }
+                }
+                Layout::Grid => {
+                    for s in &self.controller.stations {
+                        value_tiles(
+                            ui,
+                            &s.channels,
+                            &s.history,
+                            &s.display_thresholds(&self.settings.sensor.thresholds),
+                        );
+                    }
+                }
+            });
+        }
+
+        if let Some(draft) = &mut self.settings_draft
+            && let Some(save) = settings_dialog(ctx, draft)
+        {
+            let draft = self.settings_draft.take();
+            if save && let Some(settings) = draft {
+                ctx.set_theme(settings.theme);
+                set_language(settings.sensor.language);
+                self.idle.set_timeout(settings.sensor.dim_timeout());
+                if let Err(e) = settings.save() {
+                    self.console.error(format!("Failed to save settings: {e}"));
+                }
+                self.settings = settings;
+            }
+        }
+
+        if let Some(wizard) = &mut self.calibration {
+            let mut open = true;
+            let saved = egui::Window::new(tr("Calibration wizard"))
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    wizard.ui(
+                        ui,
+                        &self.controller.stations,
+                        &self.settings.sensor.calibration,
+                    )
+                })
+                .and_then(|response| response.inner.flatten());
+
+            if let Some(saved) = saved {
+                let stored = &mut self.settings.sensor.calibration;
+                stored.retain(|c| !c.is_for(saved.model, &saved.device, &saved.channel));
+                stored.push(saved.clone());
+                let calibration = saved.calibration;
+                match self.settings.save() {
+                    Ok(()) => self.console.info(format!(
+                        "Saved {} calibration of {} {}: slope {:.4}, offset {:+.3} {}",
+                        saved.channel,
+                        saved.model.as_ref(),
+                        saved.device,
+                        calibration.slope,
+                        calibration.offset,
+                        calibration.unit.as_ref()
+                    )),
+                    Err(e) => self.console.error(format!("Failed to save settings: {e}")),
+                }
+                open = false;
+            }
+            if !open {
+                self.calibration = None;
+            }
+        }
+
+        egui::Window::new(tr("Serial traffic"))
+            .open(&mut self.show_traffic)
+            .default_size([480.0, 320.0])
+            .show(ctx, |ui| match self.controller.active_station() {
+                Some(s) => {
+                    if s.sensor.as_ref().is_some_and(|s| !s.config().capture) {
+                        ui.label(tr("Captured from the next start of the sensor on"));
+                    }
+                    self.traffic.ui(ui, &s.traffic);
+                }
+                None => {
+                    ui.label(tr("No sensor"));
+                }
+            });
+
+        if self.show_info
+            && let Some(s) = self.controller.active_station()
+            && let (Some(sensor), Some(port)) = (&s.sensor, &s.port)
+        {
+            SidePanel::right("info").show(ctx, |ui| {
+                ui.heading(tr("Sensor"));
+                let info = sensor.info();
+                info_panel(ui, sensor.model(), port, info.as_ref(), &s.channels);
+            });
+        }
+
+        if self.show_stats {
+            SidePanel::right("stats").show(ctx, |ui| {
+                ScrollArea::vertical().show(ui, |ui| {
+                    if let Some(s) = self.controller.active_station() {
+                        ui.heading(&s.name);
+                        stats_panel(ui, &s.channels, &s.history, self.view, &s.session_stats);
+                    }
+                });
+            });
+        }
+
+        if self.show_compare {
+            TopBottomPanel::bottom("compare")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    ui.strong(tr("Sensor comparison"));
+                    let from = self.view.and_then(|(min, _)| from_x(min));
+                    ScrollArea::vertical().show(ui, |ui| {
+                        comparison_panel(ui, &self.controller.stations, from);
+                    });
+                });
+        }
+
+        if self.show_tail {
+            TopBottomPanel::bottom("log_tail")
+                .resizable(true)
+                .default_height(140.0)
+                .show(ctx, |ui| {
+                    ui.strong(tr("Log tail"));
+                    let path = self.controller.active_station().and_then(Station::log_path);
+                    self.tail.ui(ui, path);
+                });
+        }
+
+        if self.show_console {
+            TopBottomPanel::bottom("console")
+                .resizable(true)
+                .default_height(150.0)
+                .show(ctx, |ui| self.console.ui(ui));
+        }
+
+        // Chart in central panel
+        CentralPanel::default().show(ctx, |ui| {
+            Frame::default()
+                .inner_margin(Margin {
+                    left: 2,
+                    right: 2,
+                    top: 2,
+                    bottom: 2 + 20, /* for status bar */
+                })
+                .show(ui, |ui| {
+                    self.error_banners(ui);
+                    self.station_tabs(ui);
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut self.tab, Tab::Plot, tr("📈 Plot"));
+                        ui.selectable_value(&mut self.tab, Tab::Table, tr("📋 Table"));
+                        ui.selectable_value(&mut self.tab, Tab::Histogram, tr("📊 Histogram"));
+                        ui.selectable_value(&mut self.tab, Tab::Heatmap, tr("🗓 Daily pattern"));
+                    });
+                    ui.separator();
+
+                    match self.tab {
+                        Tab::Plot => self.plot_area(ui),
+                        Tab::Table => {
+                            if let Some(s) = self.controller.active_station() {
+                                sample_table(ui, &s.channels, &s.history);
+                            }
+                        }
+                        // Over the time range last shown in the plot
+                        Tab::Histogram => {
+                            if let Some(s) = self.controller.active_station() {
+                                self.histogram.ui(ui, &s.channels, &s.history, self.view);
+                            }
+                        }
+                        Tab::Heatmap => {
+                            if let Some(s) = self.controller.active_station() {
+                                self.heatmap
+                                    .ui(ui, &s.channels, &s.history, &s.minute_means);
+                            }
+                        }
+                    }
+                });
+        });
+
+        // Status bar at the bottom
+        TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal_centered(|ui| {
+                ui.toggle_value(&mut self.show_console, "🗒")
+                    .on_hover_text(tr("Message history"));
+                ui.label(RichText::new(self.console.latest()).color(
+                    if ctx.style().visuals.dark_mode {
+                        Color32::WHITE
+                    } else {
+                        Color32::BLACK
+                    },
+                ));
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if let Some(station) = self.controller.active_station()
+                        && station.is_running()
+                    {
+                        acquisition_status(ui, station);
+                        ui.separator();
+                    }
+                    for s in &self.controller.stations {
+                        if let Some(left) = s.remaining() {
+                            ui.label(format!(
+                                "⏱ {} {} {}",
+                                s.name,
+                                format_duration(left),
+                                tr("left")
+                            ));
+                        }
+                    }
+                });
+            });
+        });
+
+        if self.kiosk {
+            self.exit_guard(ctx);
+        } else {
+            // Settings are saved as they change, the geometry only on exit as it changes
+            // continuously while the window is dragged
+            self.geometry = Geometry::of(ctx).or(self.geometry);
+        }
+        let prefs = Prefs {
+            geometry: self.saved_prefs.geometry,
+            ..self.prefs()
+        };
+        if prefs != self.saved_prefs {
+            self.save_prefs(prefs);
+        }
+
+        if dimmed {
+            dim_overlay(ctx);
+        }
+        if ctx.input(|i| i.events.iter().any(is_user_input)) {
+            self.idle.wake();
+        }
+
+        // request redraw
+        ctx.request_repaint_after(Duration::from_millis(100));
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.save_prefs(self.prefs());
+        self.controller.shut_down();
+    }
+}
@@ -0,0 +1,19 @@
+use std::thread;
+
+use notify_rust::Notification;
+
+/// Raise a desktop notification, shown even while the window is minimized
+///
+/// Sent from a thread of its own as the notification service may be slow to answer.
+pub fn notify(summary: String, body: String) {
+    thread::spawn(move || {
+        if let Err(e) = Notification::new()
+            .appname("EnvSensor Demo")
+            .summary(&summary)
+            .body(&body)
+            .show()
+        {
+            eprintln!("Failed to show notification: {e}");
+        }
+    });
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use egui::{ThemePreference, ViewportBuilder};
+use serde::{Deserialize, Serialize};
+
+use envsensor_demo::{
+    gui::plot::{TimeWindow, YScale},
+    sensor::Unit,
+};
+
+use crate::Layout;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum Theme {
+    #[default]
+    System,
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::System, Theme::Dark, Theme::Light];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Theme::System => "Follow system",
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+        }
+    }
+}
+
+impl From<Theme> for ThemePreference {
+    fn from(theme: Theme) -> Self {
+        match theme {
+            Theme::System => ThemePreference::System,
+            Theme::Dark => ThemePreference::Dark,
+            Theme::Light => ThemePreference::Light,
+        }
+    }
+}
+
+/// Position of the window's outer corner and size of its content area, in points
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Geometry {
+    pub pos: [f32; 2],
+    pub size: [f32; 2],
+    pub maximized: bool,
+}
+
+impl Geometry {
+    /// Current geometry of the window, `None` until the platform reports it
+    pub fn of(ctx: &egui::Context) -> Option<Self> {
+        ctx.input(|i| {
+            let viewport = i.viewport();
+            Some(Self {
+                pos: viewport.outer_rect?.min.into(),
+                size: viewport.inner_rect?.size().into(),
+                maximized: viewport.maximized.unwrap_or(false),
+            })
+        })
+    }
+
+    pub fn apply(&self, builder: ViewportBuilder) -> ViewportBuilder {
+        builder
+            .with_position(self.pos)
+            .with_inner_size(self.size)
+            .with_maximized(self.maximized)
+    }
+}
+
+/// UI choices restored on the next launch
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Prefs {
+    /// Last sensor model started, by name
+    pub sensor: Option<String>,
+    pub port: Option<String>,
+    pub rydason_address: u8,
+    pub logging: bool,
+    pub log_dir: PathBuf,
+    pub layout: Layout,
+    pub window: TimeWindow,
+    pub y_scale: YScale,
+    pub show_tiles: bool,
+    pub show_stats: bool,
+    pub show_compare: bool,
+    pub show_info: bool,
+    pub show_tail: bool,
+    pub notifications: bool,
+    pub audible_alarm: bool,
+    /// Moving average window of the plot lines
+    pub smoothing: usize,
+    /// Display unit per channel, by `channel_key`, the log keeps the delivered one
+    pub display_units: BTreeMap<String, Unit>,
+    /// Channels switched off in the plot legend, per sensor model name
+    pub hidden_channels: BTreeMap<String, BTreeSet<String>>,
+    pub geometry: Option<Geometry>,
+}
+
+impl Default for Prefs {
+    fn default() -> Self {
+        Self {
+            sensor: None,
+            port: None,
+            rydason_address: 1,
+            logging: true,
+            log_dir: PathBuf::from("."),
+            layout: Layout::Tabs,
+            window: TimeWindow::TenMinutes,
+            y_scale: YScale::Shared,
+            show_tiles: true,
+            show_stats: true,
+            show_compare: false,
+            show_info: false,
+            show_tail: false,
+            notifications: true,
+            audible_alarm: false,
+            smoothing: 1,
+            display_units: BTreeMap::new(),
+            hidden_channels: BTreeMap::new(),
+            geometry: None,
+        }
+    }
+}
+
+fn prefs_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("envsensor-demo").join("prefs.json"))
+}
+
+impl Prefs {
+    /// Saved preferences, the defaults when there are none or they can't be read
+    pub fn load() -> Self {
+        let Some(path) = prefs_path() else {
+            return Self::default();
+        };
+
+        fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| {
+                serde_json::from_str(&text)
+                    .inspect_err(|e| eprintln!("Ignoring {}: {e}", path.display()))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = prefs_path().context("No configuration directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use egui::{ComboBox, DragValue};
+use serialport::{Parity, StopBits};
+
+use envsensor_demo::sensor::SerialSettings;
+
+const BAUD_RATES: [u32; 8] = [2400, 4800, 9600, 19200, 38400, 57600, 115200, 230400];
+
+fn or_default<T: std::fmt::Debug>(value: &Option<T>) -> String {
+    value
+        .as_ref()
+        .map(|v| format!("{v:?}"))
+        .unwrap_or_else(|| "Driver default".to_string())
+}
+
+/// Advanced serial line overrides, left at "Driver default" the driver's settings apply
+pub fn serial_settings(ui: &mut egui::Ui, serial: &mut SerialSettings) {
+    ui.label("Baud");
+    ComboBox::from_id_salt("baud_rate")
+        .selected_text(or_default(&serial.baud_rate))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut serial.baud_rate, None, "Driver default");
+            for baud in BAUD_RATES {
+                ui.selectable_value(&mut serial.baud_rate, Some(baud), baud.to_string());
+            }
+        });
+
+    ui.label("Parity");
+    ComboBox::from_id_salt("parity")
+        .selected_text(or_default(&serial.parity))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut serial.parity, None, "Driver default");
+            for parity in [Parity::None, Parity::Odd, Parity::Even] {
+                ui.selectable_value(&mut serial.parity, Some(parity), format!("{parity:?}"));
+            }
+        });
+
+    ui.label("Stop bits");
+    ComboBox::from_id_salt("stop_bits")
+        .selected_text(or_default(&serial.stop_bits))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut serial.stop_bits, None, "Driver default");
+            for bits in [StopBits::One, StopBits::Two] {
+                ui.selectable_value(&mut serial.stop_bits, Some(bits), format!("{bits:?}"));
+            }
+        });
+
+    // 0 keeps the driver's timeout
+    ui.label("Timeout");
+    let mut secs = serial.timeout.map_or(0.0, |t| t.as_secs_f32());
+    if ui
+        .add(
+            DragValue::new(&mut secs)
+                .range(0.0..=60.0)
+                .speed(0.1)
+                .suffix(" s"),
+        )
+        .changed()
+    {
+        serial.timeout = (secs > 0.0).then(|| Duration::from_secs_f32(secs));
+    }
+}
@@ -0,0 +1,423 @@
+use std::fs;
+
+use anyhow::{Context, Result};
+use egui::{Button, ComboBox, DragValue, Grid, Id, Modal};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use envsensor_demo::{
+    alert::Threshold,
+    anomaly::AnomalyConfig,
+    convert::{convert, is_concentration},
+    i18n::{Language, tr},
+    logger::TimestampFormat,
+    process::{AutoZero, Compensation, Despike, DespikeMode, HumidityCorrection},
+    sensor::{SensorType, Unit},
+    settings::Settings,
+};
+
+use crate::prefs::Theme;
+use crate::thresholds::threshold_editor;
+
+/// The sensor settings plus the theme, stored in the same TOML file
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct GuiSettings {
+    pub theme: Theme,
+    #[serde(flatten)]
+    pub sensor: Settings,
+}
+
+impl Default for GuiSettings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            sensor: Settings {
+                thresholds: SensorType::iter().map(Threshold::new).collect(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl GuiSettings {
+    /// Saved settings, the defaults when there are none or they can't be read
+    pub fn load() -> Self {
+        let Some(path) = Settings::default_path() else {
+            return Self::default();
+        };
+
+        fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| {
+                toml::from_str::<Self>(&text)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|settings| settings.sensor.validate().map(|()| settings))
+                    .inspect_err(|e| eprintln!("Ignoring {}: {e}", path.display()))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Settings::default_path().context("No configuration directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, toml::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+}
+
+const TIMESTAMP_FORMATS: [(&str, TimestampFormat); 3] = [
+    ("MM/DD/YYYY hh:mm:ss", TimestampFormat::Legacy),
+    ("RFC 3339, local time", TimestampFormat::Rfc3339),
+    ("RFC 3339, UTC", TimestampFormat::Utc),
+];
+
+fn timestamp_label(format: &TimestampFormat) -> &'static str {
+    TIMESTAMP_FORMATS
+        .iter()
+        .find(|(_, f)| f == format)
+        .map_or("Custom", |(label, _)| label)
+}
+
+fn units_editor(ui: &mut egui::Ui, settings: &mut Settings) {
+    Grid::new("units").striped(true).show(ui, |ui| {
+        for ty in SensorType::iter().filter(|&ty| is_concentration(ty)) {
+            ui.label(ty.as_ref());
+
+            let mut unit = settings.units.get(&ty).copied();
+            // Particles have no molar mass, only mass concentrations apply
+            let units = Unit::iter().filter(|&u| convert(1.0, ty, Unit::UgPerM3, u).is_some());
+            ComboBox::from_id_salt(("unit", ty.as_ref()))
+                .selected_text(unit.as_ref().map_or(tr("As reported"), |u| u.as_ref()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut unit, None, tr("As reported"));
+                    for u in units {
+                        ui.selectable_value(&mut unit, Some(u), u.as_ref());
+                    }
+                });
+            match unit {
+                Some(unit) => settings.units.insert(ty, unit),
+                None => settings.units.remove(&ty),
+            };
+            ui.end_row();
+        }
+    });
+}
+
+fn despike_label(mode: Option<DespikeMode>) -> &'static str {
+    match mode {
+        None => "Off",
+        Some(DespikeMode::Flag) => "Flag",
+        Some(DespikeMode::Replace) => "Replace",
+    }
+}
+
+/// Manual ambient conditions of the compensation, off when unchecked
+fn compensation_editor(ui: &mut egui::Ui, compensation: &mut Option<Compensation>) {
+    ui.horizontal(|ui| {
+        let mut enabled = compensation.is_some();
+        if ui
+            .checkbox(&mut enabled, "")
+            .on_hover_text(tr(
+                "Convert gases at these conditions unless a running sensor measures the \
+                 temperature",
+            ))
+            .changed()
+        {
+            *compensation = enabled.then(Compensation::default);
+        }
+        if let Some(compensation) = compensation {
+            let ambient = &mut compensation.manual;
+            ui.add(
+                DragValue::new(&mut ambient.temperature)
+                    .range(-40.0..=60.0)
+                    .speed(0.1)
+                    .suffix(" °C"),
+            );
+            ui.add(
+                DragValue::new(&mut ambient.pressure)
+                    .range(500.0..=1100.0)
+                    .speed(1.0)
+                    .suffix(" hPa"),
+            );
+            ui.checkbox(
+                &mut compensation.pm_standard,
+                tr("PM at standard conditions"),
+            );
+        }
+    });
+}
+
+/// Rolling z-score alerts, off when unchecked
+fn anomaly_editor(ui: &mut egui::Ui, anomaly: &mut Option<AnomalyConfig>) {
+    ui.horizontal(|ui| {
+        let mut enabled = anomaly.is_some();
+        if ui
+            .checkbox(&mut enabled, tr("Anomaly alerts"))
+            .on_hover_text(tr(
+                "Alert on readings this many standard deviations from their recent mean",
+            ))
+            .changed()
+        {
+            *anomaly = enabled.then(AnomalyConfig::default);
+        }
+        if let Some(anomaly) = anomaly {
+            ui.add(
+                DragValue::new(&mut anomaly.sigmas)
+                    .range(2.0..=10.0)
+                    .speed(0.1)
+                    .suffix(" σ"),
+            );
+            ui.add(
+                DragValue::new(&mut anomaly.window)
+                    .range(10..=3600)
+                    .suffix(" samples"),
+            );
+        }
+    });
+}
+
+fn humidity_correction_label(correction: Option<HumidityCorrection>) -> &'static str {
+    match correction {
+        None => "Off",
+        Some(HumidityCorrection::Kohler { .. }) => "κ-Köhler",
+        Some(HumidityCorrection::Empirical { .. }) => "Empirical",
+    }
+}
+
+/// Model of the derived humidity corrected PM channels, with its parameters
+fn humidity_correction_editor(ui: &mut egui::Ui, correction: &mut Option<HumidityCorrection>) {
+    ui.horizontal(|ui| {
+        ComboBox::from_id_salt("humidity_correction")
+            .selected_text(tr(humidity_correction_label(*correction)))
+            .show_ui(ui, |ui| {
+                for choice in [
+                    None,
+                    Some(HumidityCorrection::default()),
+                    Some(HumidityCorrection::Empirical { a: 0.25, b: 2.0 }),
+                ] {
+                    let label = humidity_correction_label(choice);
+                    if ui
+                        .selectable_label(
+                            humidity_correction_label(*correction) == label,
+                            tr(label),
+                        )
+                        .clicked()
+                    {
+                        *correction = choice;
+                    }
+                }
+            })
+            .response
+            .on_hover_text(tr(
+                "Adds PM channels corrected for the water particles take up in humid air",
+            ));
+        match correction {
+            Some(HumidityCorrection::Kohler { kappa }) => {
+                ui.label("κ");
+                ui.add(DragValue::new(kappa).range(0.0..=1.5).speed(0.01));
+            }
+            Some(HumidityCorrection::Empirical { a, b }) => {
+                ui.label("a");
+                ui.add(DragValue::new(a).range(0.0..=5.0).speed(0.01));
+                ui.label("b");
+                ui.add(DragValue::new(b).range(0.0..=5.0).speed(0.01));
+            }
+            None => {}
+        }
+    });
+}
+
+fn despike_editor(ui: &mut egui::Ui, despike: &mut Option<Despike>) {
+    let mode = despike.map(|d| d.mode);
+    ComboBox::from_id_salt("despike")
+        .selected_text(tr(despike_label(mode)))
+        .show_ui(ui, |ui| {
+            for choice in [None, Some(DespikeMode::Flag), Some(DespikeMode::Replace)] {
+                if ui
+                    .selectable_label(mode == choice, tr(despike_label(choice)))
+                    .clicked()
+                {
+                    *despike = choice.map(|mode| Despike {
+                        mode,
+                        ..despike.unwrap_or_default()
+                    });
+                }
+            }
+        })
+        .response
+        .on_hover_text(tr(
+            "Flagged spikes are marked, replaced ones get the median",
+        ));
+}
+
+fn log_editor(ui: &mut egui::Ui, settings: &mut Settings) {
+    Grid::new("log").show(ui, |ui| {
+        ui.label(tr("Timestamp"));
+        ui.horizontal(|ui| {
+            let format = &mut settings.timestamp;
+            ComboBox::from_id_salt("timestamp")
+                .selected_text(timestamp_label(format))
+                .show_ui(ui, |ui| {
+                    for (label, f) in TIMESTAMP_FORMATS {
+                        ui.selectable_value(format, f, label);
+                    }
+                    if ui
+                        .selectable_label(matches!(format, TimestampFormat::Custom(_)), "Custom")
+                        .clicked()
+                        && !matches!(format, TimestampFormat::Custom(_))
+                    {
+                        *format = TimestampFormat::Custom("%Y-%m-%d %H:%M:%S".to_string());
+                    }
+                });
+            if let TimestampFormat::Custom(pattern) = format {
+                ui.text_edit_singleline(pattern)
+                    .on_hover_text("chrono strftime pattern");
+            }
+        });
+        ui.end_row();
+
+        if let Err(e) = settings.timestamp.validate() {
+            ui.label("");
+            ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+            ui.end_row();
+        }
+
+        ui.label(tr("Flush every"));
+        ui.add(
+            DragValue::new(&mut settings.flush_interval)
+                .range(1..=3600)
+                .suffix(" s"),
+        );
+        ui.end_row();
+
+        ui.label(tr("Crash journal"));
+        ui.checkbox(&mut settings.wal, "")
+            .on_hover_text(tr("Syncs every row to disk so a crash loses nothing"));
+        ui.end_row();
+
+        ui.label(tr("Rolling means"));
+        ui.checkbox(&mut settings.aggregate, "").on_hover_text(tr(
+            "1-min, 15-min and 1-h means in a summary CSV, the daily pattern covers the whole run",
+        ));
+        ui.end_row();
+    });
+}
+
+/// Modal editor of a copy of the settings, `Some(true)` once saved, `Some(false)` on cancel
+pub fn settings_dialog(ctx: &egui::Context, draft: &mut GuiSettings) -> Option<bool> {
+    let modal = Modal::new(Id::new("settings")).show(ctx, |ui| {
+        ui.set_width(380.0);
+        ui.heading(tr("Settings"));
+
+        ui.separator();
+        ui.strong(tr("Acquisition"));
+        Grid::new("acquisition").show(ui, |ui| {
+            ui.label(tr("Poll interval"));
+            ui.add(
+                DragValue::new(&mut draft.sensor.poll_interval)
+                    .range(0.0..=3600.0)
+                    .speed(0.1)
+                    .suffix(" s"),
+            );
+            ui.end_row();
+
+            ui.label(tr("Smoothing"));
+            ui.add(
+                DragValue::new(&mut draft.sensor.smoothing)
+                    .range(0..=600)
+                    .suffix(" samples"),
+            );
+            ui.end_row();
+
+            ui.label(tr("Spike rejection"));
+            despike_editor(ui, &mut draft.sensor.despike);
+            ui.end_row();
+
+            ui.label(tr("Compensation"));
+            compensation_editor(ui, &mut draft.sensor.compensation);
+            ui.end_row();
+
+            ui.label(tr("Humidity correction"));
+            humidity_correction_editor(ui, &mut draft.sensor.humidity_correction);
+            ui.end_row();
+
+            ui.label(tr("Auto-zero"));
+            let mut auto_zero = draft.sensor.auto_zero.is_some();
+            if ui
+                .checkbox(&mut auto_zero, "")
+                .on_hover_text(tr(
+                    "Zero the gas channels on long stable stretches of clean air",
+                ))
+                .changed()
+            {
+                draft.sensor.auto_zero = auto_zero.then(AutoZero::default);
+            }
+            ui.end_row();
+        });
+
+        ui.separator();
+        ui.strong(tr("Units"));
+        units_editor(ui, &mut draft.sensor);
+
+        ui.separator();
+        ui.strong(tr("Thresholds"));
+        threshold_editor(ui, &mut draft.sensor.thresholds);
+        anomaly_editor(ui, &mut draft.sensor.anomaly);
+
+        ui.separator();
+        ui.strong(tr("Log"));
+        log_editor(ui, &mut draft.sensor);
+
+        ui.separator();
+        ui.strong(tr("Appearance"));
+        Grid::new("appearance").show(ui, |ui| {
+            ui.label(tr("Theme"));
+            ui.horizontal(|ui| {
+                for theme in Theme::ALL {
+                    ui.radio_value(&mut draft.theme, theme, tr(theme.label()));
+                }
+            });
+            ui.end_row();
+
+            ui.label(tr("Language"));
+            let language = &mut draft.sensor.language;
+            ComboBox::from_id_salt("language")
+                .selected_text(language.label())
+                .show_ui(ui, |ui| {
+                    for l in Language::ALL {
+                        ui.selectable_value(language, l, l.label());
+                    }
+                });
+            ui.end_row();
+
+            ui.label(tr("Dim the display after"));
+            ui.add(
+                DragValue::new(&mut draft.sensor.dim_after)
+                    .range(0..=240)
+                    .suffix(" min"),
+            )
+            .on_hover_text(tr("0 keeps it bright, input or an alert wakes it"));
+            ui.end_row();
+        });
+
+        ui.separator();
+        ui.label(tr("Changes apply to sensors started from now on"));
+        ui.horizontal(|ui| {
+            let valid = draft.sensor.validate().is_ok();
+            let save = ui.add_enabled(valid, Button::new(tr("Save"))).clicked();
+            let cancel = ui.button(tr("Cancel")).clicked();
+            (save || cancel).then_some(save)
+        })
+        .inner
+    });
+
+    // Escape or a click outside closes it like Cancel
+    modal
+        .inner
+        .or_else(|| modal.should_close().then_some(false))
+}
@@ -0,0 +1,121 @@
+use egui::{Grid, RichText};
+
+use envsensor_demo::{
+    gui::plot::from_x,
+    history::History,
+    sensor::SensorChannel,
+    stats::{ChannelStats, RunningStats},
+};
+
+fn stats_row(ui: &mut egui::Ui, label: &str, stats: Option<ChannelStats>) {
+    ui.label(label);
+    match stats {
+        Some(s) => {
+            for v in [s.min, s.max, s.mean, s.stddev] {
+                ui.monospace(format!("{v:.2}"));
+            }
+        }
+        None => (0..4).for_each(|_| {
+            ui.label("-");
+        }),
+    }
+    ui.end_row();
+}
+
+/// The statistics of all channels as a tab separated table
+fn stats_tsv(
+    channels: &[SensorChannel],
+    history: &History,
+    visible: &[Option<ChannelStats>],
+    session: &[RunningStats],
+) -> String {
+    let mut text = "Channel\tRange\tMin\tMax\tMean\tStd dev\tCurrent".to_string();
+
+    for (idx, ch) in channels.iter().enumerate() {
+        let current = history
+            .latest()
+            .and_then(|s| s.data.get(idx))
+            .map(|d| d.value.to_string())
+            .unwrap_or_default();
+        let ranges = [
+            ("Visible", visible.get(idx).copied().flatten()),
+            ("Session", session.get(idx).and_then(RunningStats::summary)),
+        ];
+
+        for (range, stats) in ranges {
+            text.push_str(&format!("\n{}({})\t{range}", ch.name(), ch.unit.as_ref()));
+            match stats {
+                Some(s) => {
+                    for v in [s.min, s.max, s.mean, s.stddev] {
+                        text.push_str(&format!("\t{v}"));
+                    }
+                }
+                None => text.push_str("\t\t\t\t"),
+            }
+            text.push_str(&format!("\t{current}"));
+        }
+    }
+
+    text
+}
+
+/// Per-channel statistics of the visible plot range and of the whole session
+pub fn stats_panel(
+    ui: &mut egui::Ui,
+    channels: &[SensorChannel],
+    history: &History,
+    view: Option<(f64, f64)>,
+    session: &[RunningStats],
+) {
+    let (from, to) = match view {
+        Some((min, max)) => (from_x(min), from_x(max)),
+        None => (None, None),
+    };
+
+    let visible = (0..channels.len())
+        .map(|idx| {
+            ChannelStats::from_values(
+                history
+                    .range(from, to)
+                    .filter_map(|s| s.data.get(idx))
+                    .map(|d| d.value),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if ui.button("📋 Copy").clicked() {
+        ui.ctx()
+            .copy_text(stats_tsv(channels, history, &visible, session));
+    }
+
+    for (idx, ch) in channels.iter().enumerate() {
+        let current = history.latest().and_then(|s| s.data.get(idx));
+
+        ui.horizontal(|ui| {
+            ui.strong(format!("{} ({})", ch.name(), ch.unit.as_ref()));
+            if let Some(d) = current {
+                ui.label(
+                    RichText::new(format!("{:.2}", d.value))
+                        .monospace()
+                        .strong(),
+                );
+            }
+        });
+
+        Grid::new(("stats", idx)).striped(true).show(ui, |ui| {
+            for header in ["", "Min", "Max", "Mean", "σ"] {
+                ui.label(header);
+            }
+            ui.end_row();
+
+            stats_row(ui, "Visible", visible[idx]);
+            stats_row(
+                ui,
+                "Session",
+                session.get(idx).and_then(RunningStats::summary),
+            );
+        });
+
+        ui.separator();
+    }
+}
@@ -0,0 +1,69 @@
+use egui::{Grid, ScrollArea};
+
+use envsensor_demo::{history::History, sensor::SensorChannel};
+
+/// Rows listed in the table, newest first
+const TABLE_ROWS: usize = 1000;
+
+/// Tab separated rows, pastes straight into a spreadsheet
+fn to_tsv(channels: &[SensorChannel], history: &History) -> String {
+    let mut text = std::iter::once("Timestamp".to_string())
+        .chain(
+            channels
+                .iter()
+                .map(|ch| format!("{}({})", ch.name(), ch.unit.as_ref())),
+        )
+        .collect::<Vec<_>>()
+        .join("\t");
+
+    for sample in history.iter().rev().take(TABLE_ROWS) {
+        text.push('\n');
+        text.push_str(&sample.timestamp.format("%Y-%m-%d %H:%M:%S").to_string());
+        for d in &sample.data {
+            text.push_str(&format!("\t{}", d.value));
+        }
+    }
+
+    text
+}
+
+/// The most recent samples with all channel values
+pub fn sample_table(ui: &mut egui::Ui, channels: &[SensorChannel], history: &History) {
+    let rows = history.len().min(TABLE_ROWS);
+
+    ui.horizontal(|ui| {
+        ui.label(format!("Last {rows} samples"));
+        if ui.button("📋 Copy").clicked() {
+            ui.ctx().copy_text(to_tsv(channels, history));
+        }
+    });
+
+    Grid::new("sample_table_header")
+        .min_col_width(100.0)
+        .show(ui, |ui| {
+            ui.strong("Time");
+            for ch in channels {
+                ui.strong(format!("{} ({})", ch.name(), ch.unit.as_ref()));
+            }
+            ui.end_row();
+        });
+
+    let row_height = ui.text_style_height(&egui::TextStyle::Body) + 4.0;
+    ScrollArea::vertical()
+        .auto_shrink(false)
+        .show_rows(ui, row_height, rows, |ui, range| {
+            Grid::new("sample_table")
+                .striped(true)
+                .min_col_width(100.0)
+                .start_row(range.start)
+                .show(ui, |ui| {
+                    for sample in history.iter().rev().skip(range.start).take(range.len()) {
+                        ui.monospace(sample.timestamp.format("%H:%M:%S").to_string());
+                        for d in &sample.data {
+                            ui.monospace(format!("{:.2}", d.value));
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+}
@@ -0,0 +1,61 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use egui::{RichText, ScrollArea};
+
+use envsensor_demo::{i18n::tr, logger::tail};
+
+/// Lines of the log shown
+const TAIL_LINES: usize = 8;
+
+/// How often the file is read again
+const REFRESH: Duration = Duration::from_secs(1);
+
+/// The end of the log file as it is on disk, read again every `REFRESH`
+#[derive(Default)]
+pub struct LogTail {
+    path: Option<PathBuf>,
+    lines: Vec<String>,
+    error: Option<String>,
+    read_at: Option<Instant>,
+}
+
+impl LogTail {
+    fn refresh(&mut self, path: &Path) {
+        let due = self.read_at.is_none_or(|t| t.elapsed() >= REFRESH);
+        if !due && self.path.as_deref() == Some(path) {
+            return;
+        }
+
+        match tail(path, TAIL_LINES) {
+            Ok(lines) => {
+                self.lines = lines;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+        self.path = Some(path.to_path_buf());
+        self.read_at = Some(Instant::now());
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, path: Option<PathBuf>) {
+        let Some(path) = path else {
+            ui.label(tr("Not logging"));
+            return;
+        };
+        self.refresh(&path);
+
+        ui.label(RichText::new(path.display().to_string()).weak())
+            .on_hover_text(tr("Rows show up here once they are flushed to the file"));
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        ScrollArea::horizontal().show(ui, |ui| {
+            for line in &self.lines {
+                ui.monospace(line);
+            }
+        });
+    }
+}
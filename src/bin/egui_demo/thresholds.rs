@@ -0,0 +1,67 @@
+use egui::{ComboBox, DragValue, Grid};
+use strum::IntoEnumIterator;
+
+use envsensor_demo::{
+    alert::Threshold,
+    convert::{convert, is_concentration},
+    sensor::Unit,
+};
+
+/// Checkbox enabling a limit plus its value
+fn limit(ui: &mut egui::Ui, value: &mut Option<f32>) {
+    ui.horizontal(|ui| {
+        let mut enabled = value.is_some();
+        if ui.checkbox(&mut enabled, "").changed() {
+            *value = enabled.then_some(0.0);
+        }
+        if let Some(v) = value {
+            ui.add(DragValue::new(v).speed(0.1));
+        }
+    });
+}
+
+/// Unit the limits are set in, the one of the first channel of the type when unset
+fn unit(ui: &mut egui::Ui, threshold: &mut Threshold) {
+    let ty = threshold.sensor_type;
+    if !is_concentration(ty) {
+        ui.label("");
+        return;
+    }
+
+    let choices = Unit::iter().filter(|&u| convert(1.0, ty, u, Unit::UgPerM3).is_some());
+    ComboBox::from_id_salt(("threshold_unit", ty.as_ref()))
+        .selected_text(threshold.unit.as_ref().map_or("Channel", |u| u.as_ref()))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut threshold.unit, None, "Channel");
+            for u in choices {
+                ui.selectable_value(&mut threshold.unit, Some(u), u.as_ref());
+            }
+        });
+}
+
+/// One row of low/high alarm limits per sensor type
+pub fn threshold_editor(ui: &mut egui::Ui, thresholds: &mut [Threshold]) {
+    Grid::new("thresholds").striped(true).show(ui, |ui| {
+        ui.strong("Type");
+        ui.strong("Unit");
+        ui.strong("Low");
+        ui.strong("High");
+        ui.strong("Hysteresis");
+        ui.end_row();
+
+        for threshold in thresholds {
+            ui.label(threshold.sensor_type.as_ref());
+            unit(ui, threshold);
+            limit(ui, &mut threshold.low);
+            limit(ui, &mut threshold.high);
+            ui.add(
+                DragValue::new(&mut threshold.hysteresis)
+                    .speed(0.1)
+                    .range(0.0..=f32::MAX),
+            );
+            ui.end_row();
+        }
+    });
+    ui.label("Limits are converted to the unit of each channel of the type");
+    ui.label("Alerts use the limits set when the sensor was started");
+}
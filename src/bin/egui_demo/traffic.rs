@@ -0,0 +1,63 @@
+use std::collections::VecDeque;
+
+use egui::{Color32, RichText, ScrollArea};
+
+use envsensor_demo::traffic::{Direction, Frame};
+
+/// State of the serial traffic window
+#[derive(Default)]
+pub struct TrafficView {
+    /// New frames are dropped while paused, the list stays put
+    pub paused: bool,
+    /// Show only this direction
+    direction: Option<Direction>,
+    /// Show only frames containing these bytes, in hex
+    pattern: String,
+}
+
+impl TrafficView {
+    fn matches(&self, frame: &Frame) -> bool {
+        let pattern = self.pattern.replace(' ', "").to_uppercase();
+
+        self.direction.is_none_or(|d| d == frame.direction)
+            && (pattern.is_empty() || frame.hex().replace(' ', "").contains(&pattern))
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, frames: &VecDeque<Frame>) {
+        ui.horizontal(|ui| {
+            ui.toggle_value(&mut self.paused, "⏸ Pause");
+            ui.separator();
+            ui.selectable_value(&mut self.direction, None, "All");
+            ui.selectable_value(&mut self.direction, Some(Direction::Tx), "TX");
+            ui.selectable_value(&mut self.direction, Some(Direction::Rx), "RX");
+            ui.separator();
+            ui.add(
+                egui::TextEdit::singleline(&mut self.pattern)
+                    .hint_text("Bytes, e.g. FF 86")
+                    .desired_width(120.0),
+            );
+        });
+        ui.separator();
+
+        ScrollArea::vertical()
+            .auto_shrink(false)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for frame in frames.iter().filter(|f| self.matches(f)) {
+                    let (label, color) = match frame.direction {
+                        Direction::Tx => ("TX", Color32::from_rgb(49, 130, 189)),
+                        Direction::Rx => ("RX", Color32::from_rgb(49, 163, 84)),
+                    };
+                    ui.label(
+                        RichText::new(format!(
+                            "{} {label} {}",
+                            frame.timestamp.format("%H:%M:%S%.3f"),
+                            frame.hex()
+                        ))
+                        .monospace()
+                        .color(color),
+                    );
+                }
+            });
+    }
+}
@@ -0,0 +1,206 @@
+//! egui frontend of a remote envsensord, built for the browser with trunk or run natively
+
+use std::collections::BTreeSet;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::Duration;
+
+use envsensor_demo::{
+    gui::{
+        plot::{PlotOptions, TimeWindow, YScale, plot_sensor},
+        tiles::value_tiles,
+    },
+    remote::{self, RemoteMsg},
+    sensor::AppMsg,
+    station::Station,
+};
+
+/// Samples kept for the plot, one hour at 1 Hz
+const HISTORY_LEN: usize = 60 * 60;
+
+/// Port of the WebSocket stream of envsensord, see `--ws`
+const WS_PORT: u16 = 9001;
+
+struct WebApp {
+    /// WebSocket stream of envsensord
+    url: String,
+    remote: Option<Receiver<RemoteMsg>>,
+    station: Station,
+    window: TimeWindow,
+    /// Channels switched off in the legend
+    hidden: BTreeSet<String>,
+    /// Last status message, alert or error
+    status: String,
+}
+
+impl WebApp {
+    fn new(url: String) -> Self {
+        let mut app = Self {
+            url,
+            remote: None,
+            station: Station::new(String::new(), HISTORY_LEN),
+            window: TimeWindow::TenMinutes,
+            hidden: BTreeSet::new(),
+            status: String::new(),
+        };
+        app.connect();
+        app
+    }
+
+    fn connect(&mut self) {
+        self.station = Station::new(self.url.clone(), HISTORY_LEN);
+        match remote::connect(&self.url) {
+            Ok(rx) => {
+                self.remote = Some(rx);
+                self.status = format!("Connecting to {}", self.url);
+            }
+            Err(e) => {
+                self.remote = None;
+                self.status = e.to_string();
+            }
+        }
+    }
+
+    /// Take in what envsensord sent since the last frame
+    fn poll(&mut self) {
+        let Some(rx) = &self.remote else {
+            return;
+        };
+
+        let mut messages = Vec::new();
+        loop {
+            match rx.try_recv() {
+                Ok(RemoteMsg::Hello(hello)) => {
+                    self.station.name = format!("{} @ {}", hello.model.as_ref(), self.url);
+                    self.station.model = Some(hello.model);
+                    self.status = format!("Connected to {}", self.station.name);
+                }
+                Ok(RemoteMsg::Msg(msg)) => messages.push(msg),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.remote = None;
+                    break;
+                }
+            }
+        }
+
+        for msg in self.station.receive(messages) {
+            match msg {
+                AppMsg::Status(text) => self.status = text,
+                AppMsg::Error(e) => self.status = format!("Error: {e}"),
+                AppMsg::Alert(alert) => self.status = format!("Alert: {alert}"),
+                AppMsg::Anomaly(anomaly) => self.status = format!("Anomaly: {anomaly}"),
+                AppMsg::Marker(marker) => self.station.markers.push(marker),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl eframe::App for WebApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll();
+
+        egui::TopBottomPanel::top("connection").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("envsensord");
+                let edit = ui.text_edit_singleline(&mut self.url);
+                let entered = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                let label = if self.remote.is_some() {
+                    "Reconnect"
+                } else {
+                    "Connect"
+                };
+                if ui.button(label).clicked() || entered {
+                    self.connect();
+                }
+
+                ui.separator();
+                for window in TimeWindow::ALL {
+                    ui.selectable_value(&mut self.window, window, window.label());
+                }
+            });
+        });
+
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(&self.status);
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.label(format!("{} samples", self.station.received));
+                });
+            });
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let station = &self.station;
+            if station.channels.is_empty() {
+                ui.centered_and_justified(|ui| ui.label("Waiting for samples"));
+                return;
+            }
+
+            value_tiles(ui, &station.channels, &station.history, &[]);
+            ui.separator();
+            let response = plot_sensor(
+                ui,
+                "web_plot",
+                &station.history,
+                &station.channels,
+                YScale::PerUnit,
+                PlotOptions {
+                    normalize: false,
+                    window: self.window,
+                    height: None,
+                    frozen: None,
+                    follow: true,
+                    reset: false,
+                    smoothing: 0,
+                    thresholds: &[],
+                    markers: &station.markers,
+                    reference: None,
+                    hidden: &self.hidden,
+                },
+            );
+            self.hidden = response.inner.hidden;
+        });
+
+        ctx.request_repaint_after(Duration::from_millis(100));
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> eframe::Result<()> {
+    let url = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| format!("ws://localhost:{WS_PORT}"));
+
+    eframe::run_native(
+        "envsensor",
+        eframe::NativeOptions::default(),
+        Box::new(|_| Ok(Box::new(WebApp::new(url)))),
+    )
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use wasm_bindgen::JsCast;
+
+    let window = web_sys::window().expect("No window");
+    // envsensord usually serves the page too
+    let host = window.location().hostname().unwrap_or_default();
+    let url = format!("ws://{host}:{WS_PORT}");
+    let canvas = window
+        .document()
+        .and_then(|document| document.get_element_by_id("egui_web"))
+        .and_then(|canvas| canvas.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+        .expect("No egui_web canvas on the page");
+
+    wasm_bindgen_futures::spawn_local(async move {
+        eframe::WebRunner::new()
+            .start(
+                canvas,
+                eframe::WebOptions::default(),
+                Box::new(|_| Ok(Box::new(WebApp::new(url)))),
+            )
+            .await
+            .expect("Failed to start egui_web");
+    });
+}
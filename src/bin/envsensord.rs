@@ -0,0 +1,172 @@
+//! Headless daemon exposing the sensors over a REST API
+
+use anyhow::{Result, anyhow};
+
+use envsensor_demo::{
+    api::{ApiConfig, serve},
+    config::Config,
+    metrics::MetricsConfig,
+    modbus_tcp::ModbusTcpConfig,
+    process::Despike,
+    report::write_report,
+    sensor_community::SensorCommunityConfig,
+    settings::Settings,
+    webhook::WebhookConfig,
+    ws::WsConfig,
+};
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: envsensord [--addr <host:port>] [--history <samples>] \
+         [--ws <host:port>] [--metrics <host:port>] [--grpc <host:port>] \
+         [--modbus <host:port>] [--dbus] [--aggregate] [--interpolate-gaps] [--webhook <url>] \
+         [--sensor-community <node id>] [--site <name>] [--location <lat,lon>] \
+         [--unit <type=unit>]... [--smooth <samples>] [--despike <samples>] \
+         [--config <settings.toml>]\n\
+         Usage: envsensord report <log.csv> [--day <YYYY-MM-DD>] [--config <settings.toml>]"
+    );
+    std::process::exit(2);
+}
+
+/// Write the HTML report of a CSV log next to it, for the whole session or one day
+fn report(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let log = args.next().unwrap_or_else(|| usage());
+    let mut day = None;
+    let mut settings = Settings::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--day" => {
+                day = Some(
+                    args.next()
+                        .and_then(|day| day.parse().ok())
+                        .ok_or_else(|| anyhow!("--day expects a date, e.g. 2024-05-01"))?,
+                )
+            }
+            "--config" => {
+                let path = args.next().unwrap_or_else(|| usage());
+                settings = Settings::load(path.as_ref())?;
+            }
+            _ => usage(),
+        }
+    }
+
+    let path = write_report(log.as_ref(), day, &settings.thresholds)?;
+    println!("Wrote {}", path.display());
+
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let mut api = ApiConfig::default();
+    let mut config = Config::default();
+    let mut grpc: Option<String> = None;
+
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if(|arg| arg == "report").is_some() {
+        return report(args);
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--addr" => api.addr = args.next().unwrap_or_else(|| usage()),
+            "--history" => {
+                api.history = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| anyhow!("--history expects a number"))?
+            }
+            "--ws" => {
+                config.ws = Some(WsConfig {
+                    addr: args.next().unwrap_or_else(|| usage()),
+                })
+            }
+            "--metrics" => {
+                config.metrics = Some(MetricsConfig {
+                    addr: args.next().unwrap_or_else(|| usage()),
+                })
+            }
+            "--modbus" => {
+                config.modbus_tcp = Some(ModbusTcpConfig {
+                    addr: args.next().unwrap_or_else(|| usage()),
+                })
+            }
+            "--dbus" => config.dbus = true,
+            "--aggregate" => config.aggregate = true,
+            "--interpolate-gaps" => config.interpolate_gaps = true,
+            "--webhook" => {
+                config.webhook = Some(WebhookConfig {
+                    url: args.next().unwrap_or_else(|| usage()),
+                    ..Default::default()
+                })
+            }
+            "--sensor-community" => {
+                config.sensor_community = Some(SensorCommunityConfig {
+                    node_id: args.next().unwrap_or_else(|| usage()),
+                    ..Default::default()
+                })
+            }
+            "--site" => {
+                config.site.get_or_insert_default().name = args.next().unwrap_or_else(|| usage())
+            }
+            "--location" => {
+                let location = args.next().unwrap_or_else(|| usage());
+                let (lat, lon) = location
+                    .split_once(',')
+                    .and_then(|(lat, lon)| {
+                        Some((lat.trim().parse().ok()?, lon.trim().parse().ok()?))
+                    })
+                    .ok_or_else(|| anyhow!("--location expects <lat,lon>"))?;
+                let site = config.site.get_or_insert_default();
+                site.lat = Some(lat);
+                site.lon = Some(lon);
+            }
+            "--unit" => {
+                let unit = args.next().unwrap_or_else(|| usage());
+                let (ty, unit) = unit
+                    .split_once('=')
+                    .and_then(|(ty, unit)| Some((ty.parse().ok()?, unit.parse().ok()?)))
+                    .ok_or_else(|| anyhow!("--unit expects <type=unit>, e.g. CO=mg/m3"))?;
+                config.process.units.push((ty, unit));
+            }
+            "--smooth" => {
+                config.process.smoothing = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| anyhow!("--smooth expects a number"))?
+            }
+            "--despike" => {
+                let window = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| anyhow!("--despike expects a number"))?;
+                config.process.despike = Some(Despike {
+                    window,
+                    ..Default::default()
+                });
+            }
+            "--config" => {
+                let path = args.next().unwrap_or_else(|| usage());
+                Settings::load(path.as_ref())?.apply(&mut config);
+            }
+            "--grpc" => grpc = Some(args.next().unwrap_or_else(|| usage())),
+            _ => usage(),
+        }
+    }
+
+    // gRPC replaces the REST API, both control the sensor so only one may own it
+    if let Some(addr) = grpc {
+        #[cfg(feature = "grpc")]
+        {
+            println!("Serving gRPC on {addr}");
+            return envsensor_demo::grpc::serve(&addr, config);
+        }
+
+        #[cfg(not(feature = "grpc"))]
+        return Err(anyhow!(
+            "Built without gRPC support, cannot serve on {addr}"
+        ));
+    }
+
+    println!("Listening on http://{}", api.addr);
+
+    serve(api, config)
+}
@@ -0,0 +1,97 @@
+use chrono::TimeDelta;
+use slint::{Color, ModelRc, VecModel};
+
+use envsensor_demo::{
+    history::History,
+    stats::decimate,
+    theme::{Rgb, channel_colors},
+};
+
+use super::{Chart, Series};
+
+/// Time shown in the live chart, up to the newest sample
+pub const SPAN: TimeDelta = TimeDelta::minutes(10);
+
+/// Width and height of the path coordinates, the viewbox of the paths in main.slint
+const VIEWBOX: f32 = 1000.0;
+
+/// slint color of a theme color
+pub fn color([r, g, b]: Rgb) -> Color {
+    Color::from_rgb_u8(r, g, b)
+}
+
+/// SVG path through `points`, already in viewbox coordinates
+fn path_commands(points: impl Iterator<Item = (f32, f32)>) -> String {
+    let mut commands = String::new();
+    for (n, (x, y)) in points.enumerate() {
+        let command = if n == 0 { 'M' } else { 'L' };
+        commands.push_str(&format!("{command} {x:.1} {y:.1} "));
+    }
+    commands
+}
+
+/// Lines of all channels in `history` over the newest `span`, or all of it, on a shared
+/// value axis
+pub fn chart(history: &History, span: Option<TimeDelta>) -> Chart {
+    let (Some(first), Some(latest)) = (history.iter().next(), history.latest()) else {
+        return Chart::default();
+    };
+    if latest.data.is_empty() {
+        return Chart::default();
+    }
+    let to = latest.timestamp;
+    let from = span.map_or(first.timestamp, |span| first.timestamp.max(to - span));
+    let width = (to - from).as_seconds_f64().max(1.0);
+
+    let channels = latest.data.len();
+    let mut points = vec![Vec::new(); channels];
+    for s in history.range(Some(from), Some(to)) {
+        let x = (s.timestamp - from).as_seconds_f64() / width * VIEWBOX as f64;
+        for (line, d) in points.iter_mut().zip(&s.data) {
+            line.push((x, d.value));
+        }
+    }
+
+    let values = || points.iter().flatten().map(|(_, y)| *y);
+    let min = values().fold(f32::MAX, f32::min);
+    let max = values().fold(f32::MIN, f32::max);
+    // A flat line sits in the middle
+    let (min, max) = if max > min {
+        (min, max)
+    } else {
+        (min - 1.0, max + 1.0)
+    };
+    let to_y = |value: f32| VIEWBOX - (value - min) / (max - min) * VIEWBOX;
+
+    let channels = latest.data.iter().map(|d| d.channel()).collect::<Vec<_>>();
+    let series = points
+        .iter()
+        .zip(&latest.data)
+        .zip(channel_colors(&channels))
+        .map(|((line, d), rgb)| {
+            let commands = if line.len() > VIEWBOX as usize {
+                path_commands(
+                    decimate(line, VIEWBOX as usize)
+                        .into_iter()
+                        .map(|e| (e.x as f32, to_y(e.mean))),
+                )
+            } else {
+                path_commands(line.iter().map(|&(x, y)| (x as f32, to_y(y))))
+            };
+
+            Series {
+                name: format!("{} ({})", d.ty.as_ref(), d.unit.as_ref()).into(),
+                color: color(rgb),
+                commands: commands.into(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Chart {
+        series: ModelRc::new(VecModel::from(series)),
+        y_min: format!("{min:.1}").into(),
+        y_max: format!("{max:.1}").into(),
+        from: from.format("%H:%M:%S").to_string().into(),
+        to: to.format("%H:%M:%S").to_string().into(),
+    }
+}
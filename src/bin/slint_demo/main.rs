@@ -1,24 +1,364 @@
+use std::{cell::RefCell, collections::BTreeMap, path::PathBuf, rc::Rc, time::Duration};
+
 use anyhow::Result;
+use slint::{ModelRc, SharedString, TimerMode, VecModel};
+
+use envsensor_demo::{
+    alert::{Alert, Breach},
+    config::Config,
+    controller::Controller,
+    idle::IdleDim,
+    logger::LogConfig,
+    sensor::{AppMsg, SampleData, Sensor, SensorType, Unit},
+    settings::Settings,
+    station::Station,
+    theme,
+};
+
+mod chart;
 
 slint::include_modules!();
 
-fn main() -> Result<()> {
+/// How often the messages of the sensor thread are picked up
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Samples kept for the chart, one day at 1 Hz
+const HISTORY_LEN: usize = 24 * 60 * 60;
+
+fn string_list<'a>(items: impl IntoIterator<Item = &'a str>) -> ModelRc<SharedString> {
+    let items = items
+        .into_iter()
+        .map(SharedString::from)
+        .collect::<Vec<_>>();
+    ModelRc::new(VecModel::from(items))
+}
+
+/// Latest values of `station` for the readings row, in the colors of the chart
+fn readings(station: &Station, sample: &SampleData) -> ModelRc<Reading> {
+    let colors = theme::channel_colors(&station.channels);
+    let readings = sample
+        .data
+        .iter()
+        .zip(colors)
+        .map(|(d, rgb)| Reading {
+            name: d.ty.as_ref().into(),
+            value: format!("{:.1}", d.value).into(),
+            unit: d.unit.as_ref().into(),
+            color: chart::color(rgb),
+        })
+        .collect::<Vec<_>>();
+
+    ModelRc::new(VecModel::from(readings))
+}
+
+/// Banner rows of the channels beyond their limits
+fn alarms(active: &BTreeMap<(SensorType, Unit), Alert>) -> ModelRc<Alarm> {
+    let alarms = active
+        .values()
+        .map(|alert| {
+            let unit = alert.unit.as_ref();
+            Alarm {
+                name: alert.sensor_type.as_ref().into(),
+                value: format!("{:.1} {unit}", alert.value).into(),
+                limit: alert
+                    .limit
+                    .map(|limit| format!("{limit:.1} {unit}"))
+                    .unwrap_or_default()
+                    .into(),
+                above: alert.breach == Some(Breach::Above),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    ModelRc::new(VecModel::from(alarms))
+}
+
+/// Statistics of each channel since the sensor was started, for the history page
+fn stats(station: &Station) -> ModelRc<StatRow> {
+    let rows = station
+        .channels
+        .iter()
+        .zip(&station.session_stats)
+        .filter_map(|(ch, stats)| {
+            let stats = stats.summary()?;
+            Some(StatRow {
+                name: format!("{} ({})", ch.name(), ch.unit.as_ref()).into(),
+                min: format!("{:.1}", stats.min).into(),
+                mean: format!("{:.1}", stats.mean).into(),
+                max: format!("{:.1}", stats.max).into(),
+                count: stats.count.to_string().into(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    ModelRc::new(VecModel::from(rows))
+}
+
+/// Details of the sensor of `station` for the diagnostics page
+fn diagnostics(station: &Station) -> Diagnostics {
+    let info = station.sensor.as_ref().and_then(Sensor::info);
+    let info = info.unwrap_or_default();
+    let unit = station.channels.first().map_or("", |ch| ch.unit.as_ref());
+    let running_for = station
+        .started
+        .filter(|_| station.is_running())
+        .map(|started| {
+            let secs = started.elapsed().as_secs();
+            format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+        });
+
+    Diagnostics {
+        model: station.model.as_ref().map_or("", |m| m.as_ref()).into(),
+        port: station.port.clone().unwrap_or_default().into(),
+        firmware: info.firmware.unwrap_or_default().into(),
+        serial_number: info.serial_number.unwrap_or_default().into(),
+        range: info
+            .range
+            .map(|range| format!("{range} {unit}"))
+            .unwrap_or_default()
+            .into(),
+        received: station.received.to_string().into(),
+        running_for: running_for.unwrap_or_default().into(),
+        error: station.error.clone().unwrap_or_default().into(),
+    }
+}
+
+/// Take the values of the settings page
+fn settings_from_ui(ui: &AppWindow, settings: &mut Settings) {
+    settings.poll_interval = ui.get_poll_interval().max(1) as f32;
+    settings.smoothing = ui.get_smoothing().max(0) as usize;
+    settings.dim_after = ui.get_dim_after().max(0) as u32;
+}
+
+/// Show the window until it's closed, the backend is already chosen
+pub fn run() -> Result<()> {
     let ui = AppWindow::new()?;
-    let timer = std::rc::Rc::new(slint::Timer::default());
+    ui.global::<Theme>().set_alert(chart::color(theme::ALERT));
+
+    // Same settings and language as the egui frontend
+    let settings = Settings::default_path()
+        .and_then(|path| Settings::load(&path).ok())
+        .unwrap_or_default();
+    let language = settings.language;
+    if let Err(e) = slint::select_bundled_translation(language.code()) {
+        eprintln!("No {} translation: {e}", language.label());
+    }
+    ui.set_poll_interval(settings.poll_interval.round().max(1.0) as i32);
+    ui.set_smoothing(settings.smoothing as i32);
+    ui.set_dim_after(settings.dim_after as i32);
+    let idle = Rc::new(RefCell::new(IdleDim::new(settings.dim_timeout())));
+    let settings = Rc::new(RefCell::new(settings));
+    let controller = Rc::new(RefCell::new(Controller::new(HISTORY_LEN)));
+    // Latest alert of each channel beyond its limits
+    let active_alarms = Rc::new(RefCell::new(BTreeMap::new()));
+    {
+        let controller = controller.borrow();
+        ui.set_sensors(string_list(controller.sensors.iter().map(|m| m.as_ref())));
+        ui.set_ports(string_list(controller.ports.iter().map(String::as_str)));
+    }
+
+    let log_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    ui.set_log_dir(log_dir.display().to_string().into());
+
     let ui_weak = ui.as_weak();
-    let timer_clone = timer.clone();
-
-    let mut i = 0;
-    slint::Timer::single_shot(std::time::Duration::from_millis(100), move || {
-        timer_clone.start(
-            slint::TimerMode::Repeated,
-            std::time::Duration::from_secs(1),
-            move || {
-                if let Some(win) = ui_weak.upgrade() {
-                    i += 1;
-                    win.invoke_log(format!("{i}").into());
-                }
+    let state = controller.clone();
+    ui.on_add_port(move |address| {
+        let Some(ui) = ui_weak.upgrade() else {
+            return;
+        };
+        let mut controller = state.borrow_mut();
+        controller.add_port(&address);
+        ui.set_ports(string_list(controller.ports.iter().map(String::as_str)));
+        ui.set_port_index(controller.port_choice as i32);
+    });
+
+    // No folder dialog on Android, logs go to the app's data directory
+    #[cfg(target_os = "android")]
+    ui.set_folder_picker(false);
+    #[cfg(not(target_os = "android"))]
+    let ui_weak = ui.as_weak();
+    #[cfg(not(target_os = "android"))]
+    ui.on_choose_log_dir(move || {
+        let Some(ui) = ui_weak.upgrade() else {
+            return;
+        };
+        if let Some(dir) = rfd::FileDialog::new()
+            .set_directory(ui.get_log_dir().as_str())
+            .pick_folder()
+        {
+            ui.set_log_dir(dir.display().to_string().into());
+        }
+    });
+
+    let ui_weak = ui.as_weak();
+    let state = idle.clone();
+    ui.on_wake(move || {
+        state.borrow_mut().wake();
+        if let Some(ui) = ui_weak.upgrade() {
+            ui.set_dimmed(false);
+        }
+    });
+
+    let ui_weak = ui.as_weak();
+    let saved = settings.clone();
+    let state = idle.clone();
+    ui.on_save_settings(move || {
+        let Some(ui) = ui_weak.upgrade() else {
+            return;
+        };
+        let mut settings = saved.borrow_mut();
+        settings_from_ui(&ui, &mut settings);
+        state.borrow_mut().set_timeout(settings.dim_timeout());
+        let Some(path) = Settings::default_path() else {
+            ui.invoke_show_error("No configuration directory to save the settings to".into());
+            return;
+        };
+        match settings.save(&path) {
+            Ok(()) => ui.invoke_log(format!("Saved settings to {}", path.display()).into()),
+            Err(e) => ui.invoke_show_error(format!("Failed to save settings: {e}").into()),
+        }
+    });
+
+    let ui_weak = ui.as_weak();
+    let state = controller.clone();
+    let start_alarms = active_alarms.clone();
+    ui.on_start(move || {
+        let Some(ui) = ui_weak.upgrade() else {
+            return;
+        };
+        let mut controller = state.borrow_mut();
+        controller.sensor_choice = ui.get_sensor_index().max(0) as usize;
+        controller.port_choice = ui.get_port_index().max(0) as usize;
+        // Only the latest run is shown
+        controller.stations.clear();
+
+        let mut config = Config {
+            log: LogConfig {
+                enabled: ui.get_logging(),
+                dir: PathBuf::from(ui.get_log_dir().as_str()),
+                name: Some(ui.get_log_name().trim())
+                    .filter(|n| !n.is_empty())
+                    .map(str::to_string),
+                ..Default::default()
             },
+            ..Default::default()
+        };
+        let mut settings = settings.borrow_mut();
+        settings_from_ui(&ui, &mut settings);
+        settings.apply(&mut config);
+        match controller.start(config) {
+            Ok(station) => {
+                start_alarms.borrow_mut().clear();
+                ui.set_running(true);
+                ui.set_alert(false);
+                ui.set_alarms(ModelRc::default());
+                ui.set_status(format!("Running {}", station.name).into());
+                ui.invoke_log(format!("Started {}", station.name).into());
+            }
+            Err(e) => {
+                ui.invoke_log(format!("Failed to start: {e}").into());
+                ui.invoke_show_error(format!("Failed to start: {e}").into());
+            }
+        }
+    });
+
+    let ui_weak = ui.as_weak();
+    let state = controller.clone();
+    ui.on_stop(move || {
+        if let Some(station) = state.borrow_mut().active_station_mut() {
+            station.stop();
+        }
+        if let Some(ui) = ui_weak.upgrade() {
+            ui.set_running(false);
+            ui.set_status("Stopped".into());
+            ui.invoke_log("Stopped".into());
+        }
+    });
+
+    let timer = slint::Timer::default();
+    let ui_weak = ui.as_weak();
+    // Time of the newest sample shown, the chart is only redrawn for new ones
+    let mut shown = None;
+    timer.start(TimerMode::Repeated, POLL_INTERVAL, move || {
+        let Some(ui) = ui_weak.upgrade() else {
+            return;
+        };
+
+        let mut controller = controller.borrow_mut();
+
+        // Keep the chosen port selected when others come and go
+        controller.port_choice = ui.get_port_index().max(0) as usize;
+        if controller.update_ports().is_some() {
+            ui.set_ports(string_list(controller.ports.iter().map(String::as_str)));
+            ui.set_port_index(controller.port_choice as i32);
+        }
+
+        // Messages which may change the diagnostics page
+        let mut changed = false;
+        for (_, msg) in controller.poll() {
+            changed |= matches!(msg, AppMsg::Status(_) | AppMsg::Error(_));
+            match msg {
+                AppMsg::Status(text) => {
+                    ui.set_status(text.as_str().into());
+                    ui.invoke_log(text.into());
+                }
+                // The sensor thread ends on an error, e.g. when the port can't be opened
+                AppMsg::Error(e) => {
+                    idle.borrow_mut().wake();
+                    ui.set_status(format!("Stopped on an error: {e}").into());
+                    ui.invoke_log(format!("Error: {e}").into());
+                    ui.invoke_show_error(e.into());
+                }
+                AppMsg::Alert(alert) => {
+                    ui.set_status(alert.to_string().into());
+                    ui.invoke_log(format!("Alert: {alert}").into());
+
+                    let mut active = active_alarms.borrow_mut();
+                    if alert.breach.is_some() {
+                        idle.borrow_mut().wake();
+                        active.insert((alert.sensor_type, alert.unit), alert);
+                    } else {
+                        active.remove(&(alert.sensor_type, alert.unit));
+                    }
+                    ui.set_alert(!active.is_empty());
+                    ui.set_alarms(alarms(&active));
+                }
+                AppMsg::Anomaly(anomaly) => {
+                    idle.borrow_mut().wake();
+                    ui.set_status(anomaly.to_string().into());
+                    ui.invoke_log(format!("Anomaly: {anomaly}").into());
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(station) = controller.active_station()
+            && let Some(latest) = station.history.latest()
+            && shown != Some(latest.timestamp)
+        {
+            shown = Some(latest.timestamp);
+            changed = true;
+            ui.set_readings(readings(station, latest));
+            ui.set_chart(chart::chart(&station.history, Some(chart::SPAN)));
+            ui.set_history_chart(chart::chart(&station.history, None));
+            ui.set_stats(stats(station));
+        }
+        let station = controller.active_station();
+        let running = station.is_some_and(Station::is_running);
+        if let Some(station) = station
+            && (changed || running != ui.get_running())
+        {
+            ui.set_diagnostics(diagnostics(station));
+        }
+        ui.set_running(running);
+        ui.set_dimmed(idle.borrow().is_dimmed());
+        // The logger names the file once it's created
+        let log_file = station.and_then(Station::log_path);
+        ui.set_log_file(
+            log_file
+                .map(|path| path.display().to_string())
+                .unwrap_or_default()
+                .into(),
         );
     });
 
@@ -26,3 +366,16 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+// The Android library starts with `run` instead
+#[allow(dead_code)]
+fn main() -> Result<()> {
+    // Straight to the display, for panels without a desktop
+    #[cfg(feature = "linuxkms")]
+    slint::BackendSelector::new()
+        .backend_name("linuxkms".into())
+        .renderer_name("software".into())
+        .select()?;
+
+    run()
+}
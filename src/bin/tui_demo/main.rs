@@ -0,0 +1,273 @@
+//! Terminal frontend, for watching a sensor over SSH where there is no display server
+
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::TimeDelta;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    symbols::Marker,
+    text::{Line, Span},
+    widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph},
+};
+
+use envsensor_demo::{
+    alert::threshold_for, config::Config, controller::Controller, sensor::AppMsg,
+    settings::Settings, station::Station,
+};
+
+/// Samples kept for the chart, one day at 1 Hz
+const HISTORY_LEN: usize = 24 * 60 * 60;
+
+/// Time shown in the chart, up to the newest sample
+const SPAN: TimeDelta = TimeDelta::minutes(10);
+
+/// Longest wait for a key before the sensor messages are taken in again
+const TICK: Duration = Duration::from_millis(200);
+
+const KEYS: &str = "s start/stop  ←/→ channel  n sensor  p port  q quit";
+
+fn usage() -> ! {
+    eprintln!("Usage: tui_demo [--sensor <model>] [--port <port>] [--config <settings.toml>]");
+    std::process::exit(2);
+}
+
+struct App {
+    controller: Controller,
+    /// Options of the sensors started
+    config: Config,
+    /// Index of the channel in the chart
+    channel: usize,
+    /// Last status message, alert or error
+    status: String,
+    quit: bool,
+}
+
+impl App {
+    fn handle_key(&mut self, code: KeyCode) {
+        let running = self
+            .controller
+            .active_station()
+            .is_some_and(Station::is_running);
+
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('s') | KeyCode::Char(' ') => self.start_stop(),
+            KeyCode::Left => self.channel = self.channel.saturating_sub(1),
+            KeyCode::Right => self.channel += 1,
+            // The choices only matter for the next start
+            KeyCode::Char('n') if !running => {
+                let count = self.controller.sensors.len().max(1);
+                self.controller.sensor_choice = (self.controller.sensor_choice + 1) % count;
+            }
+            KeyCode::Char('p') if !running => {
+                let count = self.controller.ports.len().max(1);
+                self.controller.port_choice = (self.controller.port_choice + 1) % count;
+            }
+            _ => {}
+        }
+    }
+
+    fn start_stop(&mut self) {
+        if let Some(station) = self.controller.active_station_mut()
+            && station.is_running()
+        {
+            station.stop();
+            self.status = "Stopped".to_string();
+            return;
+        }
+
+        // Only the latest run is shown
+        self.controller.stations.clear();
+        self.status = match self.controller.start(self.config.clone()) {
+            Ok(station) => format!("Started {}", station.name),
+            Err(e) => format!("Failed to start: {e}"),
+        };
+    }
+
+    /// Take in what the sensor sent and the serial ports that came or went
+    fn poll(&mut self) {
+        self.controller.update_ports();
+
+        for (_, msg) in self.controller.poll() {
+            match msg {
+                AppMsg::Status(text) => self.status = text,
+                AppMsg::Error(e) => self.status = format!("Error: {e}"),
+                AppMsg::Alert(alert) => self.status = format!("Alert: {alert}"),
+                AppMsg::Anomaly(anomaly) => self.status = format!("Anomaly: {anomaly}"),
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, values, chart, status] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Min(6),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let station = self.controller.active_station();
+        let running = station.is_some_and(Station::is_running);
+        let sensor = self.controller.sensor().map_or("-", |s| s.as_ref());
+        let port = self.controller.port().unwrap_or("no serial port");
+        frame.render_widget(
+            Line::from(vec![
+                " envsensor ".bold().reversed(),
+                format!(" {sensor} on {port}  ").into(),
+                if running {
+                    "● running".green()
+                } else {
+                    "○ stopped".dark_gray()
+                },
+            ]),
+            header,
+        );
+
+        let Some((station, latest)) = station.and_then(|s| Some((s, s.history.latest()?))) else {
+            frame.render_widget(
+                Paragraph::new("No data, press s to start the sensor")
+                    .block(Block::bordered().title(" Latest ")),
+                values,
+            );
+            frame.render_widget(Line::from(vec![Span::raw(&self.status)]), status);
+            return;
+        };
+        let channels = &station.channels;
+        self.channel = self.channel.min(channels.len().saturating_sub(1));
+
+        // Values beyond their limits in red, the charted channel underlined
+        let thresholds = station.display_thresholds(&self.config.thresholds);
+        let mut spans = Vec::new();
+        for (idx, d) in latest.data.iter().enumerate() {
+            let mut style = Style::default();
+            if threshold_for(&thresholds, d.ty, d.unit).is_some_and(|t| t.check(d.value).is_some())
+            {
+                style = style.red().bold();
+            }
+            if idx == self.channel {
+                style = style.underlined();
+            }
+            spans.push(Span::styled(
+                format!("{} {:.1} {}", d.ty.as_ref(), d.value, d.unit.as_ref()),
+                style,
+            ));
+            spans.push(Span::raw("   "));
+        }
+        frame.render_widget(
+            Paragraph::new(Line::from(spans)).block(Block::bordered().title(" Latest ")),
+            values,
+        );
+
+        if let Some(ch) = channels.get(self.channel) {
+            let points = station
+                .history
+                .range(Some(latest.timestamp - SPAN), None)
+                .filter_map(|s| {
+                    let d = s.data.get(self.channel)?;
+                    let x = (s.timestamp - latest.timestamp).as_seconds_f64();
+                    Some((x, d.value as f64))
+                })
+                .collect::<Vec<_>>();
+            let min = points.iter().map(|p| p.1).fold(f64::MAX, f64::min);
+            let max = points.iter().map(|p| p.1).fold(f64::MIN, f64::max);
+            // A flat line sits in the middle
+            let (min, max) = if max > min {
+                (min, max)
+            } else {
+                (min - 1.0, max + 1.0)
+            };
+
+            let name = format!("{} ({})", ch.name(), ch.unit.as_ref());
+            let dataset = Dataset::default()
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .cyan()
+                .data(&points);
+            let span = SPAN.as_seconds_f64();
+            frame.render_widget(
+                Chart::new(vec![dataset])
+                    .block(Block::bordered().title(format!(" {name} ")))
+                    .x_axis(
+                        Axis::default()
+                            .bounds([-span, 0.0])
+                            .labels(["-10 min", "-5 min", "now"]),
+                    )
+                    .y_axis(
+                        Axis::default()
+                            .bounds([min, max])
+                            .labels([format!("{min:.1}"), format!("{max:.1}")]),
+                    ),
+                chart,
+            );
+        }
+
+        frame.render_widget(
+            Line::from(vec![
+                Span::raw(&self.status),
+                Span::raw("  "),
+                KEYS.dark_gray(),
+            ]),
+            status,
+        );
+    }
+}
+
+fn run(mut terminal: DefaultTerminal, app: &mut App) -> Result<()> {
+    while !app.quit {
+        app.poll();
+        terminal.draw(|frame| app.draw(frame))?;
+
+        if event::poll(TICK)?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            app.handle_key(key.code);
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let mut controller = Controller::new(HISTORY_LEN);
+    let mut settings = Settings::default_path()
+        .and_then(|path| Settings::load(&path).ok())
+        .unwrap_or_default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sensor" => controller.select_sensor(&args.next().unwrap_or_else(|| usage())),
+            "--port" => controller.add_port(&args.next().unwrap_or_else(|| usage())),
+            "--config" => {
+                let path = args.next().unwrap_or_else(|| usage());
+                settings = Settings::load(path.as_ref())?;
+            }
+            _ => usage(),
+        }
+    }
+
+    let mut config = Config::default();
+    settings.apply(&mut config);
+    let mut app = App {
+        controller,
+        config,
+        channel: 0,
+        status: KEYS.to_string(),
+        quit: false,
+    };
+
+    let terminal = ratatui::init();
+    let result = run(terminal, &mut app);
+    ratatui::restore();
+
+    app.controller.shut_down();
+
+    result
+}
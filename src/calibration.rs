@@ -0,0 +1,194 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::convert::convert;
+use crate::history::History;
+use crate::sensor::{SensorInfo, SensorModel, SensorType, Unit};
+
+/// Linear correction of the readings of one channel, `slope * value + offset`
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Calibration {
+    pub slope: f32,
+    pub offset: f32,
+    /// Unit of `offset`, converted to the unit of the channel when they differ
+    pub unit: Unit,
+}
+
+impl Calibration {
+    /// Leaves values in `unit` as they are
+    pub fn identity(unit: Unit) -> Self {
+        Self {
+            slope: 1.0,
+            offset: 0.0,
+            unit,
+        }
+    }
+
+    /// Correct `value` of a channel of type `ty` in `unit`
+    pub fn apply(&self, ty: SensorType, value: f32, unit: Unit) -> f32 {
+        let offset = convert(self.offset, ty, self.unit, unit).unwrap_or(self.offset);
+        self.slope * value + offset
+    }
+
+    /// This calibration followed by `fit`, which was found on values in `unit` already
+    /// corrected by this one
+    pub fn then(&self, ty: SensorType, fit: &LinearFit, unit: Unit) -> Self {
+        let offset = convert(self.offset, ty, self.unit, unit).unwrap_or(self.offset);
+        Self {
+            slope: fit.slope * self.slope,
+            offset: fit.slope * offset + fit.offset,
+            unit,
+        }
+    }
+}
+
+/// How a device is told apart from others of its model: the serial number it reports, the
+/// port it is connected to when it reports none
+pub fn device_id(port: &str, info: Option<&SensorInfo>) -> String {
+    info.and_then(|info| info.serial_number.clone())
+        .unwrap_or_else(|| port.to_string())
+}
+
+/// Calibration of one channel of one device, as saved by the calibration wizard
+///
+/// ```toml
+/// [[calibration]]
+/// model = "RYDASON"
+/// device = "/dev/ttyUSB0"
+/// channel = "co_ppm"
+/// slope = 0.95
+/// offset = -0.4
+/// unit = "ppm"
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ChannelCalibration {
+    pub model: SensorModel,
+    /// See `device_id`
+    pub device: String,
+    /// `SensorChannel::id` of the channel as logged
+    pub channel: String,
+    #[serde(flatten)]
+    pub calibration: Calibration,
+}
+
+impl ChannelCalibration {
+    pub fn is_for(&self, model: SensorModel, device: &str, channel: &str) -> bool {
+        self.model == model && self.device == device && self.channel == channel
+    }
+}
+
+/// Least squares line through reference values over the readings of the device under test
+#[derive(Clone, Copy, Debug)]
+pub struct LinearFit {
+    pub slope: f32,
+    pub offset: f32,
+    /// Coefficient of determination, `None` while the reference is constant
+    pub r_squared: Option<f32>,
+    pub count: usize,
+}
+
+impl LinearFit {
+    /// Fit of `(value, reference)` pairs, `None` with fewer than two distinct values
+    ///
+    /// Pairs with a NaN or infinite value are left out.
+    pub fn of(pairs: impl Iterator<Item = (f32, f32)>) -> Option<Self> {
+        let pairs = pairs
+            .filter(|(v, r)| v.is_finite() && r.is_finite())
+            .map(|(v, r)| (v as f64, r as f64))
+            .collect::<Vec<_>>();
+        let n = pairs.len() as f64;
+        let mean_v = pairs.iter().map(|(v, _)| v).sum::<f64>() / n;
+        let mean_r = pairs.iter().map(|(_, r)| r).sum::<f64>() / n;
+        let (mut svr, mut svv, mut srr) = (0.0, 0.0, 0.0);
+        for (v, r) in &pairs {
+            svr += (v - mean_v) * (r - mean_r);
+            svv += (v - mean_v) * (v - mean_v);
+            srr += (r - mean_r) * (r - mean_r);
+        }
+        if pairs.len() < 2 || svv <= 0.0 {
+            return None;
+        }
+
+        let slope = svr / svv;
+        Some(Self {
+            slope: slope as f32,
+            offset: (mean_r - slope * mean_v) as f32,
+            r_squared: (srr > 0.0).then(|| (svr * svr / (svv * srr)) as f32),
+            count: pairs.len(),
+        })
+    }
+
+    /// Channel `idx` of `history` since `from` against channel `ref_idx` of `reference`,
+    /// which is interpolated at the timestamps of `history`
+    pub fn between(
+        history: &History,
+        idx: usize,
+        reference: &History,
+        ref_idx: usize,
+        from: Option<DateTime<Local>>,
+    ) -> Option<Self> {
+        Self::of(history.range(from, None).filter_map(|s| {
+            let value = s.data.get(idx)?.value;
+            let r = *reference.value_at(s.timestamp)?.get(ref_idx)?;
+            Some((value, r))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_and_compose() {
+        // The device reads 10 % high with a 2 ppm offset
+        let pairs = [0.0, 10.0, 20.0, 40.0].map(|r| (1.1 * r + 2.0, r));
+        let fit = LinearFit::of(pairs.into_iter()).unwrap();
+        assert!((fit.slope - 1.0 / 1.1).abs() < 1e-5);
+        assert!(fit.r_squared.is_some_and(|r2| (r2 - 1.0).abs() < 1e-5));
+
+        let calibration = Calibration::identity(Unit::PPM).then(SensorType::CO, &fit, Unit::PPM);
+        assert!((calibration.apply(SensorType::CO, 46.0, Unit::PPM) - 40.0).abs() < 1e-4);
+        // A second fit on corrected values refines the first
+        let halved = LinearFit::of([(0.0, 0.0), (10.0, 5.0)].into_iter()).unwrap();
+        let refined = calibration.then(SensorType::CO, &halved, Unit::PPM);
+        assert!((refined.apply(SensorType::CO, 46.0, Unit::PPM) - 20.0).abs() < 1e-4);
+        // Offsets follow the unit of the channel
+        let ppb = Calibration {
+            slope: 1.0,
+            offset: 1.0,
+            unit: Unit::PPM,
+        };
+        assert!((ppb.apply(SensorType::CO, 0.0, Unit::PPB) - 1000.0).abs() < 1e-2);
+
+        assert!(LinearFit::of([(1.0, 2.0)].into_iter()).is_none());
+    }
+
+    #[test]
+    fn fit_skips_non_finite() {
+        let pairs = [
+            (2.0, 0.0),
+            (f32::NAN, 5.0),
+            (12.0, 10.0),
+            (7.0, f32::INFINITY),
+        ];
+        let fit = LinearFit::of(pairs.into_iter()).unwrap();
+
+        assert_eq!(fit.count, 2);
+        assert!((fit.slope - 1.0).abs() < 1e-5);
+        assert!((fit.offset + 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn device_ids() {
+        let info = SensorInfo {
+            serial_number: Some("A1234".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(device_id("/dev/ttyUSB0", Some(&info)), "A1234");
+        assert_eq!(
+            device_id("/dev/ttyUSB0", Some(&SensorInfo::default())),
+            "/dev/ttyUSB0"
+        );
+    }
+}
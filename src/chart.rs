@@ -0,0 +1,173 @@
+use std::{path::Path, sync::OnceLock};
+
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, Local};
+use plotters::{coord::Shift, prelude::*, style::register_font};
+
+use crate::sensor::{SampleData, SensorChannel};
+use crate::theme::{Rgb, channel_colors};
+
+/// Size of the rendered chart in pixels
+pub const CHART_SIZE: (u32, u32) = (1200, 600);
+
+const FONT: &str = "sans-serif";
+
+/// Text is drawn with the font bundled with egui, no system fonts are needed
+fn register_fonts() -> Result<()> {
+    static FONTS: OnceLock<bool> = OnceLock::new();
+    let registered = FONTS.get_or_init(|| {
+        register_font(FONT, FontStyle::Normal, epaint_default_fonts::UBUNTU_LIGHT).is_ok()
+    });
+    if !registered {
+        bail!("Failed to register the chart font");
+    }
+
+    Ok(())
+}
+
+fn draw<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    title: &str,
+    channels: &[SensorChannel],
+    colors: &[Rgb],
+    samples: &[&SampleData],
+) -> Result<()> {
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        bail!("No samples to plot");
+    };
+    let (min, max) = samples
+        .iter()
+        .flat_map(|s| s.data.iter().map(|d| d.value))
+        .fold((f32::MAX, f32::MIN), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    let margin = ((max - min) * 0.05).max(0.5);
+
+    // plotters errors borrow the backend, keep only the message
+    let err = |e: DrawingAreaErrorKind<DB::ErrorType>| anyhow!("{e}");
+
+    root.fill(&WHITE).map_err(err)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, (FONT, 24))
+        .margin(16)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(first.timestamp..last.timestamp, min - margin..max + margin)
+        .map_err(err)?;
+
+    let format = if last.timestamp - first.timestamp > chrono::TimeDelta::days(1) {
+        "%m-%d %H:%M"
+    } else {
+        "%H:%M:%S"
+    };
+    chart
+        .configure_mesh()
+        .x_labels(8)
+        .x_label_formatter(&|t: &DateTime<Local>| t.format(format).to_string())
+        .label_style((FONT, 14))
+        .draw()
+        .map_err(err)?;
+
+    for (idx, ch) in channels.iter().enumerate() {
+        let [r, g, b] = colors.get(idx).copied().unwrap_or([0, 0, 0]);
+        let style = RGBColor(r, g, b).stroke_width(2);
+        let points = samples
+            .iter()
+            .filter_map(|s| s.data.get(idx).map(|d| (s.timestamp, d.value)));
+
+        chart
+            .draw_series(LineSeries::new(points, style))
+            .map_err(err)?
+            .label(format!("{} ({})", ch.name(), ch.unit.as_ref()))
+            .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], style));
+    }
+
+    chart
+        .configure_series_labels()
+        .label_font((FONT, 14))
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(err)?;
+    root.present().map_err(err)?;
+
+    Ok(())
+}
+
+/// Render the samples as a line chart with axes and legend, PNG or SVG after the extension
+///
+/// The channels have the colors of the frontends.
+pub fn render_chart<'a>(
+    path: &Path,
+    title: &str,
+    channels: &[SensorChannel],
+    samples: impl Iterator<Item = &'a SampleData>,
+) -> Result<()> {
+    register_fonts()?;
+    let samples = samples.collect::<Vec<_>>();
+    let colors = &channel_colors(channels);
+
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("svg") => draw(
+            SVGBackend::new(path, CHART_SIZE).into_drawing_area(),
+            title,
+            channels,
+            colors,
+            &samples,
+        ),
+        Some("png") => draw(
+            BitMapBackend::new(path, CHART_SIZE).into_drawing_area(),
+            title,
+            channels,
+            colors,
+            &samples,
+        ),
+        _ => bail!("Unsupported chart format, use .png or .svg"),
+    }
+}
+
+/// Render the samples like `render_chart` into an SVG document, for embedding in reports
+pub fn chart_svg<'a>(
+    title: &str,
+    channels: &[SensorChannel],
+    samples: impl Iterator<Item = &'a SampleData>,
+) -> Result<String> {
+    register_fonts()?;
+    let samples = samples.collect::<Vec<_>>();
+    let mut svg = String::new();
+    draw(
+        SVGBackend::with_string(&mut svg, CHART_SIZE).into_drawing_area(),
+        title,
+        channels,
+        &channel_colors(channels),
+        &samples,
+    )?;
+
+    Ok(svg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::{SensorType, Unit};
+
+    #[test]
+    fn svg_has_title_and_legend() {
+        let start = Local::now();
+        let samples = (0..10)
+            .map(|n| SampleData::single(start, n, SensorType::CO, n as f32, Unit::PPM))
+            .collect::<Vec<_>>();
+        let path = std::env::temp_dir().join(format!("envsensor-{}.svg", std::process::id()));
+
+        render_chart(
+            &path,
+            "Test chart",
+            &[SensorChannel::new(SensorType::CO, Unit::PPM)],
+            samples.iter(),
+        )
+        .unwrap();
+
+        let svg = std::fs::read_to_string(&path).unwrap();
+        assert!(svg.contains("Test chart"));
+        assert!(svg.contains("CO (ppm)"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
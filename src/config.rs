@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::alert::Threshold;
+use crate::anomaly::AnomalyConfig;
+use crate::drift::DriftLimit;
+use crate::logger::LogConfig;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::metrics::MetricsConfig;
+use crate::modbus_tcp::ModbusTcpConfig;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::mqtt::MqttConfig;
+use crate::process::ProcessConfig;
+use crate::sensor::SerialSettings;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::sensor_community::SensorCommunityConfig;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::webhook::WebhookConfig;
+use crate::ws::WsConfig;
+
+/// Where a sensor is deployed, attached to every sample so data from
+/// several deployments can be merged
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Site {
+    pub name: String,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+}
+
+impl Site {
+    /// Header columns appended to the CSV log
+    pub const CSV_COLUMNS: [&str; 3] = ["Site", "Latitude", "Longitude"];
+
+    /// Values for `CSV_COLUMNS`, commas in the name are replaced to keep the CSV valid
+    pub fn csv_values(&self) -> String {
+        let coord = |c: Option<f64>| c.map(|c| c.to_string()).unwrap_or_default();
+
+        format!(
+            "{},{},{}",
+            self.name.replace(',', " "),
+            coord(self.lat),
+            coord(self.lon)
+        )
+    }
+
+    /// Topic-safe form of the name
+    pub fn slug(&self) -> String {
+        self.name
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect()
+    }
+}
+
+/// Runtime options for a sensor session
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub log: LogConfig,
+    pub serial: SerialSettings,
+    /// Minimum time between two readings, zero reads as fast as the sensor answers
+    pub interval: Duration,
+    /// Stop on its own after running this long
+    pub duration: Option<Duration>,
+    /// Operator notes stored in the session metadata
+    pub notes: String,
+    /// Deployment location added to logs and published data when set
+    pub site: Option<Site>,
+    /// Unit conversion and filtering, the raw values are logged alongside
+    pub process: ProcessConfig,
+    /// Alarm limits, crossings are broadcast as alerts
+    pub thresholds: Vec<Threshold>,
+    /// Baseline drift limits, exceeding one is reported as a status message
+    pub drift: Vec<DriftLimit>,
+    /// Rolling z-score detection, unusual readings are broadcast as anomalies when set
+    pub anomaly: Option<AnomalyConfig>,
+    /// Broadcast the raw serial traffic for debugging
+    pub capture: bool,
+    /// Broadcast rolling 1-min/15-min/1-h means and maxima, logged to a summary CSV
+    pub aggregate: bool,
+    /// Fill gaps between samples with interpolated ones for the aggregates
+    pub interpolate_gaps: bool,
+    /// Publish samples to an MQTT broker when set
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mqtt: Option<MqttConfig>,
+    /// Serve Prometheus metrics when set
+    #[cfg(not(target_arch = "wasm32"))]
+    pub metrics: Option<MetricsConfig>,
+    /// Stream samples to WebSocket clients when set
+    pub ws: Option<WsConfig>,
+    /// Act as a Modbus TCP slave when set
+    pub modbus_tcp: Option<ModbusTcpConfig>,
+    /// Publish the sensor on the D-Bus session bus (Linux, `dbus` feature)
+    pub dbus: bool,
+    /// POST samples to an HTTP endpoint when set
+    #[cfg(not(target_arch = "wasm32"))]
+    pub webhook: Option<WebhookConfig>,
+    /// Upload PM readings to Sensor.Community when set
+    #[cfg(not(target_arch = "wasm32"))]
+    pub sensor_community: Option<SensorCommunityConfig>,
+}
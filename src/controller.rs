@@ -0,0 +1,247 @@
+use std::{
+    sync::mpsc::Receiver,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use bus::Bus;
+
+use crate::{
+    config::Config,
+    process::SharedTemperature,
+    sensor::{AppMsg, Sensor, SensorModel},
+    serial_port_list,
+    simulator::SIM_PREFIX,
+    station::Station,
+    transport::tcp_address,
+    watch_serial_ports,
+};
+
+/// How often the serial ports are enumerated again
+const PORT_SCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Frontend independent part of the demos: choosing a sensor and a port, starting and
+/// stopping sensors, and taking in what they send
+pub struct Controller {
+    pub sensors: Vec<SensorModel>,
+    /// Index into `sensors`
+    pub sensor_choice: usize,
+    pub ports: Vec<String>,
+    /// Index into `ports`
+    pub port_choice: usize,
+    port_updates: Receiver<Vec<String>>,
+    pub stations: Vec<Station>,
+    /// Index of the station the frontend shows
+    pub active: usize,
+    /// Samples buffered per started station
+    history_len: usize,
+    /// Ambient temperature from whichever sensor measures it, for the compensation of all
+    temperature: SharedTemperature,
+}
+
+impl Controller {
+    /// Controller with the serial ports of this machine, watched for changes
+    pub fn new(history_len: usize) -> Self {
+        Self::with_ports(
+            serial_port_list(),
+            watch_serial_ports(PORT_SCAN_INTERVAL),
+            history_len,
+        )
+    }
+
+    /// Controller choosing from `ports`, later lists come from `port_updates`
+    pub fn with_ports(
+        ports: Vec<String>,
+        port_updates: Receiver<Vec<String>>,
+        history_len: usize,
+    ) -> Self {
+        Self {
+            sensors: SensorModel::all(),
+            sensor_choice: 0,
+            ports,
+            port_choice: 0,
+            port_updates,
+            stations: Vec::new(),
+            active: 0,
+            history_len,
+            temperature: SharedTemperature::default(),
+        }
+    }
+
+    /// The chosen sensor model
+    pub fn sensor(&self) -> Option<SensorModel> {
+        self.sensors.get(self.sensor_choice).copied()
+    }
+
+    /// The chosen serial port
+    pub fn port(&self) -> Option<&str> {
+        self.ports.get(self.port_choice).map(String::as_str)
+    }
+
+    /// Choose the sensor model called `name`, e.g. the one saved from the last run
+    pub fn select_sensor(&mut self, name: &str) {
+        if let Some(idx) = self.sensors.iter().position(|s| s.as_ref() == name) {
+            self.sensor_choice = idx;
+        }
+    }
+
+    pub fn select_port(&mut self, name: &str) {
+        if let Some(idx) = self.ports.iter().position(|p| p == name) {
+            self.port_choice = idx;
+        }
+    }
+
+    /// Add a port which isn't enumerated, e.g. a TCP bridge, and choose it
+    pub fn add_port(&mut self, name: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+        if !self.ports.iter().any(|p| p == name) {
+            self.ports.push(name.to_string());
+        }
+        self.select_port(name);
+    }
+
+    /// Replace the port list, keeping the chosen port if it's still there, returns the new ports
+    ///
+    /// TCP bridges and simulators added with `add_port` are kept.
+    pub fn set_ports(&mut self, mut ports: Vec<String>) -> Vec<String> {
+        ports.extend(
+            self.ports
+                .iter()
+                .filter(|p| {
+                    (tcp_address(p).is_some() || p.starts_with(SIM_PREFIX)) && !ports.contains(p)
+                })
+                .cloned()
+                .collect::<Vec<_>>(),
+        );
+        let added = ports
+            .iter()
+            .filter(|p| !self.ports.contains(p))
+            .cloned()
+            .collect();
+
+        self.port_choice = self
+            .port()
+            .and_then(|chosen| ports.iter().position(|p| p == chosen))
+            .unwrap_or(0);
+        self.ports = ports;
+
+        added
+    }
+
+    /// Take the latest port list from the watcher, `None` when it didn't change
+    pub fn update_ports(&mut self) -> Option<Vec<String>> {
+        let ports = self.port_updates.try_iter().last()?;
+        Some(self.set_ports(ports))
+    }
+
+    pub fn active_station(&self) -> Option<&Station> {
+        self.stations.get(self.active)
+    }
+
+    pub fn active_station_mut(&mut self) -> Option<&mut Station> {
+        self.stations.get_mut(self.active)
+    }
+
+    /// Add a station, e.g. loaded from a log, and show it
+    pub fn add_station(&mut self, station: Station) -> &mut Station {
+        self.stations.push(station);
+        self.active = self.stations.len() - 1;
+        &mut self.stations[self.active]
+    }
+
+    /// Remove the station at `idx`, stopping its sensor
+    pub fn close(&mut self, idx: usize) {
+        if idx < self.stations.len() {
+            self.stations.remove(idx).stop();
+            if self.active >= idx {
+                self.active = self.active.saturating_sub(1);
+            }
+        }
+    }
+
+    /// Stop all stations and wait until their logs are closed
+    pub fn shut_down(&mut self) {
+        for station in &mut self.stations {
+            station.shut_down();
+        }
+    }
+
+    /// Start the chosen sensor on the chosen port as a new station and show it
+    pub fn start(&mut self, mut config: Config) -> Result<&mut Station> {
+        let model = self.sensor().context("No sensor chosen")?;
+        let port = self.port().context("No serial port available")?.to_string();
+        if self
+            .stations
+            .iter()
+            .any(|s| s.is_running() && s.port.as_ref() == Some(&port))
+        {
+            bail!("{port} is already in use");
+        }
+
+        let mut bus = Bus::new(10);
+        let rx = bus.add_rx();
+        let duration = config.duration;
+        config.process.temperature = self.temperature.clone();
+        let sensor = Sensor::new(&model, &port, rx, config)?;
+        sensor.start(bus)?;
+
+        let mut station = Station::new(format!("{} @ {port}", model.as_ref()), self.history_len);
+        station.port = Some(port);
+        station.model = Some(model);
+        station.deadline = duration.map(|d| Instant::now() + d);
+        station.started = Some(Instant::now());
+        station.sensor = Some(sensor);
+
+        Ok(self.add_station(station))
+    }
+
+    /// Take in what all sensors sent since the last call, returns all but the samples with the
+    /// index of their station
+    pub fn poll(&mut self) -> Vec<(usize, AppMsg)> {
+        self.stations
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(idx, station)| station.poll().into_iter().map(move |msg| (idx, msg)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn port_choice_follows_updates() {
+        let (tx, rx) = mpsc::channel();
+        let mut controller = Controller::with_ports(vec!["COM1".into(), "COM3".into()], rx, 10);
+        controller.select_port("COM3");
+        assert!(controller.update_ports().is_none());
+
+        // The chosen port stays chosen when another one appears before it
+        tx.send(vec!["COM1".into(), "COM2".into(), "COM3".into()])
+            .unwrap();
+        assert_eq!(controller.update_ports(), Some(vec!["COM2".to_string()]));
+        assert_eq!(controller.port(), Some("COM3"));
+
+        // And falls back to the first one once it's gone
+        controller.set_ports(vec!["COM2".into()]);
+        assert_eq!(controller.port(), Some("COM2"));
+
+        // Bridges aren't enumerated, they stay
+        controller.add_port(" tcp://10.0.0.2:4001 ");
+        assert_eq!(controller.port(), Some("tcp://10.0.0.2:4001"));
+        controller.set_ports(vec!["COM2".into()]);
+        assert_eq!(controller.port(), Some("tcp://10.0.0.2:4001"));
+
+        controller.ports.clear();
+        controller.set_ports(Vec::new());
+        assert!(controller.port().is_none());
+        assert!(controller.start(Config::default()).is_err());
+        assert!(controller.stations.is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+
+use crate::sensor::{SensorType, SizeBin, Unit};
+
+/// Molar volume of an ideal gas at 25 °C and 1013.25 hPa, in litres
+pub const MOLAR_VOLUME: f32 = 24.45;
+
+const ZERO_CELSIUS: f32 = 273.15;
+
+/// Air temperature in °C and pressure in hPa, standard conditions by default
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Ambient {
+    pub temperature: f32,
+    pub pressure: f32,
+}
+
+impl Default for Ambient {
+    fn default() -> Self {
+        Self {
+            temperature: 25.0,
+            pressure: 1013.25,
+        }
+    }
+}
+
+impl Ambient {
+    /// Molar volume of an ideal gas in these conditions, in litres
+    pub fn molar_volume(&self) -> f32 {
+        let standard = Self::default();
+        MOLAR_VOLUME * (self.temperature + ZERO_CELSIUS) / (standard.temperature + ZERO_CELSIUS)
+            * standard.pressure
+            / self.pressure
+    }
+}
+
+/// Molar mass in g/mol, only known for gases
+pub fn molar_mass(ty: SensorType) -> Option<f32> {
+    match ty {
+        SensorType::CO => Some(28.01),
+        SensorType::NO2 => Some(46.01),
+        SensorType::PM1
+        | SensorType::PM2_5
+        | SensorType::PM10
+        | SensorType::PM1Dry
+        | SensorType::PM2_5Dry
+        | SensorType::PM10Dry => None,
+        SensorType::Temperature
+        | SensorType::Humidity
+        | SensorType::DewPoint
+        | SensorType::AbsoluteHumidity
+        | SensorType::ParticleCount => None,
+    }
+}
+
+/// Gas and particle mass concentrations, the other types have one unit each
+pub fn is_concentration(ty: SensorType) -> bool {
+    !matches!(
+        ty,
+        SensorType::Temperature
+            | SensorType::Humidity
+            | SensorType::DewPoint
+            | SensorType::AbsoluteHumidity
+            | SensorType::ParticleCount
+    )
+}
+
+fn is_volumetric(unit: Unit) -> bool {
+    matches!(unit, Unit::PPM | Unit::PPB | Unit::PercentVol)
+}
+
+/// Factor to ppm for volume ratios, to mg/m³ for mass concentrations
+fn base_factor(unit: Unit) -> Option<f32> {
+    match unit {
+        Unit::PPM | Unit::MgPerM3 => Some(1.0),
+        Unit::PPB | Unit::UgPerM3 => Some(0.001),
+        Unit::PercentVol | Unit::TenGPerM3 => Some(10_000.0),
+        Unit::GPerM3 | Unit::Celsius | Unit::PercentRH | Unit::PerCm3 => None,
+    }
+}
+
+/// Convert a reading between units, `None` when it needs a molar mass the gas doesn't have
+pub fn convert(value: f32, ty: SensorType, from: Unit, to: Unit) -> Option<f32> {
+    convert_at(value, ty, from, to, Ambient::default())
+}
+
+/// Convert a reading between units in the `ambient` conditions rather than standard ones
+pub fn convert_at(
+    value: f32,
+    ty: SensorType,
+    from: Unit,
+    to: Unit,
+    ambient: Ambient,
+) -> Option<f32> {
+    if !is_concentration(ty) {
+        return (from == to).then_some(value);
+    }
+    let base = value * base_factor(from)?;
+    let molar_volume = ambient.molar_volume();
+
+    let base = match (is_volumetric(from), is_volumetric(to)) {
+        (true, false) => base * molar_mass(ty)? / molar_volume,
+        (false, true) => base * molar_volume / molar_mass(ty)?,
+        _ => base,
+    };
+
+    Some(base / base_factor(to)?)
+}
+
+/// Density in g/cm³ usually assumed for ambient particles when estimating mass from counts
+pub const PARTICLE_DENSITY: f32 = 1.65;
+
+/// Mass concentration in µg/m³ of the particles below `size` µm, estimated from number
+/// concentrations in #/cm³ of non-overlapping size bins
+///
+/// Each particle is taken as a sphere of `density` g/cm³ with the geometric mean diameter of
+/// its bin. A bin reaching past `size` counts with the share of its range below it.
+pub fn mass_from_counts(counts: &[(SizeBin, f32)], size: f32, density: f32) -> f32 {
+    counts
+        .iter()
+        .filter(|(bin, _)| bin.from < size && bin.to > bin.from)
+        .map(|&(bin, count)| {
+            let share = ((size - bin.from) / (bin.to - bin.from)).min(1.0);
+            let diameter = (bin.from.max(0.01) * bin.to.min(size)).sqrt();
+            // #/cm³ to #/m³ and µm³ to cm³ cancel out with g to µg
+            count * share * density * std::f32::consts::PI / 6.0 * diameter.powi(3)
+        })
+        .sum()
+}
+
+/// Coefficients of the Magnus formula over water, Sonntag 1990
+const MAGNUS_A: f32 = 17.62;
+const MAGNUS_B: f32 = 243.12;
+
+/// Dew point in °C of air at `t` °C and `rh` % relative humidity
+pub fn dew_point(t: f32, rh: f32) -> f32 {
+    // Dry air has no dew point, keep the logarithm finite
+    let gamma = (rh.max(0.1) / 100.0).ln() + MAGNUS_A * t / (MAGNUS_B + t);
+    MAGNUS_B * gamma / (MAGNUS_A - gamma)
+}
+
+/// Water vapour in g/m³ of air at `t` °C and `rh` % relative humidity
+pub fn absolute_humidity(t: f32, rh: f32) -> f32 {
+    // Saturation vapour pressure in hPa
+    let saturation = 6.112 * (MAGNUS_A * t / (MAGNUS_B + t)).exp();
+    // Molar mass of water over the gas constant, in g·K/J, with hPa to Pa
+    saturation * rh * 2.1674 / (273.15 + t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mass_of_counts() {
+        let counts = [
+            (SizeBin { from: 0.3, to: 1.0 }, 100.0),
+            (
+                SizeBin {
+                    from: 1.0,
+                    to: 10.0,
+                },
+                1.0,
+            ),
+        ];
+
+        // 100 spheres of 0.548 µm a cm³ at 1 g/cm³
+        let pm1 = mass_from_counts(&counts, 1.0, 1.0);
+        assert!((pm1 - 8.61).abs() < 0.01);
+
+        // A sixth of the second bin, of 1.58 µm
+        let pm2_5 = mass_from_counts(&counts, 2.5, 1.0);
+        assert!((pm2_5 - pm1 - 2.07 / 6.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn gas_conversions() {
+        let mg = convert(1.0, SensorType::CO, Unit::PPM, Unit::MgPerM3).unwrap();
+        assert!((mg - 1.1456).abs() < 1e-3);
+
+        let ppm = convert(mg, SensorType::CO, Unit::MgPerM3, Unit::PPM).unwrap();
+        assert!((ppm - 1.0).abs() < 1e-5);
+
+        assert_eq!(
+            convert(2.5, SensorType::NO2, Unit::PPM, Unit::PPB),
+            Some(2500.0)
+        );
+        assert_eq!(
+            convert(10.0, SensorType::PM2_5, Unit::UgPerM3, Unit::PPB),
+            None
+        );
+
+        // Warmer, thinner air holds less gas per volume
+        let ambient = Ambient {
+            temperature: 35.0,
+            pressure: 900.0,
+        };
+        let thin = convert_at(1.0, SensorType::CO, Unit::PPM, Unit::MgPerM3, ambient).unwrap();
+        assert!((thin - mg * 298.15 / 308.15 * 900.0 / 1013.25).abs() < 1e-4);
+    }
+
+    #[test]
+    fn humidity() {
+        assert!((dew_point(20.0, 50.0) - 9.26).abs() < 0.01);
+        assert!((absolute_humidity(20.0, 50.0) - 8.62).abs() < 0.01);
+        assert_eq!(
+            convert(21.5, SensorType::Temperature, Unit::Celsius, Unit::Celsius),
+            Some(21.5)
+        );
+        assert_eq!(
+            convert(1.0, SensorType::Humidity, Unit::PercentRH, Unit::PPM),
+            None
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::RecvTimeoutError,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use bus::BusReader;
+use zbus::{blocking::connection, interface, object_server::SignalEmitter};
+
+use crate::sensor::{AppMsg, SensorChannel, SensorModel};
+
+const NAME: &str = "org.envsensor.Demo";
+const PATH: &str = "/org/envsensor/Demo";
+
+/// State published on the session bus, values are keyed by `SensorChannel::id()`
+struct Demo {
+    model: String,
+    status: String,
+    timestamp: i64,
+    latest: HashMap<String, f64>,
+}
+
+#[interface(name = "org.envsensor.Demo")]
+impl Demo {
+    #[zbus(property)]
+    fn model(&self) -> String {
+        self.model.clone()
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> String {
+        self.status.clone()
+    }
+
+    /// Unix time (seconds) of the latest sample
+    #[zbus(property)]
+    fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    #[zbus(property)]
+    fn latest(&self) -> HashMap<String, f64> {
+        self.latest.clone()
+    }
+
+    #[zbus(signal)]
+    async fn new_sample(
+        emitter: &SignalEmitter<'_>,
+        timestamp: i64,
+        values: HashMap<String, f64>,
+    ) -> zbus::Result<()>;
+
+    /// A channel crossed or came back within one of its alarm limits
+    #[zbus(signal)]
+    async fn alert(emitter: &SignalEmitter<'_>, message: String) -> zbus::Result<()>;
+}
+
+/// Publish the running sensor as `org.envsensor.Demo` on the session bus
+pub fn spawn_dbus_thread(
+    model: SensorModel,
+    flag: Arc<AtomicBool>,
+    mut rx: BusReader<AppMsg>,
+    channels: &[SensorChannel],
+) -> Result<()> {
+    let demo = Demo {
+        model: model.as_ref().to_string(),
+        status: String::new(),
+        timestamp: 0,
+        latest: HashMap::new(),
+    };
+
+    let conn = connection::Builder::session()?
+        .name(NAME)?
+        .serve_at(PATH, demo)?
+        .build()?;
+
+    let ids = channels.iter().map(|ch| ch.id()).collect::<Vec<_>>();
+
+    thread::spawn(move || -> Result<()> {
+        let iface = conn.object_server().interface::<_, Demo>(PATH)?;
+        let emitter = iface.signal_emitter();
+
+        while !flag.load(Ordering::SeqCst) {
+            match rx.recv_timeout(Duration::from_millis(500)) {
+                Ok(AppMsg::Sample(sample)) => {
+                    let values = ids
+                        .iter()
+                        .cloned()
+                        .zip(sample.data.iter().map(|d| d.value as f64))
+                        .collect::<HashMap<_, _>>();
+                    let timestamp = sample.timestamp.timestamp();
+
+                    let mut demo = iface.get_mut();
+                    demo.latest = values.clone();
+                    demo.timestamp = timestamp;
+                    zbus::block_on(async {
+                        demo.latest_changed(emitter).await?;
+                        demo.timestamp_changed(emitter).await?;
+                        Demo::new_sample(emitter, timestamp, values).await
+                    })?;
+                }
+                Ok(AppMsg::Status(status) | AppMsg::Error(status)) => {
+                    let mut demo = iface.get_mut();
+                    demo.status = status;
+                    zbus::block_on(demo.status_changed(emitter))?;
+                }
+                Ok(AppMsg::Alert(alert)) => {
+                    zbus::block_on(Demo::alert(emitter, alert.to_string()))?;
+                }
+                Ok(AppMsg::Anomaly(anomaly)) => {
+                    zbus::block_on(Demo::alert(emitter, anomaly.to_string()))?;
+                }
+                Ok(AppMsg::Traffic(_) | AppMsg::Marker(_) | AppMsg::Aggregate(_))
+                | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        // Dropping the connection releases the well-known name
+        Ok(())
+    });
+
+    Ok(())
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+
+use chrono::{DateTime, Local, TimeDelta};
+use serde::{Deserialize, Serialize};
+
+use crate::convert::convert;
+use crate::sensor::{SampleData, SensorType, Unit};
+
+/// Daily baselines kept for the drift rate
+const BASELINE_DAYS: usize = 14;
+
+/// Largest baseline change per day of one sensor type before a recalibration is due,
+/// converted to the unit of each channel of the type
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DriftLimit {
+    pub sensor_type: SensorType,
+    /// Unit of the limit, the one of the first channel of the type when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<Unit>,
+    pub per_day: f32,
+}
+
+impl DriftLimit {
+    /// The limit converted to `unit`, `None` when there's no conversion between the units
+    fn in_unit(&self, unit: Unit) -> Option<f32> {
+        match self.unit {
+            Some(from) if from != unit => convert(self.per_day, self.sensor_type, from, unit),
+            _ => Some(self.per_day),
+        }
+    }
+}
+
+/// A channel's baseline moves faster than its `DriftLimit`
+#[derive(Clone, Debug)]
+pub struct Drift {
+    pub sensor_type: SensorType,
+    pub unit: Unit,
+    /// Baseline change per day over the days seen so far
+    pub per_day: f32,
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} baseline drifts by {:+.3} {}/day, recalibration recommended",
+            self.sensor_type.as_ref(),
+            self.per_day,
+            self.unit.as_ref()
+        )
+    }
+}
+
+/// Baseline of one channel, the lowest hourly mean of each day
+#[derive(Default)]
+struct Baseline {
+    hour_start: Option<DateTime<Local>>,
+    hour_sum: f32,
+    hour_count: usize,
+    day_start: Option<DateTime<Local>>,
+    day_min: Option<f32>,
+    /// Days since the first one and their baseline
+    days: Vec<(f32, f32)>,
+}
+
+impl Baseline {
+    /// Take in a value, true when a day's baseline was completed
+    fn push(&mut self, timestamp: DateTime<Local>, value: f32) -> bool {
+        // A NaN would stick in the hourly sum and the day's minimum
+        if !value.is_finite() {
+            return false;
+        }
+        let hour_start = *self.hour_start.get_or_insert(timestamp);
+        let day_start = *self.day_start.get_or_insert(timestamp);
+
+        if timestamp - hour_start >= TimeDelta::hours(1) {
+            if self.hour_count > 0 {
+                let mean = self.hour_sum / self.hour_count as f32;
+                self.day_min = Some(self.day_min.map_or(mean, |min| min.min(mean)));
+            }
+            self.hour_start = Some(timestamp);
+            self.hour_sum = 0.0;
+            self.hour_count = 0;
+        }
+        self.hour_sum += value;
+        self.hour_count += 1;
+
+        if timestamp - day_start < TimeDelta::days(1) {
+            return false;
+        }
+        self.day_start = Some(timestamp);
+        let Some(min) = self.day_min.take() else {
+            return false;
+        };
+
+        let day = self.days.last().map_or(0.0, |&(day, _)| day + 1.0);
+        self.days.push((day, min));
+        if self.days.len() > BASELINE_DAYS {
+            self.days.remove(0);
+        }
+        true
+    }
+
+    /// Least squares slope of the daily baselines
+    fn per_day(&self) -> Option<f32> {
+        if self.days.len() < 2 {
+            return None;
+        }
+
+        let n = self.days.len() as f32;
+        let mean_x = self.days.iter().map(|(x, _)| x).sum::<f32>() / n;
+        let mean_y = self.days.iter().map(|(_, y)| y).sum::<f32>() / n;
+        let (cov, var) = self.days.iter().fold((0.0, 0.0), |(cov, var), (x, y)| {
+            (
+                cov + (x - mean_x) * (y - mean_y),
+                var + (x - mean_x).powi(2),
+            )
+        });
+
+        Some(cov / var)
+    }
+}
+
+/// Follows the long-term baseline of each channel, reported once per day while it drifts
+/// beyond its limit
+pub struct DriftMonitor {
+    limits: Vec<DriftLimit>,
+    baselines: Vec<Baseline>,
+}
+
+impl DriftMonitor {
+    pub fn new(limits: Vec<DriftLimit>) -> Self {
+        Self {
+            limits,
+            baselines: Vec::new(),
+        }
+    }
+
+    pub fn check(&mut self, sample: &SampleData) -> Vec<Drift> {
+        if self.limits.is_empty() {
+            return Vec::new();
+        }
+        if self.baselines.len() != sample.data.len() {
+            for limit in &mut self.limits {
+                limit.unit = limit.unit.or_else(|| {
+                    let d = sample.data.iter().find(|d| d.ty == limit.sensor_type)?;
+                    Some(d.unit)
+                });
+            }
+        }
+        self.baselines
+            .resize_with(sample.data.len(), Baseline::default);
+
+        let mut drifts = Vec::new();
+        for (d, baseline) in sample.data.iter().zip(&mut self.baselines) {
+            let Some(limit) = self
+                .limits
+                .iter()
+                .find(|l| l.sensor_type == d.ty)
+                .and_then(|l| l.in_unit(d.unit))
+            else {
+                continue;
+            };
+            if !baseline.push(sample.timestamp, d.value) {
+                continue;
+            }
+            if let Some(per_day) = baseline.per_day()
+                && per_day.abs() > limit
+            {
+                drifts.push(Drift {
+                    sensor_type: d.ty,
+                    unit: d.unit,
+                    per_day,
+                });
+            }
+        }
+
+        drifts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rising_baseline() {
+        let mut monitor = DriftMonitor::new(vec![DriftLimit {
+            sensor_type: SensorType::CO,
+            unit: None,
+            per_day: 0.5,
+        }]);
+        let start = Local::now();
+        let mut drifts = Vec::new();
+
+        // Every 10 minutes for 4 days, a daily peak on a baseline rising by 1 ppm a day
+        for n in 0..=4 * 24 * 6 {
+            let hours = n as f32 / 6.0;
+            let peak = if hours % 24.0 > 12.0 { 5.0 } else { 0.0 };
+            let sample = SampleData::single(
+                start,
+                n * 600,
+                SensorType::CO,
+                hours / 24.0 + peak,
+                Unit::PPM,
+            );
+            drifts.extend(monitor.check(&sample));
+        }
+
+        // Reported from the second day on
+        assert_eq!(drifts.len(), 3);
+        assert!((drifts[2].per_day - 1.0).abs() < 0.05);
+    }
+}
@@ -0,0 +1,136 @@
+use std::{fs, path::Path};
+
+use anyhow::{Result, bail};
+use rust_xlsxwriter::{Chart, ChartFormat, ChartLine, ChartType, Color, Format, Workbook};
+use serde_json::json;
+
+use crate::logger::{TimestampFormat, csv_header, csv_row};
+use crate::sensor::{SampleData, SensorChannel};
+use crate::session::Session;
+use crate::stats::ChannelStats;
+use crate::theme::channel_colors;
+
+/// Write a session to an .xlsx workbook with a data sheet, a stats sheet and a line chart
+pub fn export_xlsx(session: &Session, path: &Path) -> Result<()> {
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+    let datetime = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+    let rows = session.samples.len() as u32;
+    let cols = session.channels.len() as u16;
+
+    let data = workbook.add_worksheet().set_name("Data")?;
+    data.write_string_with_format(0, 0, "Timestamp", &bold)?;
+    data.set_column_width(0, 20)?;
+    for col in 0..cols {
+        data.write_string_with_format(0, col + 1, session.channel_name(col as usize), &bold)?;
+        data.set_column_width(col + 1, 14)?;
+    }
+
+    for (row, sample) in session.samples.iter().enumerate() {
+        let row = row as u32 + 1;
+        data.write_datetime_with_format(row, 0, sample.timestamp.naive_local(), &datetime)?;
+        for (col, d) in sample.data.iter().enumerate() {
+            data.write_number(row, col as u16 + 1, d.value)?;
+        }
+    }
+
+    let mut chart = Chart::new(ChartType::Line);
+    chart.title().set_name("Sensor data");
+    chart.x_axis().set_name("Time");
+    // In the colors of the frontends
+    let colors = channel_colors(&session.channels);
+    for (col, [r, g, b]) in (1..=cols).zip(colors) {
+        let color = Color::RGB(u32::from_be_bytes([0, r, g, b]));
+        chart
+            .add_series()
+            .set_name(("Data", 0, col))
+            .set_categories(("Data", 1, 0, rows, 0))
+            .set_values(("Data", 1, col, rows, col))
+            .set_format(ChartFormat::new().set_line(ChartLine::new().set_color(color)));
+    }
+    data.insert_chart(1, cols + 2, &chart)?;
+
+    let stats = workbook.add_worksheet().set_name("Stats")?;
+    for (col, name) in ["Channel", "Min", "Max", "Mean", "Std dev", "Samples"]
+        .iter()
+        .enumerate()
+    {
+        stats.write_string_with_format(0, col as u16, *name, &bold)?;
+    }
+    stats.set_column_width(0, 16)?;
+
+    for idx in 0..session.channels.len() {
+        let row = idx as u32 + 1;
+        stats.write_string(row, 0, session.channel_name(idx))?;
+        // Samples with a value of this channel, not all rows
+        let stats_of = ChannelStats::from_values(session.values(idx));
+        if let Some(s) = &stats_of {
+            stats.write_number(row, 1, s.min)?;
+            stats.write_number(row, 2, s.max)?;
+            stats.write_number(row, 3, s.mean)?;
+            stats.write_number(row, 4, s.stddev)?;
+        }
+        stats.write_number(row, 5, stats_of.map_or(0, |s| s.count) as u32)?;
+    }
+
+    workbook.save(path)?;
+
+    Ok(())
+}
+
+/// Write a slice of samples to CSV or JSON, picked by the file extension
+///
+/// The CSV uses RFC 3339 timestamps so it can be opened again with `Session::load_csv`.
+pub fn export_samples<'a>(
+    channels: &[SensorChannel],
+    samples: impl Iterator<Item = &'a SampleData>,
+    path: &Path,
+) -> Result<usize> {
+    let samples = samples.collect::<Vec<_>>();
+
+    let content = match path.extension().and_then(|e| e.to_str()) {
+        Some("csv") => {
+            let mut content = csv_header(channels, None);
+            for sample in &samples {
+                content.push('\n');
+                content.push_str(&csv_row(sample, &TimestampFormat::Rfc3339));
+            }
+            content.push('\n');
+            content
+        }
+        Some("json") => {
+            serde_json::to_string_pretty(&json!({ "channels": channels, "samples": samples }))?
+        }
+        _ => bail!("Unsupported export format: {}", path.display()),
+    };
+
+    fs::write(path, content)?;
+
+    Ok(samples.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Local;
+
+    use super::*;
+    use crate::sensor::{SensorType, Unit};
+
+    #[test]
+    fn csv_selection_round_trip() {
+        let channels = [SensorChannel::new(SensorType::CO, Unit::PPM)];
+        let samples = (0..3)
+            .map(|i| SampleData::single(Local::now(), 0, SensorType::CO, i as f32, Unit::PPM))
+            .collect::<Vec<_>>();
+
+        let path =
+            std::env::temp_dir().join(format!("envsensor-export-{}.csv", std::process::id()));
+        let written = export_samples(&channels, samples[1..].iter(), &path).unwrap();
+        let session = Session::load_csv(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(written, 2);
+        assert_eq!(session.samples.len(), 2);
+        assert_eq!(session.values(0).collect::<Vec<_>>(), vec![1.0, 2.0]);
+    }
+}
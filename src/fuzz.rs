@@ -0,0 +1,26 @@
+use crate::{nextpm, rydason, tb600b_c};
+
+// Entry points of the targets in fuzz/, each feeds arbitrary bytes to every reply decoder
+// of one driver. Errors are expected, a panic is a bug: a malformed frame must never take
+// the sensor thread down. New drivers get a function here and a target of their own.
+
+/// TB600B-C parameter replies and auto reports
+pub fn tb600b_c(data: &[u8]) {
+    let scale = tb600b_c::decode_params(data).map_or(1, |(_, scale, _)| scale);
+    let _ = tb600b_c::decode_auto_report(data, scale);
+}
+
+/// Rydason Modbus RTU register replies
+pub fn rydason(data: &[u8]) {
+    let _ = rydason::decode_type(data);
+    let _ = rydason::decode_unit(data);
+    let scale = rydason::decode_scale(data).unwrap_or(1);
+    let _ = rydason::decode_measured(data, scale);
+}
+
+/// NextPM reading, temperature/humidity and firmware replies
+pub fn nextpm(data: &[u8]) {
+    let _ = nextpm::decode_reading(data);
+    let _ = nextpm::decode_temp_humidity(data);
+    let _ = nextpm::decode_firmware(data);
+}
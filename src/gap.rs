@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use chrono::{DateTime, Local, TimeDelta};
+
+use crate::sensor::{Quality, SampleData};
+
+/// A gap is a pause between two samples longer than this many typical intervals
+pub const GAP_FACTOR: i32 = 3;
+
+/// Recent intervals the typical one is taken from
+const INTERVALS: usize = 15;
+
+/// Gaps in a row after which they are taken as the new typical interval
+const RESEED_GAPS: usize = 3;
+
+/// Time without samples, the sensor stalled or was reconnected
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gap {
+    /// Last sample before the gap
+    pub from: DateTime<Local>,
+    /// First sample after it
+    pub to: DateTime<Local>,
+}
+
+impl Gap {
+    pub fn duration(&self) -> TimeDelta {
+        self.to - self.from
+    }
+}
+
+impl fmt::Display for Gap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Gap of {:.1} s", self.duration().as_seconds_f64())
+    }
+}
+
+/// Watches the time between samples for pauses much longer than the usual interval
+pub struct GapDetector {
+    /// Shortest interval to expect, the configured poll interval
+    min_interval: TimeDelta,
+    intervals: VecDeque<TimeDelta>,
+    /// Intervals of the gaps in a row so far
+    gaps: Vec<TimeDelta>,
+    last: Option<DateTime<Local>>,
+}
+
+impl GapDetector {
+    pub fn new(min_interval: TimeDelta) -> Self {
+        Self {
+            min_interval,
+            intervals: VecDeque::new(),
+            gaps: Vec::new(),
+            last: None,
+        }
+    }
+
+    /// Median of the recent intervals, never below the configured one
+    pub fn expected_interval(&self) -> Option<TimeDelta> {
+        let mut intervals = self.intervals.iter().copied().collect::<Vec<_>>();
+        intervals.sort();
+        let median = *intervals.get(intervals.len() / 2)?;
+        Some(median.max(self.min_interval))
+    }
+
+    /// Take in the time of a new sample, the gap before it if there is one
+    ///
+    /// Gaps don't count towards the typical interval, unless `RESEED_GAPS` come in a row. The
+    /// sensor then got slower rather than missed samples, and they become the typical interval.
+    pub fn push(&mut self, timestamp: DateTime<Local>) -> Option<Gap> {
+        let last = self.last.replace(timestamp)?;
+        let interval = timestamp - last;
+
+        if let Some(expected) = self.expected_interval()
+            && interval > expected * GAP_FACTOR
+        {
+            self.gaps.push(interval);
+            if self.gaps.len() < RESEED_GAPS {
+                return Some(Gap {
+                    from: last,
+                    to: timestamp,
+                });
+            }
+            self.intervals = self.gaps.drain(..).collect();
+            return None;
+        }
+
+        self.gaps.clear();
+        self.intervals.push_back(interval);
+        if self.intervals.len() > INTERVALS {
+            self.intervals.pop_front();
+        }
+        None
+    }
+}
+
+/// Samples every `step` inside the gap between `prev` and `next`, linear between them and
+/// flagged as interpolated
+pub fn interpolate(prev: &SampleData, next: &SampleData, step: TimeDelta) -> Vec<SampleData> {
+    let span = (next.timestamp - prev.timestamp).as_seconds_f64() as f32;
+    if step <= TimeDelta::zero() || span <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut samples = Vec::new();
+    let mut timestamp = prev.timestamp + step;
+    while timestamp < next.timestamp {
+        let t = (timestamp - prev.timestamp).as_seconds_f64() as f32 / span;
+        let data = prev
+            .data
+            .iter()
+            .zip(&next.data)
+            .map(|(a, b)| {
+                let mut d = a.clone();
+                d.value = a.value + (b.value - a.value) * t;
+                d.raw = None;
+                d.quality = Quality::Interpolated;
+                d
+            })
+            .collect();
+        samples.push(SampleData { timestamp, data });
+        timestamp += step;
+    }
+
+    samples
+}
+
+/// Split `points` in time order into runs without gaps, `x` gives the time of a point in
+/// seconds
+pub fn split_at_gaps<T>(points: &[T], x: impl Fn(&T) -> f64) -> Vec<&[T]> {
+    let mut intervals = points
+        .windows(2)
+        .map(|pair| x(&pair[1]) - x(&pair[0]))
+        .collect::<Vec<_>>();
+    intervals.sort_by(f64::total_cmp);
+    let Some(&median) = intervals.get(intervals.len() / 2) else {
+        return vec![points];
+    };
+    let limit = median * GAP_FACTOR as f64;
+
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for n in 1..points.len() {
+        if x(&points[n]) - x(&points[n - 1]) > limit {
+            runs.push(&points[start..n]);
+            start = n;
+        }
+    }
+    runs.push(&points[start..]);
+
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::{SensorType, Unit};
+
+    #[test]
+    fn detect_and_fill_gaps() {
+        let start = Local::now();
+        let sample =
+            |secs, value| SampleData::single(start, secs, SensorType::CO, value, Unit::PPM);
+
+        let mut detector = GapDetector::new(TimeDelta::zero());
+        let gaps = [0, 1, 2, 3, 10, 11]
+            .into_iter()
+            .filter_map(|secs| detector.push(start + TimeDelta::seconds(secs)))
+            .collect::<Vec<_>>();
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].duration(), TimeDelta::seconds(7));
+        assert_eq!(detector.expected_interval(), Some(TimeDelta::seconds(1)));
+
+        let filled = interpolate(&sample(3, 3.0), &sample(10, 10.0), TimeDelta::seconds(1));
+        assert_eq!(filled.len(), 6);
+        assert_eq!(filled[0].data[0].value, 4.0);
+        assert_eq!(filled[0].data[0].quality, Quality::Interpolated);
+
+        // Slower from 11 s on, only the first two are gaps
+        let gaps = [21, 31, 41, 51]
+            .into_iter()
+            .filter_map(|secs| detector.push(start + TimeDelta::seconds(secs)))
+            .count();
+        assert_eq!(gaps, 2);
+        assert_eq!(detector.expected_interval(), Some(TimeDelta::seconds(10)));
+
+        let xs = [0.0, 1.0, 2.0, 3.0, 10.0, 11.0];
+        let runs = split_at_gaps(&xs, |x| *x);
+        assert_eq!(runs, vec![&xs[..4], &xs[4..]]);
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use bus::Bus;
+use tokio::sync::broadcast;
+use tokio_stream::{StreamExt, wrappers::BroadcastStream};
+use tonic::{Request, Response, Status, transport::Server};
+
+use crate::config::Config;
+use crate::sensor::{AppMsg, SampleData, Sensor, SensorModel};
+use crate::serial_port_list;
+
+pub mod proto {
+    tonic::include_proto!("envsensor");
+}
+
+use proto::sensor_service_server::{SensorService, SensorServiceServer};
+
+struct Running {
+    sensor: Sensor,
+    model: SensorModel,
+    port: String,
+}
+
+struct SensorServiceImpl {
+    config: Config,
+    running: Arc<Mutex<Option<Running>>>,
+    samples: broadcast::Sender<SampleData>,
+}
+
+impl From<SampleData> for proto::Sample {
+    fn from(sample: SampleData) -> Self {
+        proto::Sample {
+            timestamp_ms: sample.timestamp.timestamp_millis(),
+            readings: sample
+                .data
+                .into_iter()
+                .map(|d| proto::Reading {
+                    r#type: d.ty.as_ref().to_string(),
+                    value: d.value,
+                    unit: d.unit.as_ref().to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl SensorService for SensorServiceImpl {
+    async fn list_sensors(
+        &self,
+        _: Request<proto::ListSensorsRequest>,
+    ) -> Result<Response<proto::ListSensorsResponse>, Status> {
+        let running = self.running.lock().unwrap();
+
+        Ok(Response::new(proto::ListSensorsResponse {
+            models: SensorModel::all()
+                .iter()
+                .map(|m| m.as_ref().to_string())
+                .collect(),
+            ports: serial_port_list(),
+            running_model: running.as_ref().map(|r| r.model.as_ref().to_string()),
+            running_port: running.as_ref().map(|r| r.port.clone()),
+        }))
+    }
+
+    async fn start(
+        &self,
+        request: Request<proto::StartRequest>,
+    ) -> Result<Response<proto::StartResponse>, Status> {
+        let request = request.into_inner();
+        let mut running = self.running.lock().unwrap();
+
+        if running.is_some() {
+            return Err(Status::failed_precondition("A sensor is already running"));
+        }
+
+        let model = SensorModel::from_str(&request.model)
+            .map_err(|_| Status::invalid_argument("Unknown model"))?;
+
+        let mut bus = Bus::new(10);
+        let rx = bus.add_rx();
+        let sensor = Sensor::new(&model, &request.port, rx, self.config.clone())
+            .and_then(|sensor| sensor.start(bus).map(|_| sensor))
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        *running = Some(Running {
+            sensor,
+            model,
+            port: request.port,
+        });
+
+        Ok(Response::new(proto::StartResponse {}))
+    }
+
+    async fn stop(
+        &self,
+        _: Request<proto::StopRequest>,
+    ) -> Result<Response<proto::StopResponse>, Status> {
+        match self.running.lock().unwrap().take() {
+            Some(r) => {
+                r.sensor.stop();
+                Ok(Response::new(proto::StopResponse {}))
+            }
+            None => Err(Status::failed_precondition("No sensor is running")),
+        }
+    }
+
+    type StreamSamplesStream =
+        std::pin::Pin<Box<dyn tokio_stream::Stream<Item = Result<proto::Sample, Status>> + Send>>;
+
+    async fn stream_samples(
+        &self,
+        _: Request<proto::StreamSamplesRequest>,
+    ) -> Result<Response<Self::StreamSamplesStream>, Status> {
+        // Slow clients skip samples instead of holding back the others
+        let stream = BroadcastStream::new(self.samples.subscribe())
+            .filter_map(|sample| sample.ok().map(|s| Ok(proto::Sample::from(s))));
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+/// Serve the gRPC API until the process exits, sensors are started with `config`
+pub fn serve(addr: &str, config: Config) -> Result<()> {
+    let addr = addr.parse()?;
+    let running = Arc::new(Mutex::new(None::<Running>));
+    let (samples, _) = broadcast::channel(64);
+
+    // Move samples from the sensor bus to the async world
+    let pump_running = running.clone();
+    let pump_samples = samples.clone();
+    thread::spawn(move || {
+        loop {
+            if let Some(r) = pump_running.lock().unwrap().as_mut() {
+                while let Some(msg) = r.sensor.try_recv() {
+                    if let AppMsg::Sample(sample) = msg {
+                        // No subscriber is not an error
+                        let _ = pump_samples.send(sample);
+                    }
+                }
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    });
+
+    let service = SensorServiceImpl {
+        config,
+        running,
+        samples,
+    };
+
+    tokio::runtime::Runtime::new()?.block_on(
+        Server::builder()
+            .add_service(SensorServiceServer::new(service))
+            .serve(addr),
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,3 @@
+pub mod aqi;
+pub mod plot;
+pub mod tiles;
@@ -0,0 +1,87 @@
+use egui::{Color32, CornerRadius, Frame, Rect, RichText, Sense, Stroke, pos2, vec2};
+
+use crate::aqi::{Aqi, Category};
+
+const SCALE_WIDTH: f32 = 180.0;
+
+fn color(category: Category) -> Color32 {
+    let [r, g, b] = category.rgb();
+    Color32::from_rgb(r, g, b)
+}
+
+/// Bar of the six category colors with a marker at `aqi`, each category equally wide
+fn scale(ui: &mut egui::Ui, aqi: &Aqi) {
+    let (rect, _) = ui.allocate_exact_size(vec2(SCALE_WIDTH, 16.0), Sense::hover());
+    let painter = ui.painter_at(rect);
+    let segment = rect.width() / Category::ALL.len() as f32;
+    let bar = Rect::from_min_size(rect.min, vec2(rect.width(), 8.0));
+
+    for (n, category) in Category::ALL.into_iter().enumerate() {
+        let min = pos2(bar.left() + segment * n as f32, bar.top());
+        painter.rect_filled(
+            Rect::from_min_size(min, vec2(segment, bar.height())),
+            CornerRadius::ZERO,
+            color(category),
+        );
+    }
+
+    let (lo, hi) = aqi.category.range();
+    let within = (aqi.index.clamp(lo, hi) - lo) as f32 / (hi - lo) as f32;
+    let x = bar.left() + segment * (aqi.category as usize as f32 + within);
+    let tip = pos2(x, bar.bottom());
+    painter.add(egui::Shape::convex_polygon(
+        vec![
+            tip,
+            pos2(x - 5.0, rect.bottom()),
+            pos2(x + 5.0, rect.bottom()),
+        ],
+        ui.visuals().text_color(),
+        Stroke::NONE,
+    ));
+}
+
+/// Tile with the air quality index in its category color, for viewers without the numbers
+///
+/// Shows the NowCast index once there is one, with the instantaneous one next to it.
+pub fn aqi_tile(ui: &mut egui::Ui, nowcast: Option<&Aqi>, instant: &Aqi) {
+    let aqi = nowcast.unwrap_or(instant);
+    let color = color(aqi.category);
+    let (title, hover) = if nowcast.is_some() {
+        (
+            "NowCast AQI",
+            "US EPA NowCast index, weighing the hourly means of the last 12 hours",
+        )
+    } else {
+        (
+            "AQI",
+            "US EPA index of the latest readings, the NowCast needs two of the last three hours",
+        )
+    };
+
+    Frame::group(ui.style())
+        .stroke(Stroke::new(2.0, color))
+        .inner_margin(8)
+        .show(ui, |ui| {
+            ui.vertical(|ui| {
+                ui.label(RichText::new(title).size(16.0).color(color));
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(aqi.index.to_string())
+                            .size(40.0)
+                            .strong()
+                            .color(color),
+                    );
+                    ui.vertical(|ui| {
+                        ui.label(RichText::new(aqi.category.label()).size(16.0).strong());
+                        ui.label(format!("from {}", aqi.pollutant.as_ref()));
+                        if nowcast.is_some() {
+                            ui.label(format!("now {}", instant.index));
+                        }
+                    });
+                });
+                scale(ui, aqi);
+            });
+        })
+        .response
+        .on_hover_text(hover);
+}
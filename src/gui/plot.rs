@@ -0,0 +1,648 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Local, TimeDelta, TimeZone};
+use egui::{Align2, Color32, Id, Stroke, Vec2};
+use egui_plot::{
+    GridInput, GridMark, HLine, Legend, Line, LineStyle, MarkerShape, Plot, PlotMemory, PlotPoint,
+    PlotPoints, PlotResponse, Points, Polygon, Text, VLine,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    alert::{Threshold, threshold_for},
+    convert::convert,
+    gap::split_at_gaps,
+    history::History,
+    sensor::{Quality, SensorChannel, SensorType, Unit, channel_key},
+    session::Marker,
+    stats::{Envelope, decimate, moving_average},
+    theme::{self, Rgb},
+};
+
+/// How channels with different magnitudes share the vertical space
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum YScale {
+    /// All channels on one axis
+    Shared,
+    /// One plot per unit, stacked with a linked time axis
+    PerUnit,
+    /// Every channel scaled to 0..1 over the buffered data
+    Normalized,
+}
+
+impl YScale {
+    pub const ALL: [YScale; 3] = [YScale::Shared, YScale::PerUnit, YScale::Normalized];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            YScale::Shared => "Shared axis",
+            YScale::PerUnit => "Axis per unit",
+            YScale::Normalized => "Normalized",
+        }
+    }
+}
+
+/// egui color of a theme color
+pub const fn color32([r, g, b]: Rgb) -> Color32 {
+    Color32::from_rgb(r, g, b)
+}
+
+/// Stable color per sensor type, `n` picks a shade when a type has several channels
+pub fn channel_color(ty: SensorType, n: usize) -> Color32 {
+    color32(theme::channel_rgb(ty, n))
+}
+
+fn channel_id(key: &str) -> Id {
+    Id::new(("channel", key))
+}
+
+/// Combo box choosing one of `channels` by index
+pub fn channel_picker(ui: &mut egui::Ui, id: &str, channels: &[SensorChannel], idx: &mut usize) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(
+            channels
+                .get(*idx)
+                .map(SensorChannel::name)
+                .unwrap_or_default(),
+        )
+        .show_ui(ui, |ui| {
+            for (n, ch) in channels.iter().enumerate() {
+                ui.selectable_value(idx, n, ch.name());
+            }
+        });
+}
+
+/// Plot x coordinate of a timestamp, Unix time in seconds
+pub fn to_x(timestamp: &DateTime<Local>) -> f64 {
+    timestamp.timestamp_millis() as f64 / 1000.0
+}
+
+pub fn from_x(x: f64) -> Option<DateTime<Local>> {
+    Local.timestamp_millis_opt((x * 1000.0) as i64).single()
+}
+
+/// Grid steps that read well on a clock, in seconds
+const TIME_STEPS: [f64; 16] = [
+    1.0, 2.0, 5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 900.0, 1800.0, 3600.0, 10800.0,
+    21600.0, 86400.0,
+];
+
+/// Grid lines on whole seconds, minutes and hours of the local time
+fn time_grid(input: GridInput) -> Vec<GridMark> {
+    let offset = Local::now().offset().local_minus_utc() as f64;
+    let (min, max) = input.bounds;
+
+    // Finest step still a few pixels apart, the next two are drawn thicker
+    let first = TIME_STEPS
+        .iter()
+        .position(|&s| s >= input.base_step_size * 8.0)
+        .unwrap_or(TIME_STEPS.len() - 1);
+    let steps = &TIME_STEPS[first..(first + 3).min(TIME_STEPS.len())];
+    let step = steps[0];
+    if (max - min) / step > 1000.0 {
+        return Vec::new();
+    }
+
+    let mut marks = Vec::new();
+    let mut value = ((min + offset) / step).ceil() * step - offset;
+    while value <= max {
+        let step_size = steps
+            .iter()
+            .rev()
+            .find(|&&s| ((value + offset) / s).fract().abs() < 1e-6)
+            .copied()
+            .unwrap_or(step);
+        marks.push(GridMark { value, step_size });
+        value += step;
+    }
+
+    marks
+}
+
+/// Tick label, with the date once the visible range spans more than a day
+fn format_tick(mark: GridMark, range: &std::ops::RangeInclusive<f64>) -> String {
+    let Some(time) = from_x(mark.value) else {
+        return String::new();
+    };
+
+    if range.end() - range.start() > 24.0 * 3600.0 {
+        time.format("%m-%d %H:%M").to_string()
+    } else {
+        time.format("%H:%M:%S").to_string()
+    }
+}
+
+/// Span of time shown by the plot, following the newest sample
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum TimeWindow {
+    Minute,
+    TenMinutes,
+    Hour,
+    All,
+}
+
+impl TimeWindow {
+    pub const ALL: [TimeWindow; 4] = [
+        TimeWindow::Minute,
+        TimeWindow::TenMinutes,
+        TimeWindow::Hour,
+        TimeWindow::All,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimeWindow::Minute => "1 min",
+            TimeWindow::TenMinutes => "10 min",
+            TimeWindow::Hour => "1 h",
+            TimeWindow::All => "All",
+        }
+    }
+
+    pub fn duration(&self) -> Option<TimeDelta> {
+        match self {
+            TimeWindow::Minute => Some(TimeDelta::minutes(1)),
+            TimeWindow::TenMinutes => Some(TimeDelta::minutes(10)),
+            TimeWindow::Hour => Some(TimeDelta::hours(1)),
+            TimeWindow::All => None,
+        }
+    }
+}
+
+/// Color of the stretches where a value is beyond its threshold
+pub const BREACH_COLOR: Color32 = color32(theme::ALERT);
+
+/// Color of the event markers
+pub const MARKER_COLOR: Color32 = color32(theme::MARKER);
+
+/// Recording of another instrument overlaid on the live plot for comparison
+pub struct Reference {
+    /// File name, shown in the legend
+    pub name: String,
+    pub channels: Vec<SensorChannel>,
+    pub history: History,
+    /// Added to the reference timestamps to line its clock up with the sensor's
+    pub offset: TimeDelta,
+}
+
+impl Reference {
+    /// Points of the reference channel of type `ty` between `from` and `to`, converted to `unit`,
+    /// from the one already in `unit` if the reference has one
+    fn points(
+        &self,
+        ty: SensorType,
+        unit: Unit,
+        from: Option<DateTime<Local>>,
+        to: Option<DateTime<Local>>,
+    ) -> Option<Vec<(f64, f32)>> {
+        let idx = self
+            .channels
+            .iter()
+            .position(|ch| ch.sensor_type == ty && ch.unit == unit)
+            .or_else(|| {
+                self.channels.iter().position(|ch| {
+                    ch.sensor_type == ty && convert(1.0, ty, ch.unit, unit).is_some()
+                })
+            })?;
+        let from_unit = self.channels[idx].unit;
+
+        let points = self
+            .history
+            .range(from.map(|t| t - self.offset), to.map(|t| t - self.offset))
+            .filter_map(|s| {
+                let value = convert(s.data.get(idx)?.value, ty, from_unit, unit)?;
+                Some((to_x(&(s.timestamp + self.offset)), value))
+            })
+            .collect();
+        Some(points)
+    }
+}
+
+/// How a single plot is drawn
+#[derive(Clone, Copy)]
+pub struct PlotOptions<'a> {
+    pub normalize: bool,
+    pub window: TimeWindow,
+    pub height: Option<f32>,
+    /// Show only samples up to this time and leave zoom and pan to the user
+    pub frozen: Option<DateTime<Local>>,
+    /// Keep the newest samples in view, off once the user zooms or pans
+    pub follow: bool,
+    /// Forget any zoom and pan
+    pub reset: bool,
+    /// Moving average over this many samples, for display only
+    pub smoothing: usize,
+    /// Alarm limits drawn as lines, values beyond them are drawn red
+    pub thresholds: &'a [Threshold],
+    /// Event markers drawn as labeled vertical lines
+    pub markers: &'a [Marker],
+    /// Dashed lines of the reference channels with the same gas as a plotted channel
+    pub reference: Option<&'a Reference>,
+    /// Channels switched off in the legend, by [`channel_key`]
+    pub hidden: &'a BTreeSet<String>,
+}
+
+/// What the user did with a plot
+#[derive(Clone, Debug, Default)]
+pub struct PlotInteraction {
+    /// Zoomed or panned
+    pub moved: bool,
+    /// Channels switched off in the legend after this frame, by [`channel_key`]
+    pub hidden: BTreeSet<String>,
+}
+
+/// Vertical line at the pointer with the time and the values of the channels there
+fn crosshair(
+    plot_ui: &mut egui_plot::PlotUi<'_>,
+    history: &History,
+    channels: &[SensorChannel],
+    selected: &[usize],
+) {
+    let Some(pointer) = plot_ui.pointer_coordinate() else {
+        return;
+    };
+    let Some((time, values)) =
+        from_x(pointer.x).and_then(|t| history.value_at(t).map(|values| (t, values)))
+    else {
+        return;
+    };
+
+    plot_ui.vline(VLine::new("", pointer.x).color(Color32::GRAY).width(1.0));
+    plot_ui.response().clone().on_hover_ui_at_pointer(|ui| {
+        ui.strong(time.format("%Y-%m-%d %H:%M:%S").to_string());
+        for &idx in selected {
+            if let (Some(ch), Some(value)) = (channels.get(idx), values.get(idx)) {
+                ui.label(format!("{}: {value:.2} {}", ch.name(), ch.unit.as_ref()));
+            }
+        }
+    });
+}
+
+/// Line through `points`, or when there are several per pixel, a line through their means in
+/// a shaded band from their minimum to their maximum, broken where samples are missing
+fn draw_channel(
+    plot_ui: &mut egui_plot::PlotUi<'_>,
+    id: Id,
+    name: String,
+    points: &[(f64, f32)],
+    color: Color32,
+) {
+    let visible_x = plot_ui.plot_bounds().range_x();
+    let visible = points.iter().filter(|(x, _)| visible_x.contains(x)).count();
+    let width = plot_ui.transform().frame().width().max(1.0) as usize;
+
+    for run in split_at_gaps(points, |(x, _)| *x) {
+        if visible <= width * 2 {
+            let run: PlotPoints = run.iter().map(|&(x, y)| [x, y as f64]).collect();
+            plot_ui.line(Line::new(name.clone(), run).id(id).color(color));
+        } else {
+            let envelopes = decimate(run, run.len().div_ceil(visible / width));
+            draw_envelopes(plot_ui, id, name.clone(), &envelopes, color);
+        }
+    }
+}
+
+/// Line through the means of `envelopes` in a band from their minimum to their maximum
+fn draw_envelopes(
+    plot_ui: &mut egui_plot::PlotUi<'_>,
+    id: Id,
+    name: String,
+    envelopes: &[Envelope],
+    color: Color32,
+) {
+    // Quads between neighbors, a single polygon along both edges would not be convex
+    for pair in envelopes.windows(2) {
+        let [a, b] = [pair[0], pair[1]];
+        let quad = vec![
+            [a.x, a.min as f64],
+            [b.x, b.min as f64],
+            [b.x, b.max as f64],
+            [a.x, a.max as f64],
+        ];
+        plot_ui.polygon(
+            // Shares the id of the line to hide with it
+            Polygon::new("", quad)
+                .id(id)
+                .fill_color(color.gamma_multiply(0.25))
+                .stroke(Stroke::NONE)
+                .allow_hover(false),
+        );
+    }
+    let means: PlotPoints = envelopes.iter().map(|e| [e.x, e.mean as f64]).collect();
+    plot_ui.line(Line::new(name, means).id(id).color(color));
+}
+
+/// Draw the channels at `selected` into one plot against a time axis
+///
+/// The inner response tells whether the user zoomed or panned the plot and which channels are
+/// switched off in its legend.
+pub fn plot_channels(
+    ui: &mut egui::Ui,
+    id: &str,
+    history: &History,
+    channels: &[SensorChannel],
+    selected: &[usize],
+    options: PlotOptions<'_>,
+) -> PlotResponse<PlotInteraction> {
+    let normalize = options.normalize;
+    let latest = options
+        .frozen
+        .or_else(|| history.latest().map(|s| s.timestamp));
+    let from = latest.zip(options.window.duration()).map(|(t, d)| t - d);
+
+    // The legend keeps what it hides in the plot memory, the channels there follow `hidden`
+    // while other entries such as limits keep their state
+    let plot_id = ui.make_persistent_id(id);
+    let keys = selected
+        .iter()
+        .map(|&idx| channel_key(channels, idx))
+        .collect::<Vec<_>>();
+    let channel_ids = keys.iter().map(|key| channel_id(key)).collect::<Vec<_>>();
+    let memory = PlotMemory::load(ui.ctx(), plot_id);
+    let hidden_items = memory
+        .iter()
+        .flat_map(|m| m.hidden_items.iter().copied())
+        .filter(|id| !channel_ids.contains(id))
+        .chain(
+            keys.iter()
+                .filter(|&key| options.hidden.contains(key))
+                .map(|key| channel_id(key)),
+        )
+        .collect::<Vec<_>>();
+    if let Some(mut memory) = memory {
+        memory.hidden_items = hidden_items.iter().copied().collect();
+        memory.store(ui.ctx(), plot_id);
+    }
+
+    let mut plot = Plot::new(id)
+        .id(plot_id)
+        .legend(Legend::default().hidden_items(hidden_items))
+        .link_axis("sensor_chart", [true, false])
+        .link_cursor("sensor_chart", [true, false])
+        .x_grid_spacer(time_grid)
+        .x_axis_formatter(format_tick)
+        // The crosshair readout replaces the nearest-point label
+        .show_x(false)
+        .show_y(false);
+    if let Some(height) = options.height {
+        plot = plot.height(height);
+    }
+    if options.reset {
+        plot = plot.reset();
+    }
+
+    let mut response = plot.show(ui, |plot_ui| {
+        // Scroll with the data, the values still fit the visible samples
+        if options.follow && options.frozen.is_none() {
+            match (from, latest) {
+                (Some(from), Some(latest)) => {
+                    plot_ui.set_plot_bounds_x(to_x(&from)..=to_x(&latest));
+                    plot_ui.set_auto_bounds([false, true]);
+                }
+                _ => plot_ui.set_auto_bounds(true),
+            }
+        }
+
+        // Highest value shown, marker labels hang from there
+        let mut top = f64::MIN;
+
+        for &idx in selected {
+            let ch = &channels[idx];
+            let values = || {
+                history
+                    .range(from, latest)
+                    .filter_map(|s| s.data.get(idx))
+                    .map(|d| d.value)
+            };
+
+            // Map the channel's own min..max onto 0..1
+            let (offset, span) = if normalize {
+                let min = values().fold(f32::MAX, f32::min);
+                let max = values().fold(f32::MIN, f32::max);
+                (min, (max - min).max(f32::EPSILON))
+            } else {
+                (0.0, 1.0)
+            };
+            top = values().fold(top, |top, v| top.max(((v - offset) / span) as f64));
+
+            let mut points = history
+                .range(from, latest)
+                .filter_map(|s| {
+                    let d = s.data.get(idx)?;
+                    Some((to_x(&s.timestamp), (d.value - offset) / span))
+                })
+                .collect::<Vec<_>>();
+            if options.smoothing > 1 {
+                let values = points.iter().map(|(_, y)| *y).collect::<Vec<_>>();
+                for (point, y) in points
+                    .iter_mut()
+                    .zip(moving_average(&values, options.smoothing))
+                {
+                    point.1 = y;
+                }
+            }
+            let name = format!("{} ({})", ch.name(), ch.unit.as_ref());
+            let n = channels[..idx]
+                .iter()
+                .filter(|c| c.sensor_type == ch.sensor_type)
+                .count();
+            let color = channel_color(ch.sensor_type, n);
+            draw_channel(
+                plot_ui,
+                channel_id(&channel_key(channels, idx)),
+                name,
+                &points,
+                color,
+            );
+
+            // Mark flagged samples, unnamed to stay out of the legend
+            for (quality, shape, marker_color) in [
+                (Quality::WarmUp, MarkerShape::Circle, Color32::GRAY),
+                (Quality::Suspect, MarkerShape::Diamond, BREACH_COLOR),
+                (Quality::OutOfRange, MarkerShape::Cross, BREACH_COLOR),
+                (Quality::Interpolated, MarkerShape::Square, color),
+            ] {
+                let flagged = history
+                    .range(from, latest)
+                    .filter_map(|s| {
+                        let d = s.data.get(idx).filter(|d| d.quality == quality)?;
+                        Some([to_x(&s.timestamp), ((d.value - offset) / span) as f64])
+                    })
+                    .collect::<Vec<_>>();
+                if !flagged.is_empty() {
+                    plot_ui.points(
+                        Points::new("", flagged)
+                            .shape(shape)
+                            .color(marker_color)
+                            .radius(3.0),
+                    );
+                }
+            }
+
+            if let Some(reference) = options.reference
+                && let Some(points) = reference.points(ch.sensor_type, ch.unit, from, latest)
+            {
+                let points: PlotPoints = points
+                    .into_iter()
+                    .map(|(x, v)| [x, ((v - offset) / span) as f64])
+                    .collect();
+                plot_ui.line(
+                    Line::new(format!("{} ({})", ch.name(), reference.name), points)
+                        .color(color)
+                        .style(LineStyle::dashed_dense()),
+                );
+            }
+
+            let Some(threshold) = threshold_for(options.thresholds, ch.sensor_type, ch.unit) else {
+                continue;
+            };
+
+            // Limits have no meaning on the 0..1 scale
+            if !normalize {
+                for limit in [threshold.low, threshold.high].into_iter().flatten() {
+                    plot_ui.hline(
+                        HLine::new(format!("{} limit", ch.name()), limit)
+                            .color(color)
+                            .style(LineStyle::dashed_loose()),
+                    );
+                }
+            }
+
+            // Redraw each run of samples beyond the limits on top, unnamed to stay out of the legend
+            let mut run: Vec<[f64; 2]> = Vec::new();
+            for s in history.range(from, latest) {
+                let Some(d) = s.data.get(idx) else {
+                    continue;
+                };
+                let point = [to_x(&s.timestamp), ((d.value - offset) / span) as f64];
+
+                if threshold.check(d.value).is_some() {
+                    run.push(point);
+                } else if !run.is_empty() {
+                    // End the run where the value comes back
+                    run.push(point);
+                    plot_ui.line(
+                        Line::new("", std::mem::take(&mut run))
+                            .color(BREACH_COLOR)
+                            .width(3.0),
+                    );
+                }
+            }
+            if !run.is_empty() {
+                plot_ui.line(Line::new("", run).color(BREACH_COLOR).width(3.0));
+            }
+        }
+
+        let markers = options
+            .markers
+            .iter()
+            .filter(|m| from.is_none_or(|from| m.timestamp >= from))
+            .filter(|m| latest.is_none_or(|latest| m.timestamp <= latest));
+        for marker in markers {
+            let x = to_x(&marker.timestamp);
+            plot_ui.vline(
+                VLine::new("", x)
+                    .color(MARKER_COLOR)
+                    .style(LineStyle::dotted_dense()),
+            );
+            if top > f64::MIN {
+                plot_ui.text(
+                    Text::new("", PlotPoint::new(x, top), format!(" {}", marker.label))
+                        .color(MARKER_COLOR)
+                        .anchor(Align2::LEFT_TOP),
+                );
+            }
+        }
+
+        crosshair(plot_ui, history, channels, selected);
+
+        let response = plot_ui.response();
+        let moved = response.dragged()
+            || response.hovered()
+                && plot_ui
+                    .ctx()
+                    .input(|i| i.smooth_scroll_delta != Vec2::ZERO || i.zoom_delta() != 1.0);
+        PlotInteraction {
+            moved,
+            hidden: BTreeSet::new(),
+        }
+    });
+
+    if let Some(memory) = PlotMemory::load(ui.ctx(), plot_id) {
+        response.inner.hidden = keys
+            .into_iter()
+            .zip(channel_ids)
+            .filter(|(_, id)| memory.hidden_items.contains(id))
+            .map(|(key, _)| key)
+            .collect();
+    }
+    response
+}
+
+/// Plot all channels of one sensor, split or scaled according to `y_scale`
+pub fn plot_sensor(
+    ui: &mut egui::Ui,
+    id: &str,
+    history: &History,
+    channels: &[SensorChannel],
+    y_scale: YScale,
+    options: PlotOptions<'_>,
+) -> PlotResponse<PlotInteraction> {
+    let all = (0..channels.len()).collect::<Vec<_>>();
+
+    match y_scale {
+        YScale::PerUnit if !channels.is_empty() => {
+            let mut units = Vec::new();
+            for ch in channels {
+                if !units.contains(&ch.unit) {
+                    units.push(ch.unit);
+                }
+            }
+            let height =
+                options.height.unwrap_or_else(|| ui.available_height()) / units.len() as f32;
+
+            let mut responses = units
+                .iter()
+                .enumerate()
+                .map(|(n, unit)| {
+                    let selected = all
+                        .iter()
+                        .copied()
+                        .filter(|&idx| channels[idx].unit == *unit)
+                        .collect::<Vec<_>>();
+                    plot_channels(
+                        ui,
+                        &format!("{id}_{n}"),
+                        history,
+                        channels,
+                        &selected,
+                        PlotOptions {
+                            height: Some(height),
+                            ..options
+                        },
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            // The time axes are linked, any plot tells the visible range
+            let moved = responses.iter().any(|r| r.inner.moved);
+            let hidden = responses
+                .iter()
+                .flat_map(|r| r.inner.hidden.iter().cloned())
+                .collect();
+            let mut response = responses.remove(0);
+            response.inner = PlotInteraction { moved, hidden };
+            response
+        }
+        _ => plot_channels(
+            ui,
+            id,
+            history,
+            channels,
+            &all,
+            PlotOptions {
+                normalize: y_scale == YScale::Normalized,
+                ..options
+            },
+        ),
+    }
+}
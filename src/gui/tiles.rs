@@ -0,0 +1,110 @@
+use chrono::TimeDelta;
+use egui::{Frame, RichText, Sense, Stroke};
+
+use crate::{
+    alert::{Threshold, threshold_for},
+    aqi::{aqi, nowcast_aqi},
+    history::History,
+    sensor::SensorChannel,
+    stats::{RateOfChange, Trend},
+};
+
+use super::aqi::aqi_tile;
+use super::plot::{BREACH_COLOR, channel_color};
+
+/// Window the rate of change is computed over
+const TREND_SPAN: TimeDelta = TimeDelta::minutes(1);
+
+/// Arrow and change per minute of channel `idx` over the last `TREND_SPAN`
+fn trend(history: &History, idx: usize) -> Option<(&'static str, String)> {
+    let latest = history.latest()?.timestamp;
+    let rate = RateOfChange::of(history.range(Some(latest - TREND_SPAN), None), idx)?;
+
+    let arrow = match rate.trend {
+        Trend::Rising => "⬆",
+        Trend::Falling => "⬇",
+        Trend::Steady => "➡",
+    };
+    Some((arrow, format!("{:+.1}/min", rate.per_minute)))
+}
+
+/// Latest sample as a header line and a value line, tab separated for spreadsheets
+fn latest_tsv(channels: &[SensorChannel], history: &History) -> Option<String> {
+    let latest = history.latest()?;
+
+    let header = std::iter::once("Timestamp".to_string())
+        .chain(
+            channels
+                .iter()
+                .map(|ch| format!("{}({})", ch.name(), ch.unit.as_ref())),
+        )
+        .collect::<Vec<_>>();
+    let values = std::iter::once(latest.timestamp.format("%Y-%m-%d %H:%M:%S").to_string())
+        .chain(latest.data.iter().map(|d| d.value.to_string()))
+        .collect::<Vec<_>>();
+
+    Some(format!("{}\n{}", header.join("\t"), values.join("\t")))
+}
+
+/// One large tile per channel with its latest value, readable from across the room
+///
+/// Values beyond their threshold are shown red. Sensors measuring a pollutant with an air
+/// quality index get an AQI tile in front.
+pub fn value_tiles(
+    ui: &mut egui::Ui,
+    channels: &[SensorChannel],
+    history: &History,
+    thresholds: &[Threshold],
+) {
+    ui.horizontal_wrapped(|ui| {
+        if let Some(instant) = history.latest().and_then(|s| aqi(&s.data)) {
+            aqi_tile(ui, nowcast_aqi(history).as_ref(), &instant);
+        }
+
+        for (idx, ch) in channels.iter().enumerate() {
+            let n = channels[..idx]
+                .iter()
+                .filter(|c| c.sensor_type == ch.sensor_type)
+                .count();
+            let color = channel_color(ch.sensor_type, n);
+            let latest = history.latest().and_then(|s| s.data.get(idx));
+            let value = latest
+                .map(|d| format!("{:.1}", d.value))
+                .unwrap_or_else(|| "-".to_string());
+            let breached = latest.is_some_and(|d| {
+                threshold_for(thresholds, ch.sensor_type, ch.unit)
+                    .is_some_and(|t| t.check(d.value).is_some())
+            });
+            let mut value = RichText::new(value).size(40.0).strong();
+            if breached {
+                value = value.color(BREACH_COLOR);
+            }
+
+            let tile = Frame::group(ui.style())
+                .stroke(Stroke::new(2.0, color))
+                .inner_margin(8)
+                .show(ui, |ui| {
+                    ui.vertical(|ui| {
+                        ui.label(RichText::new(ch.name()).size(16.0).color(color));
+                        ui.horizontal(|ui| {
+                            ui.label(value);
+                            ui.label(RichText::new(ch.unit.as_ref()).size(16.0));
+                            if let Some((arrow, rate)) = trend(history, idx) {
+                                ui.label(RichText::new(arrow).size(28.0))
+                                    .on_hover_text("Trend over the last minute");
+                                ui.label(RichText::new(rate).size(14.0).weak());
+                            }
+                        });
+                    });
+                });
+
+            tile.response.interact(Sense::click()).context_menu(|ui| {
+                if ui.button("📋 Copy readings").clicked()
+                    && let Some(text) = latest_tsv(channels, history)
+                {
+                    ui.ctx().copy_text(text);
+                }
+            });
+        }
+    });
+}
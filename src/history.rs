@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Local, TimeDelta};
+
+use crate::sensor::SampleData;
+
+/// Samples kept for a capacity of 0, a day at one sample a second
+pub const DEFAULT_CAPACITY: usize = 24 * 60 * 60;
+
+/// Bounded in-memory buffer of the most recent samples
+pub struct History {
+    capacity: usize,
+    samples: VecDeque<SampleData>,
+}
+
+impl History {
+    /// Keeps the last `capacity` samples, `DEFAULT_CAPACITY` when 0
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: if capacity == 0 {
+                DEFAULT_CAPACITY
+            } else {
+                capacity
+            },
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Append a sample, dropping the oldest one when full
+    pub fn push(&mut self, sample: SampleData) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn latest(&self) -> Option<&SampleData> {
+        self.samples.back()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &SampleData> {
+        self.samples.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut SampleData> {
+        self.samples.iter_mut()
+    }
+
+    /// Samples with `from <= timestamp <= to`, open ended when a bound is `None`
+    pub fn range(
+        &self,
+        from: Option<DateTime<Local>>,
+        to: Option<DateTime<Local>>,
+    ) -> impl Iterator<Item = &SampleData> {
+        self.samples.iter().filter(move |s| {
+            from.is_none_or(|from| s.timestamp >= from) && to.is_none_or(|to| s.timestamp <= to)
+        })
+    }
+
+    /// Samples per second over the newest `span`, `None` until it holds two samples
+    pub fn rate(&self, span: TimeDelta) -> Option<f64> {
+        let last = self.latest()?.timestamp;
+        let mut window = self.range(Some(last - span), None);
+        let first = window.next()?.timestamp;
+        let intervals = window.count();
+
+        let secs = (last - first).num_milliseconds() as f64 / 1000.0;
+        (intervals > 0 && secs > 0.0).then(|| intervals as f64 / secs)
+    }
+
+    /// Value of every channel at `time`, linear between the samples around it
+    ///
+    /// `None` outside the buffered time span.
+    pub fn value_at(&self, time: DateTime<Local>) -> Option<Vec<f32>> {
+        // Samples arrive in time order
+        let after = self.samples.partition_point(|s| s.timestamp < time);
+        let next = self.samples.get(after)?;
+        if next.timestamp == time || after == 0 {
+            return (next.timestamp == time).then(|| next.data.iter().map(|d| d.value).collect());
+        }
+        let prev = &self.samples[after - 1];
+
+        let span = (next.timestamp - prev.timestamp).num_milliseconds() as f32;
+        let t = (time - prev.timestamp).num_milliseconds() as f32 / span.max(1.0);
+
+        Some(
+            prev.data
+                .iter()
+                .zip(&next.data)
+                .map(|(a, b)| a.value + (b.value - a.value) * t)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::{SensorType, Unit};
+
+    #[test]
+    fn interpolate_between_samples() {
+        let start = Local::now();
+        let mut history = History::new(10);
+        for (n, value) in [10.0, 20.0].into_iter().enumerate() {
+            history.push(SampleData::single(
+                start,
+                n as i64 * 2,
+                SensorType::CO,
+                value,
+                Unit::PPM,
+            ));
+        }
+
+        let at = |ms| history.value_at(start + chrono::TimeDelta::milliseconds(ms));
+        assert_eq!(at(500), Some(vec![12.5]));
+        assert_eq!(at(2000), Some(vec![20.0]));
+        assert_eq!(at(-1), None);
+        assert_eq!(at(2001), None);
+    }
+
+    #[test]
+    fn sample_rate() {
+        let start = Local::now();
+        let mut history = History::new(10);
+        assert_eq!(history.rate(TimeDelta::minutes(1)), None);
+
+        for n in 0..3 {
+            history.push(SampleData::single(
+                start,
+                n * 2,
+                SensorType::CO,
+                0.0,
+                Unit::PPM,
+            ));
+        }
+        assert_eq!(history.rate(TimeDelta::minutes(1)), Some(0.5));
+        // Only the newest 2 s, one interval
+        assert_eq!(history.rate(TimeDelta::seconds(2)), Some(0.5));
+    }
+
+    #[test]
+    fn zero_capacity_is_bounded() {
+        let mut history = History::new(0);
+        assert_eq!(history.capacity, DEFAULT_CAPACITY);
+
+        let start = Local::now();
+        for n in 0..DEFAULT_CAPACITY as i64 + 2 {
+            history.push(SampleData::single(start, n, SensorType::CO, 0.0, Unit::PPM));
+        }
+        assert_eq!(history.len(), DEFAULT_CAPACITY);
+    }
+}
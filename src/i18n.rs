@@ -0,0 +1,175 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        OnceLock,
+        atomic::{AtomicU8, Ordering},
+    },
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Language of the user interface
+///
+/// Translations live in gettext catalogs under `lang/<code>/LC_MESSAGES/`, shared by the egui
+/// and slint frontends. Untranslated strings show in English.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum Language {
+    #[default]
+    English,
+    Chinese,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Chinese];
+
+    /// Name of the language in itself
+    pub fn label(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Chinese => "简体中文",
+        }
+    }
+
+    /// Name of the catalog directory
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Chinese => "zh_CN",
+        }
+    }
+
+    fn catalog(&self) -> Option<&'static HashMap<String, String>> {
+        static CHINESE: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+        match self {
+            Language::English => None,
+            Language::Chinese => Some(CHINESE.get_or_init(|| {
+                parse_po(include_str!("../lang/zh_CN/LC_MESSAGES/envsensor-demo.po"))
+            })),
+        }
+    }
+}
+
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_language(language: Language) {
+    CURRENT.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    Language::ALL[CURRENT.load(Ordering::Relaxed) as usize]
+}
+
+/// `text` in the current language
+pub fn tr(text: &'static str) -> &'static str {
+    language()
+        .catalog()
+        .and_then(|catalog| catalog.get(text))
+        .map_or(text, String::as_str)
+}
+
+fn unquote(line: &str) -> Option<String> {
+    let inner = line.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut text = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some(c) => text.push(c),
+            None => {}
+        }
+    }
+
+    Some(text)
+}
+
+/// Message ids and their translations in a `.po` catalog, the message context is ignored
+fn parse_po(text: &str) -> HashMap<String, String> {
+    enum Field {
+        Other,
+        Id,
+        Str,
+    }
+
+    let mut catalog = HashMap::new();
+    let (mut id, mut translated) = (String::new(), String::new());
+    let mut field = Field::Other;
+
+    let mut finish = |id: &mut String, translated: &mut String| {
+        let (id, translated) = (std::mem::take(id), std::mem::take(translated));
+        if !id.is_empty() && !translated.is_empty() {
+            catalog.insert(id, translated);
+        }
+    };
+
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with("msgctxt ") {
+            field = Field::Other;
+        } else if let Some(rest) = line.strip_prefix("msgid ") {
+            finish(&mut id, &mut translated);
+            field = Field::Id;
+            id = unquote(rest).unwrap_or_default();
+        } else if let Some(rest) = line.strip_prefix("msgstr ") {
+            field = Field::Str;
+            translated = unquote(rest).unwrap_or_default();
+        } else if let Some(more) = unquote(line) {
+            match field {
+                Field::Id => id.push_str(&more),
+                Field::Str => translated.push_str(&more),
+                Field::Other => {}
+            }
+        }
+    }
+    finish(&mut id, &mut translated);
+
+    catalog
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_catalog() {
+        let catalog = parse_po(
+            r#"
+# Header
+msgid ""
+msgstr "Content-Type: text/plain; charset=UTF-8\n"
+
+msgctxt "AppWindow"
+msgid "Start"
+msgstr "开始"
+
+msgid "Say \"hi\""
+msgstr ""
+"说"
+"\"你好\""
+
+msgid "Untranslated"
+msgstr ""
+"#,
+        );
+
+        assert_eq!(catalog.get("Start").map(String::as_str), Some("开始"));
+        assert_eq!(
+            catalog.get("Say \"hi\"").map(String::as_str),
+            Some("说\"你好\"")
+        );
+        assert!(!catalog.contains_key("Untranslated"));
+        assert!(!catalog.contains_key(""));
+
+        // The shipped catalog is bundled and parses
+        assert!(Language::Chinese.catalog().unwrap().len() > 10);
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::{Duration, Instant};
+
+/// Dims an always-on display after a while without input, against OLED burn-in
+///
+/// Input and alerts wake it again.
+pub struct IdleDim {
+    /// `None` never dims
+    timeout: Option<Duration>,
+    last_activity: Instant,
+}
+
+impl IdleDim {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            timeout,
+            last_activity: Instant::now(),
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Input or an alert, the display is bright for another timeout
+    pub fn wake(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub fn is_dimmed(&self) -> bool {
+        self.dimmed_at(Instant::now())
+    }
+
+    fn dimmed_at(&self, now: Instant) -> bool {
+        self.timeout
+            .is_some_and(|timeout| now.duration_since(self.last_activity) >= timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dims_after_timeout() {
+        let minute = Duration::from_secs(60);
+        let mut idle = IdleDim::new(Some(minute));
+        let start = idle.last_activity;
+        assert!(!idle.dimmed_at(start + minute / 2));
+        assert!(idle.dimmed_at(start + minute));
+
+        idle.wake();
+        assert!(!idle.dimmed_at(idle.last_activity + minute / 2));
+
+        idle.set_timeout(None);
+        assert!(!idle.dimmed_at(start + minute * 60));
+    }
+}
@@ -1,9 +1,99 @@
+pub mod aggregate;
+pub mod alert;
+pub mod anomaly;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod api;
+pub mod aqi;
+pub mod calibration;
+pub mod chart;
+pub mod config;
+pub mod controller;
+pub mod convert;
+#[cfg(all(feature = "dbus", target_os = "linux"))]
+pub mod dbus;
+pub mod drift;
+pub mod export;
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzz;
+pub mod gap;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod gui;
+pub mod history;
+pub mod i18n;
+pub mod idle;
+pub mod logger;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod metrics;
+pub mod modbus_tcp;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mqtt;
+#[cfg(feature = "serial")]
 mod nextpm;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod outbox;
+pub mod process;
+pub mod remote;
+pub mod report;
+#[cfg(feature = "serial")]
 mod rydason;
 pub mod sensor;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sensor_community;
+pub mod session;
+pub mod settings;
+pub mod simulator;
+pub mod station;
+pub mod stats;
+#[cfg(feature = "serial")]
 mod tb600b_c;
+pub mod theme;
+pub mod traffic;
+pub mod transport;
+pub mod wal;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod webhook;
+pub mod ws;
 
+use std::{
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+/// Serial ports of this machine, none when built without the `serial` feature
 pub fn serial_port_list() -> Vec<String> {
-    let ports = serialport::available_ports().unwrap_or_default();
-    ports.into_iter().map(|p| p.port_name).collect()
+    #[cfg(feature = "serial")]
+    {
+        let ports = serialport::available_ports().unwrap_or_default();
+        ports.into_iter().map(|p| p.port_name).collect()
+    }
+
+    #[cfg(not(feature = "serial"))]
+    Vec::new()
+}
+
+/// Re-enumerate the serial ports every `interval`, the new list is sent whenever it changes
+///
+/// The thread ends once the receiver is dropped.
+pub fn watch_serial_ports(interval: Duration) -> Receiver<Vec<String>> {
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut known = serial_port_list();
+        loop {
+            thread::sleep(interval);
+
+            let ports = serial_port_list();
+            if ports != known {
+                known = ports.clone();
+                if tx.send(ports).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    rx
 }
@@ -0,0 +1,868 @@
+use std::fmt::Write as _;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::{
+    fs::{File, TryLockError},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{Result, bail};
+use chrono::{
+    DateTime, Local, NaiveDateTime, SecondsFormat, Utc,
+    format::{Item, StrftimeItems},
+};
+use flate2::{Compression, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Site;
+use crate::sensor::{SampleData, SensorChannel, SensorModel};
+use crate::session::SessionMeta;
+use crate::wal::{Wal, recover};
+
+/// When to start a new log file
+#[derive(Clone, Debug, Default)]
+pub struct Rotation {
+    /// Rotate once the current file would grow beyond this size
+    pub max_bytes: Option<u64>,
+    /// Rotate once the current file has been open for this long
+    pub max_age: Option<Duration>,
+}
+
+impl Rotation {
+    pub fn daily() -> Self {
+        Self {
+            max_bytes: None,
+            max_age: Some(Duration::from_secs(24 * 60 * 60)),
+        }
+    }
+
+    pub fn size(max_bytes: u64) -> Self {
+        Self {
+            max_bytes: Some(max_bytes),
+            max_age: None,
+        }
+    }
+}
+
+/// How sample timestamps are rendered by a sink
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum TimestampFormat {
+    /// Local time as "%m/%d/%Y %H:%M:%S", the historical CSV format
+    #[default]
+    Legacy,
+    /// RFC 3339 local time with timezone offset, e.g. "2025-11-25T14:03:07+01:00"
+    Rfc3339,
+    /// RFC 3339 in UTC, e.g. "2025-11-25T13:03:07Z"
+    Utc,
+    /// Any chrono strftime pattern, rendered in local time
+    Custom(String),
+}
+
+impl TimestampFormat {
+    pub fn format(&self, ts: &DateTime<Local>) -> String {
+        match self {
+            TimestampFormat::Legacy => ts.format("%m/%d/%Y %H:%M:%S").to_string(),
+            TimestampFormat::Rfc3339 => ts.to_rfc3339_opts(SecondsFormat::Secs, false),
+            TimestampFormat::Utc => ts
+                .with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            TimestampFormat::Custom(fmt) => {
+                // An invalid pattern makes Display fail, which to_string() turns into a panic
+                let mut text = String::new();
+                match write!(text, "{}", ts.format(fmt)) {
+                    Ok(()) => text,
+                    Err(_) => TimestampFormat::Rfc3339.format(ts),
+                }
+            }
+        }
+    }
+
+    /// Fails for a custom pattern chrono can't render
+    pub fn validate(&self) -> Result<()> {
+        if let TimestampFormat::Custom(fmt) = self
+            && StrftimeItems::new(fmt).any(|item| item == Item::Error)
+        {
+            bail!("Invalid timestamp pattern \"{fmt}\"");
+        }
+
+        Ok(())
+    }
+}
+
+/// How often buffered rows are flushed to disk, whichever limit is hit first
+#[derive(Clone, Debug)]
+pub struct FlushPolicy {
+    /// Flush after this many samples
+    pub samples: usize,
+    /// Flush when the oldest unflushed sample is this old
+    pub interval: Duration,
+}
+
+impl Default for FlushPolicy {
+    fn default() -> Self {
+        Self {
+            samples: 10,
+            interval: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Cleanup of old log files in the log directory
+#[derive(Clone, Debug, Default)]
+pub struct Retention {
+    /// Remove files last modified longer ago than this
+    pub max_age: Option<Duration>,
+    /// Remove the oldest files while all logs together are larger than this
+    pub max_total_bytes: Option<u64>,
+    /// Move expired files here instead of deleting them
+    pub archive_dir: Option<PathBuf>,
+}
+
+/// Stem of the log `file_name` belongs to, e.g. "x" for "x.csv.gz", "x.meta.json" or
+/// "x_summary.csv", with the part of the sidecar's own log in `log`
+fn log_file_stems(file_name: &str) -> Option<(&str, &str)> {
+    let log = [".csv.gz", ".csv", ".meta.json"]
+        .iter()
+        .find_map(|ext| file_name.strip_suffix(ext))?;
+    let stem = ["_summary", "_events"]
+        .iter()
+        .find_map(|sidecar| log.strip_suffix(sidecar))
+        .unwrap_or(log);
+
+    Some((stem, log))
+}
+fn expire(path: &Path, retention: &Retention) -> Result<()> {
+    match &retention.archive_dir {
+        Some(archive) => {
+            std::fs::create_dir_all(archive)?;
+            let target = archive.join(path.file_name().unwrap_or_default());
+            // rename fails across file systems, fall back to copy + delete
+            if std::fs::rename(path, &target).is_err() {
+                std::fs::copy(path, &target)?;
+                std::fs::remove_file(path)?;
+            }
+        }
+        None => std::fs::remove_file(path)?,
+    }
+
+    Ok(())
+}
+
+/// Delete or archive logs in `dir` according to `retention`
+///
+/// Only the files a `CsvLogger` writes are considered, named after `name` or the default
+/// "<start time>_<model>", and never the ones a running session still writes to.
+pub fn apply_retention(dir: &Path, retention: &Retention, name: Option<&str>) -> Result<()> {
+    if retention.max_age.is_none() && retention.max_total_bytes.is_none() {
+        return Ok(());
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        let file_name = entry.file_name();
+        let Some((stem, log)) = file_name.to_str().and_then(log_file_stems) else {
+            continue;
+        };
+        if meta.is_file()
+            && is_log_stem(stem, name, None)
+            && !is_held(&dir.join(format!("{log}.csv")))
+        {
+            files.push((entry.path(), meta.modified()?, meta.len()));
+        }
+    }
+    // Oldest first
+    files.sort_by_key(|(_, modified, _)| *modified);
+
+    let now = SystemTime::now();
+    let mut total: u64 = files.iter().map(|(_, _, len)| len).sum();
+
+    for (path, modified, len) in files {
+        let too_old = retention
+            .max_age
+            .is_some_and(|age| now.duration_since(modified).unwrap_or_default() > age);
+        let too_big = retention.max_total_bytes.is_some_and(|max| total > max);
+
+        if too_old || too_big {
+            expire(&path, retention)?;
+            total -= len;
+        }
+    }
+
+    Ok(())
+}
+
+/// Compress `path` to `path.gz` and remove the original
+pub fn compress(path: &Path) -> Result<PathBuf> {
+    let mut gz_name = path.as_os_str().to_owned();
+    gz_name.push(".gz");
+    let gz_path = PathBuf::from(gz_name);
+
+    let mut input = File::open(path)?;
+    let mut encoder = GzEncoder::new(File::create(&gz_path)?, Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+
+    std::fs::remove_file(path)?;
+
+    Ok(gz_path)
+}
+
+/// The last `lines` lines of a text file, without reading all of it
+pub fn tail(path: &Path, lines: usize) -> Result<Vec<String>> {
+    // Plenty for a CSV row
+    const LINE_BYTES: u64 = 256;
+
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(LINE_BYTES * (lines as u64 + 1));
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let text = String::from_utf8_lossy(&bytes);
+
+    let mut all = text.lines().collect::<Vec<_>>();
+    // The first line is likely cut off
+    if start > 0 && !all.is_empty() {
+        all.remove(0);
+    }
+
+    Ok(all[all.len().saturating_sub(lines)..]
+        .iter()
+        .map(|l| l.to_string())
+        .collect())
+}
+
+/// "x.csv" -> "x.csv.lock"
+fn lock_path(log: &Path) -> PathBuf {
+    let mut name = log.as_os_str().to_owned();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+/// Advisory lock marking a file as written by a running session
+///
+/// The OS releases it when the process dies, so a lock file left behind by a crash is not
+/// held. Sessions sharing a log directory use it to keep their hands off each other's files.
+pub struct LogLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl LogLock {
+    pub fn acquire(log: &Path) -> Result<Self> {
+        let path = lock_path(log);
+        let file = File::create(&path)?;
+        file.try_lock().map_err(std::io::Error::from)?;
+
+        Ok(Self { file, path })
+    }
+}
+
+impl Drop for LogLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Whether a running session, in this or another process, holds the `LogLock` of `log`
+pub fn is_held(log: &Path) -> bool {
+    File::open(lock_path(log))
+        .is_ok_and(|file| matches!(file.try_lock_shared(), Err(TryLockError::WouldBlock)))
+}
+
+/// Whether `stem` is the name of a log written with `name`, or without a name by a `model`
+/// sensor; any model when `model` is unset. Rotated parts ("<stem>_1") count as well.
+fn is_log_stem(stem: &str, name: Option<&str>, model: Option<SensorModel>) -> bool {
+    let matches = |stem: &str| {
+        if name == Some(stem) {
+            return true;
+        }
+        // "<start time>_<model>", the start time is "%Y-%m-%d-%H-%M-%S"
+        let Some((time, rest)) = stem.split_at_checked(19) else {
+            return false;
+        };
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%d-%H-%M-%S").is_ok()
+            && rest.strip_prefix('_').is_some_and(|m| match model {
+                Some(model) => m == model.as_ref(),
+                None => m.parse::<SensorModel>().is_ok(),
+            })
+    };
+
+    matches(stem)
+        || stem.rsplit_once('_').is_some_and(|(stem, part)| {
+            !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()) && matches(stem)
+        })
+}
+
+fn compress_in_background(path: PathBuf, notices: Sender<String>) {
+    std::thread::spawn(move || {
+        if let Err(e) = compress(&path) {
+            let _ = notices.send(format!("Failed to compress {}: {e}", path.display()));
+        }
+    });
+}
+
+/// Log output settings
+#[derive(Clone, Debug)]
+pub struct LogConfig {
+    /// Write the samples to CSV at all
+    pub enabled: bool,
+    /// Directory the log files are created in
+    pub dir: PathBuf,
+    /// Base name of the files, "<start time>_<model>" when unset
+    pub name: Option<String>,
+    pub rotation: Rotation,
+    pub flush: FlushPolicy,
+    pub timestamp: TimestampFormat,
+    pub retention: Retention,
+    /// Gzip files once they are rotated out or the session ends
+    pub compress: bool,
+    /// Journal unflushed rows so a crash loses nothing, replayed on the next start. Every row
+    /// is synced to the journal, so this costs one disk write per sample.
+    pub wal: bool,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: PathBuf::from("."),
+            name: None,
+            rotation: Rotation::default(),
+            flush: FlushPolicy::default(),
+            timestamp: TimestampFormat::default(),
+            retention: Retention::default(),
+            compress: false,
+            wal: false,
+        }
+    }
+}
+
+/// CSV writer which rotates to a new file (with a fresh header) based on `Rotation`
+pub struct CsvLogger {
+    config: LogConfig,
+    model: SensorModel,
+    header: String,
+    site: Option<String>,
+    file: BufWriter<File>,
+    path: PathBuf,
+    written: u64,
+    opened: Instant,
+    pending: usize,
+    last_flush: Instant,
+    meta: Option<SessionMeta>,
+    wal: Option<Wal>,
+    /// Marks the current file as in use
+    _lock: LogLock,
+    /// Marker labels waiting for the next row
+    events: Vec<String>,
+    notices_tx: Sender<String>,
+    notices: Receiver<String>,
+}
+
+/// Prefix of the column holding a channel's unprocessed value
+pub const RAW_PREFIX: &str = "Raw ";
+
+/// Last column of a log, the labels of the markers set since the previous row
+pub const EVENT_COLUMN: &str = "Event";
+
+/// Column before `EVENT_COLUMN`, the channels whose value isn't `Quality::Valid`
+pub const QUALITY_COLUMN: &str = "Quality";
+
+/// `QUALITY_COLUMN` cell of `sample`, e.g. "CO=WarmUp;NO2=WarmUp", empty when all are valid
+pub fn quality_cell(sample: &SampleData) -> String {
+    sample
+        .data
+        .iter()
+        .filter(|d| !d.quality.is_valid())
+        .map(|d| format!("{}={}", d.channel().name(), d.quality.as_ref()))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+pub fn csv_header(channels: &[SensorChannel], site: Option<&Site>) -> String {
+    let mut header = format!(
+        "{},{}",
+        "Timestamp",
+        channels
+            .iter()
+            .map(|ch| {
+                let column = format!("{}({})", ch.name(), ch.unit.as_ref());
+                match ch.raw_unit {
+                    // Raw value right next to the processed one
+                    Some(raw) => format!("{column},{RAW_PREFIX}{}({})", ch.name(), raw.as_ref()),
+                    None => column,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+
+    if site.is_some() {
+        header = format!("{header},{}", Site::CSV_COLUMNS.join(","));
+    }
+
+    header
+}
+
+pub fn csv_row(sample: &SampleData, timestamp: &TimestampFormat) -> String {
+    format!(
+        "{},{}",
+        timestamp.format(&sample.timestamp),
+        sample
+            .data
+            .iter()
+            .map(|d| match d.raw {
+                Some(raw) => format!("{},{raw}", d.value),
+                None => d.value.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    )
+}
+
+/// `text` as one CSV field, quoted when it holds a separator or a quote
+pub fn csv_field(text: &str) -> String {
+    if text.contains([',', '"']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Fields of a CSV row, reverses `csv_field`
+pub fn split_csv_row(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+}
+
+fn new_file_path(dir: &Path, name: Option<&str>, model: SensorModel) -> PathBuf {
+    let stem = match name {
+        Some(name) => name.to_string(),
+        None => format!(
+            "{}_{}",
+            chrono::Local::now().format("%Y-%m-%d-%H-%M-%S"),
+            model.as_ref()
+        ),
+    };
+
+    let mut path = dir.join(format!("{stem}.csv"));
+    let mut n = 1;
+    // Rotation may happen within the same second, never overwrite a previous part
+    while path.exists() {
+        path = dir.join(format!("{stem}_{n}.csv"));
+        n += 1;
+    }
+
+    path
+}
+
+impl CsvLogger {
+    pub fn new(
+        model: SensorModel,
+        channels: &[SensorChannel],
+        config: LogConfig,
+        site: Option<&Site>,
+    ) -> Result<Self> {
+        let header = format!(
+            "{},{QUALITY_COLUMN},{EVENT_COLUMN}",
+            csv_header(channels, site)
+        );
+        let (file, path, written) = Self::open(&config, model, &header)?;
+        let lock = LogLock::acquire(&path)?;
+
+        let (notices_tx, notices) = mpsc::channel();
+        let wal = if config.wal {
+            // Only journals of crashed sessions like this one, others may still be running
+            let name = config.name.as_deref();
+            let recovery = recover(&config.dir, |log| {
+                log.file_stem()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|stem| is_log_stem(stem, name, Some(model)))
+                    && !is_held(log)
+            })?;
+            if recovery.rows > 0 {
+                let _ = notices_tx.send(format!(
+                    "Recovered {} rows of an interrupted session",
+                    recovery.rows
+                ));
+            }
+            for orphan in recovery.orphans {
+                let _ = notices_tx.send(format!(
+                    "No log for {}, nothing to recover",
+                    orphan.display()
+                ));
+            }
+            Some(Wal::open(&path)?)
+        } else {
+            None
+        };
+
+        apply_retention(&config.dir, &config.retention, config.name.as_deref())?;
+
+        Ok(CsvLogger {
+            config,
+            model,
+            header,
+            site: site.map(Site::csv_values),
+            file,
+            path,
+            written,
+            opened: Instant::now(),
+            pending: 0,
+            last_flush: Instant::now(),
+            meta: None,
+            wal,
+            _lock: lock,
+            events: Vec::new(),
+            notices_tx,
+            notices,
+        })
+    }
+
+    /// Problems and recoveries that don't stop the log, reported since the last call
+    pub fn notices(&self) -> Vec<String> {
+        self.notices.try_iter().collect()
+    }
+
+    /// Write `meta` as a sidecar next to this and every later rotated file
+    pub fn with_meta(mut self, meta: SessionMeta) -> Result<Self> {
+        meta.write(&self.path)?;
+        self.meta = Some(meta);
+
+        Ok(self)
+    }
+
+    fn open(
+        config: &LogConfig,
+        model: SensorModel,
+        header: &str,
+    ) -> Result<(BufWriter<File>, PathBuf, u64)> {
+        std::fs::create_dir_all(&config.dir)?;
+
+        let path = new_file_path(&config.dir, config.name.as_deref(), model);
+        let mut file = BufWriter::new(File::create(&path)?);
+        // Write CSV header
+        writeln!(file, "{header}")?;
+
+        Ok((file, path, header.len() as u64 + 1))
+    }
+
+    /// Path of the file currently written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn should_rotate(&self, next_len: u64) -> bool {
+        // Never rotate an empty file, otherwise a single oversized row would loop forever
+        if self.written <= self.header.len() as u64 + 1 {
+            return false;
+        }
+
+        let rotation = &self.config.rotation;
+
+        rotation
+            .max_bytes
+            .is_some_and(|max| self.written + next_len > max)
+            || rotation
+                .max_age
+                .is_some_and(|age| self.opened.elapsed() >= age)
+    }
+
+    /// Write all buffered rows to disk
+    pub fn flush(&mut self) -> Result<()> {
+        self.file.flush()?;
+        if let Some(wal) = &mut self.wal {
+            // The journal may only be emptied once the rows are really on disk
+            self.file.get_ref().sync_data()?;
+            wal.clear()?;
+        }
+        self.pending = 0;
+        self.last_flush = Instant::now();
+
+        Ok(())
+    }
+
+    /// Flush if the flush policy says so, call periodically even without new samples
+    pub fn flush_if_due(&mut self) -> Result<()> {
+        let policy = &self.config.flush;
+
+        if self.pending > 0
+            && (self.pending >= policy.samples || self.last_flush.elapsed() >= policy.interval)
+        {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        self.flush()?;
+
+        let (file, path, written) = Self::open(&self.config, self.model, &self.header)?;
+        self._lock = LogLock::acquire(&path)?;
+        self.file = file;
+        let previous = std::mem::replace(&mut self.path, path);
+        if let Some(wal) = self.wal.take() {
+            wal.remove()?;
+            self.wal = Some(Wal::open(&self.path)?);
+        }
+        if self.config.compress {
+            compress_in_background(previous, self.notices_tx.clone());
+        }
+        if let Some(meta) = &self.meta {
+            meta.write(&self.path)?;
+        }
+        self.written = written;
+        self.opened = Instant::now();
+
+        apply_retention(
+            &self.config.dir,
+            &self.config.retention,
+            self.config.name.as_deref(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Flush and, if configured, compress the current file; call when the session ends
+    pub fn finish(mut self) -> Result<()> {
+        self.flush()?;
+
+        if let Some(wal) = self.wal.take() {
+            wal.remove()?;
+        }
+
+        if self.config.compress {
+            // Close the file before compressing it
+            let path = self.path.clone();
+            drop(self);
+            compress(&path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Put a marker label in the event column of the next row
+    pub fn mark(&mut self, label: &str) {
+        // One row per line, several labels in one row are separated by ';'
+        self.events.push(label.replace([';', '\r', '\n'], " "));
+    }
+
+    pub fn write_sample(&mut self, sample: &SampleData) -> Result<()> {
+        let mut row = csv_row(sample, &self.config.timestamp);
+        if let Some(site) = &self.site {
+            row = format!("{row},{site}");
+        }
+        row = format!(
+            "{row},{},{}",
+            quality_cell(sample),
+            csv_field(&self.events.join(";"))
+        );
+        self.events.clear();
+        let len = row.len() as u64 + 1;
+
+        // Rotate before writing so the sample always lands in exactly one file
+        if self.should_rotate(len) {
+            self.rotate()?;
+        }
+
+        if let Some(wal) = &mut self.wal {
+            wal.append(&row)?;
+        }
+        writeln!(self.file, "{row}")?;
+        self.written += len;
+        self.pending += 1;
+
+        self.flush_if_due()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::{SensorType, Unit};
+
+    #[test]
+    fn rotate_by_size() {
+        let dir = std::env::temp_dir().join(format!("envsensor-rotate-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let channels = [SensorChannel::new(SensorType::CO, Unit::PPM)];
+        let config = LogConfig {
+            dir: dir.clone(),
+            rotation: Rotation::size(64),
+            ..Default::default()
+        };
+        let mut logger = CsvLogger::new(SensorModel::RYDASON, &channels, config, None).unwrap();
+
+        for i in 0..10 {
+            logger
+                .write_sample(&SampleData::single(
+                    chrono::Local::now(),
+                    0,
+                    SensorType::CO,
+                    i as f32,
+                    Unit::PPM,
+                ))
+                .unwrap();
+        }
+        logger.finish().unwrap();
+
+        let mut rows = 0;
+        let files = std::fs::read_dir(&dir).unwrap().collect::<Vec<_>>();
+        assert!(files.len() > 1);
+
+        for f in files {
+            let content = std::fs::read_to_string(f.unwrap().path()).unwrap();
+            let mut lines = content.lines();
+            assert_eq!(lines.next(), Some("Timestamp,CO(ppm),Quality,Event"));
+            rows += lines.count();
+        }
+        assert_eq!(rows, 10);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tail_of_file() {
+        let path = std::env::temp_dir().join(format!("envsensor-tail-{}.csv", std::process::id()));
+        let row = |n| format!("{n},{}", "x".repeat(100));
+        let text = (0..100).map(|n| row(n) + "\n").collect::<String>();
+        std::fs::write(&path, text).unwrap();
+
+        assert_eq!(tail(&path, 2).unwrap(), vec![row(98), row(99)]);
+        assert_eq!(tail(&path, 200).unwrap().len(), 100);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn retention_by_total_size() {
+        let dir = std::env::temp_dir().join(format!("envsensor-retention-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let log = |n| format!("2025-11-25-14-03-0{n}_RYDASON");
+        let names = [
+            format!("{}.csv.gz", log(1)),
+            format!("{}.meta.json", log(1)),
+            format!("{}_summary.csv", log(2)),
+            format!("{}.csv", log(3)),
+            format!("{}.csv", log(4)),
+            "notes.csv".to_string(),
+            "notes.txt".to_string(),
+        ];
+        let now = SystemTime::now();
+        for (i, name) in names.iter().enumerate() {
+            let file = File::create(dir.join(name)).unwrap();
+            file.set_len(100).unwrap();
+            file.set_modified(now - Duration::from_secs(3600 * (10 - i as u64)))
+                .unwrap();
+        }
+        // A running session writes the second one
+        let _lock = LogLock::acquire(&dir.join(format!("{}.csv", log(3)))).unwrap();
+
+        let retention = Retention {
+            max_total_bytes: Some(150),
+            ..Default::default()
+        };
+        apply_retention(&dir, &retention, None).unwrap();
+
+        assert!(!dir.join(&names[0]).exists());
+        assert!(!dir.join(&names[1]).exists());
+        assert!(!dir.join(&names[2]).exists());
+        assert!(dir.join(&names[3]).exists());
+        assert!(dir.join(&names[4]).exists());
+        assert!(dir.join("notes.csv").exists());
+        assert!(dir.join("notes.txt").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn quoted_fields() {
+        let label = "door \"A\", opened";
+        let row = format!("t,1,{}", csv_field(label));
+        assert_eq!(row, "t,1,\"door \"\"A\"\", opened\"");
+        assert_eq!(split_csv_row(&row), vec!["t", "1", label]);
+        assert_eq!(split_csv_row("a,,b"), vec!["a", "", "b"]);
+    }
+
+    #[test]
+    fn log_stems() {
+        let stem = "2025-11-25-14-03-07_EC_TB600BC";
+        assert!(is_log_stem(stem, None, None));
+        assert!(is_log_stem(stem, None, Some(SensorModel::EC_TB600BC)));
+        assert!(!is_log_stem(stem, None, Some(SensorModel::RYDASON)));
+        assert!(is_log_stem(&format!("{stem}_2"), None, None));
+        assert!(!is_log_stem("2025-11-25_EC_TB600BC", None, None));
+        assert!(!is_log_stem("notes", None, None));
+        assert!(is_log_stem(
+            "run_3",
+            Some("run"),
+            Some(SensorModel::RYDASON)
+        ));
+    }
+
+    #[test]
+    fn lock_held_until_dropped() {
+        let log = std::env::temp_dir().join(format!("envsensor-lock-{}.csv", std::process::id()));
+
+        assert!(!is_held(&log));
+        let lock = LogLock::acquire(&log).unwrap();
+        assert!(is_held(&log));
+        assert!(LogLock::acquire(&log).is_err());
+        drop(lock);
+        assert!(!is_held(&log));
+        assert!(!lock_path(&log).exists());
+    }
+
+    #[test]
+    fn timestamp_formats() {
+        let ts = DateTime::parse_from_rfc3339("2025-11-25T14:03:07+01:00")
+            .unwrap()
+            .with_timezone(&Local);
+
+        assert_eq!(
+            TimestampFormat::Utc.format(&ts),
+            "2025-11-25T13:03:07Z".to_string()
+        );
+        assert_eq!(
+            DateTime::parse_from_rfc3339(&TimestampFormat::Rfc3339.format(&ts)).unwrap(),
+            ts
+        );
+        assert_eq!(
+            TimestampFormat::Custom("%Y%m%d".to_string()).format(&ts),
+            ts.format("%Y%m%d").to_string()
+        );
+
+        // Rejected, and rendered as RFC 3339 instead of panicking
+        let invalid = TimestampFormat::Custom("%Y-%Q".to_string());
+        assert!(invalid.validate().is_err());
+        assert_eq!(invalid.format(&ts), TimestampFormat::Rfc3339.format(&ts));
+        assert!(TimestampFormat::Legacy.validate().is_ok());
+    }
+}
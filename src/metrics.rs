@@ -0,0 +1,195 @@
+use std::fmt::Write;
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{RecvTimeoutError, Sender},
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use bus::BusReader;
+use tiny_http::{Header, Response, Server};
+
+use crate::sensor::{AppMsg, SampleData, SensorChannel, SensorModel};
+
+/// Prometheus exporter settings
+#[derive(Clone, Debug)]
+pub struct MetricsConfig {
+    /// Address the /metrics endpoint listens on
+    pub addr: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            addr: "0.0.0.0:9898".to_string(),
+        }
+    }
+}
+
+/// Everything exported on /metrics
+struct MetricsState {
+    model: SensorModel,
+    channels: Vec<SensorChannel>,
+    latest: Option<SampleData>,
+    samples_total: u64,
+    status_total: u64,
+}
+
+impl MetricsState {
+    fn render(&self) -> String {
+        let model = self.model.as_ref();
+        let mut out = String::new();
+
+        out.push_str("# HELP envsensor_value Latest reading per channel\n");
+        out.push_str("# TYPE envsensor_value gauge\n");
+        if let Some(sample) = &self.latest {
+            for (ch, d) in self.channels.iter().zip(&sample.data) {
+                let _ = writeln!(
+                    out,
+                    "envsensor_value{{model=\"{model}\",channel=\"{}\",type=\"{}\",unit=\"{}\"}} {}",
+                    ch.id(),
+                    ch.sensor_type.as_ref(),
+                    ch.unit.as_ref(),
+                    d.value
+                );
+            }
+        }
+
+        out.push_str("# HELP envsensor_samples_total Samples read from the sensor\n");
+        out.push_str("# TYPE envsensor_samples_total counter\n");
+        let _ = writeln!(
+            out,
+            "envsensor_samples_total{{model=\"{model}\"}} {}",
+            self.samples_total
+        );
+
+        out.push_str(
+            "# HELP envsensor_status_messages_total Status and error messages from the driver\n",
+        );
+        out.push_str("# TYPE envsensor_status_messages_total counter\n");
+        let _ = writeln!(
+            out,
+            "envsensor_status_messages_total{{model=\"{model}\"}} {}",
+            self.status_total
+        );
+
+        if let Some(sample) = &self.latest {
+            out.push_str(
+                "# HELP envsensor_last_sample_timestamp_seconds Unix time of the latest sample\n",
+            );
+            out.push_str("# TYPE envsensor_last_sample_timestamp_seconds gauge\n");
+            let _ = writeln!(
+                out,
+                "envsensor_last_sample_timestamp_seconds{{model=\"{model}\"}} {}",
+                sample.timestamp.timestamp()
+            );
+        }
+
+        out
+    }
+}
+
+pub fn spawn_metrics_thread(
+    model: SensorModel,
+    flag: Arc<AtomicBool>,
+    mut rx: BusReader<AppMsg>,
+    channels: &[SensorChannel],
+    config: MetricsConfig,
+    status: Sender<AppMsg>,
+) -> Result<()> {
+    let server = Server::http(&config.addr).map_err(|e| anyhow!(e))?;
+
+    let state = Arc::new(Mutex::new(MetricsState {
+        model,
+        channels: channels.to_vec(),
+        latest: None,
+        samples_total: 0,
+        status_total: 0,
+    }));
+
+    let server_state = state.clone();
+    let server_flag = flag.clone();
+    thread::spawn(move || {
+        let content_type = Header::from_bytes("Content-Type", "text/plain; version=0.0.4").unwrap();
+
+        // A failing client must not take the endpoint down for the rest of the run
+        while !server_flag.load(Ordering::SeqCst) {
+            let request = match server.recv_timeout(Duration::from_millis(500)) {
+                Ok(Some(request)) => request,
+                Ok(None) => continue,
+                Err(e) => {
+                    let _ = status.send(AppMsg::Status(format!("Metrics receive error: {e}")));
+                    continue;
+                }
+            };
+
+            let response = if request.url() == "/metrics" {
+                Response::from_string(server_state.lock().unwrap().render())
+                    .with_header(content_type.clone())
+            } else {
+                Response::from_string("Not found").with_status_code(404)
+            };
+
+            if let Err(e) = request.respond(response) {
+                let _ = status.send(AppMsg::Status(format!("Metrics client error: {e}")));
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        while !flag.load(Ordering::SeqCst) {
+            match rx.recv_timeout(Duration::from_millis(500)) {
+                Ok(AppMsg::Sample(sample)) => {
+                    let mut state = state.lock().unwrap();
+                    state.samples_total += 1;
+                    state.latest = Some(sample);
+                }
+                Ok(AppMsg::Status(_) | AppMsg::Error(_)) => state.lock().unwrap().status_total += 1,
+                Ok(
+                    AppMsg::Alert(_)
+                    | AppMsg::Anomaly(_)
+                    | AppMsg::Traffic(_)
+                    | AppMsg::Marker(_)
+                    | AppMsg::Aggregate(_),
+                ) => {}
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::{SensorType, Unit};
+
+    #[test]
+    fn render_metrics() {
+        let state = MetricsState {
+            model: SensorModel::RYDASON,
+            channels: vec![SensorChannel::new(SensorType::CO, Unit::PPM)],
+            latest: Some(SampleData::single(
+                chrono::Local::now(),
+                0,
+                SensorType::CO,
+                1.5,
+                Unit::PPM,
+            )),
+            samples_total: 3,
+            status_total: 1,
+        };
+
+        let text = state.render();
+        assert!(text.contains(
+            "envsensor_value{model=\"RYDASON\",channel=\"co_ppm\",type=\"CO\",unit=\"ppm\"} 1.5\n"
+        ));
+        assert!(text.contains("envsensor_samples_total{model=\"RYDASON\"} 3\n"));
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    io::{Cursor, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::RecvTimeoutError,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use binrw::{BinRead, BinWrite, binrw};
+use bus::BusReader;
+
+use crate::sensor::{AppMsg, SensorChannel};
+
+const READ_HOLDING_REGISTERS: u8 = 0x03;
+const READ_INPUT_REGISTERS: u8 = 0x04;
+
+const ILLEGAL_FUNCTION: u8 = 0x01;
+const ILLEGAL_DATA_ADDRESS: u8 = 0x02;
+
+/// Modbus TCP slave settings
+#[derive(Clone, Debug)]
+pub struct ModbusTcpConfig {
+    pub addr: String,
+}
+
+impl Default for ModbusTcpConfig {
+    fn default() -> Self {
+        Self {
+            addr: "0.0.0.0:502".to_string(),
+        }
+    }
+}
+
+/// MBAP header plus the read request PDU
+#[derive(BinRead)]
+#[brw(big)]
+struct ReadReq {
+    transaction: u16,
+    protocol: u16,
+    len: u16,
+    unit: u8,
+    func: u8,
+    addr: u16,
+    count: u16,
+}
+
+#[binrw]
+#[brw(big)]
+struct RspHeader {
+    transaction: u16,
+    protocol: u16,
+    len: u16,
+    unit: u8,
+    func: u8,
+}
+
+/// Channel `n` is an IEEE 754 f32 in registers `2n` (high word) and `2n + 1` (low word)
+fn to_registers(values: &[f32]) -> Vec<u16> {
+    values
+        .iter()
+        .flat_map(|v| {
+            let bits = v.to_bits();
+            [(bits >> 16) as u16, (bits & 0xFFFF) as u16]
+        })
+        .collect()
+}
+
+/// Build the response frame for one request
+fn respond(registers: &[u16], req: &ReadReq) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+
+    let func = match req.func {
+        READ_HOLDING_REGISTERS | READ_INPUT_REGISTERS => {
+            let start = req.addr as usize;
+            let end = start + req.count as usize;
+
+            if req.count == 0 || req.count > 125 || end > registers.len() {
+                body.push(ILLEGAL_DATA_ADDRESS);
+                req.func | 0x80
+            } else {
+                body.push((req.count * 2) as u8);
+                for r in &registers[start..end] {
+                    body.extend_from_slice(&r.to_be_bytes());
+                }
+                req.func
+            }
+        }
+        _ => {
+            body.push(ILLEGAL_FUNCTION);
+            req.func | 0x80
+        }
+    };
+
+    let mut frame = Cursor::new(Vec::new());
+    RspHeader {
+        transaction: req.transaction,
+        protocol: req.protocol,
+        len: body.len() as u16 + 2,
+        unit: req.unit,
+        func,
+    }
+    .write(&mut frame)?;
+    frame.get_mut().extend_from_slice(&body);
+
+    Ok(frame.into_inner())
+}
+
+/// Fill `buf` from `stream`, false once `done` is set or the client hung up
+fn read_request(stream: &mut TcpStream, buf: &mut [u8], done: &AtomicBool) -> Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        if done.load(Ordering::SeqCst) {
+            return Ok(false);
+        }
+        match stream.read(&mut buf[filled..]) {
+            Ok(0) => return Ok(false),
+            Ok(n) => filled += n,
+            // The read timeout, time to look at `done` again
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(true)
+}
+
+fn serve_client(
+    mut stream: TcpStream,
+    registers: Arc<Mutex<Vec<u16>>>,
+    done: Arc<AtomicBool>,
+) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_millis(500)))?;
+
+    loop {
+        let mut buf = [0u8; 12];
+        if !read_request(&mut stream, &mut buf, &done)? {
+            return Ok(());
+        }
+        let req = ReadReq::read(&mut Cursor::new(&buf))?;
+
+        // Drain whatever follows in longer (unsupported) requests
+        let extra = (req.len as usize).saturating_sub(6);
+        std::io::copy(&mut (&stream).take(extra as u64), &mut std::io::sink())?;
+
+        let frame = respond(&registers.lock().unwrap(), &req)?;
+        stream.write_all(&frame)?;
+    }
+}
+
+/// Expose the latest value of every channel as holding (and input) registers
+pub fn spawn_modbus_tcp_thread(
+    flag: Arc<AtomicBool>,
+    mut rx: BusReader<AppMsg>,
+    channels: &[SensorChannel],
+    config: ModbusTcpConfig,
+) -> Result<()> {
+    let listener = TcpListener::bind(&config.addr)?;
+    listener.set_nonblocking(true)?;
+
+    let registers = Arc::new(Mutex::new(to_registers(&vec![f32::NAN; channels.len()])));
+
+    // Set once the samples stop, on a stop or when the bus goes away, ends all clients
+    let done = Arc::new(AtomicBool::new(false));
+
+    let server_registers = registers.clone();
+    let server_done = done.clone();
+    thread::spawn(move || {
+        while !server_done.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let registers = server_registers.clone();
+                    let done = server_done.clone();
+                    thread::spawn(move || serve_client(stream, registers, done));
+                }
+                Err(_) => thread::sleep(Duration::from_millis(100)),
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        while !flag.load(Ordering::SeqCst) {
+            match rx.recv_timeout(Duration::from_millis(500)) {
+                Ok(AppMsg::Sample(sample)) => {
+                    let values = sample.data.iter().map(|d| d.value).collect::<Vec<_>>();
+                    *registers.lock().unwrap() = to_registers(&values);
+                }
+                Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        done.store(true, Ordering::SeqCst);
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(func: u8, addr: u16, count: u16) -> ReadReq {
+        let mut frame = vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, func];
+        frame.extend_from_slice(&addr.to_be_bytes());
+        frame.extend_from_slice(&count.to_be_bytes());
+
+        ReadReq::read(&mut Cursor::new(frame)).unwrap()
+    }
+
+    #[test]
+    fn read_holding_registers() {
+        let registers = to_registers(&[1.0, 12.5]);
+
+        let frame = respond(&registers, &request(0x03, 2, 2)).unwrap();
+        assert_eq!(
+            frame,
+            [
+                0x00, 0x01, 0x00, 0x00, 0x00, 0x07, 0x01, 0x03, 0x04, 0x41, 0x48, 0x00, 0x00
+            ]
+        );
+
+        let frame = respond(&registers, &request(0x03, 3, 2)).unwrap();
+        assert_eq!(frame[7..], [0x83, ILLEGAL_DATA_ADDRESS]);
+
+        let frame = respond(&registers, &request(0x06, 0, 1)).unwrap();
+        assert_eq!(frame[7..], [0x86, ILLEGAL_FUNCTION]);
+    }
+}
@@ -0,0 +1,306 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{RecvTimeoutError, Sender},
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use bus::BusReader;
+use rumqttc::{Client, MqttOptions, QoS};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::config::Site;
+use crate::logger::TimestampFormat;
+use crate::sensor::{AppMsg, SensorChannel, SensorModel, SensorType, Unit};
+
+/// MQTT publishing settings
+#[derive(Clone, Debug)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Topics are "<prefix>/<model>/<channel>"
+    pub topic_prefix: String,
+    /// 0, 1 or 2
+    pub qos: u8,
+    /// Let the broker keep the last value for new subscribers
+    pub retain: bool,
+    pub timestamp: TimestampFormat,
+    /// Home Assistant discovery prefix, discovery messages are only sent when set
+    pub discovery_prefix: Option<String>,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 1883,
+            client_id: "envsensor-demo".to_string(),
+            username: None,
+            password: None,
+            topic_prefix: "envsensor".to_string(),
+            qos: 0,
+            retain: false,
+            timestamp: TimestampFormat::Rfc3339,
+            discovery_prefix: None,
+        }
+    }
+}
+
+impl MqttConfig {
+    pub fn qos(&self) -> QoS {
+        match self.qos {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            _ => QoS::ExactlyOnce,
+        }
+    }
+
+    /// "<prefix>[/<site>]/<model>/<channel>"
+    pub fn topic(
+        &self,
+        site: Option<&Site>,
+        model: SensorModel,
+        channel: &SensorChannel,
+    ) -> String {
+        match site {
+            Some(site) => format!(
+                "{}/{}/{}/{}",
+                self.topic_prefix,
+                site.slug(),
+                model.as_ref(),
+                channel.id()
+            ),
+            None => format!("{}/{}/{}", self.topic_prefix, model.as_ref(), channel.id()),
+        }
+    }
+}
+
+/// Unit spelled the way Home Assistant expects it
+fn ha_unit(unit: Unit) -> &'static str {
+    match unit {
+        Unit::PPM => "ppm",
+        Unit::PPB => "ppb",
+        Unit::MgPerM3 => "mg/m³",
+        Unit::UgPerM3 => "µg/m³",
+        Unit::PercentVol => "%",
+        Unit::TenGPerM3 => "10g/m³",
+        Unit::GPerM3 => "g/m³",
+        Unit::Celsius => "°C",
+        Unit::PercentRH => "%",
+        Unit::PerCm3 => "#/cm³",
+    }
+}
+
+/// Home Assistant device class, only when HA accepts the channel's unit for it
+fn ha_device_class(channel: &SensorChannel) -> Option<&'static str> {
+    match (channel.sensor_type, channel.unit) {
+        (SensorType::CO, Unit::PPM) => Some("carbon_monoxide"),
+        (SensorType::NO2, Unit::UgPerM3) => Some("nitrogen_dioxide"),
+        (SensorType::PM1, Unit::UgPerM3) => Some("pm1"),
+        (SensorType::PM2_5, Unit::UgPerM3) => Some("pm25"),
+        (SensorType::PM10, Unit::UgPerM3) => Some("pm10"),
+        (SensorType::Temperature | SensorType::DewPoint, Unit::Celsius) => Some("temperature"),
+        (SensorType::Humidity, Unit::PercentRH) => Some("humidity"),
+        _ => None,
+    }
+}
+
+/// Discovery topic and config payload announcing one channel as a HA sensor entity
+fn discovery_message(
+    config: &MqttConfig,
+    prefix: &str,
+    site: Option<&Site>,
+    model: SensorModel,
+    channel: &SensorChannel,
+) -> (String, serde_json::Value) {
+    let node_id = format!("{}_{}", config.client_id, model.as_ref());
+    let unique_id = format!("{node_id}_{}", channel.id());
+
+    let mut payload = json!({
+        "name": format!("{} ({})", channel.name(), channel.unit.as_ref()),
+        "unique_id": unique_id,
+        "state_topic": config.topic(site, model, channel),
+        "value_template": "{{ value_json.value }}",
+        "unit_of_measurement": ha_unit(channel.unit),
+        "state_class": "measurement",
+        "device": {
+            "identifiers": [node_id],
+            "name": format!("EnvSensor {}", model.as_ref()),
+            "model": model.as_ref(),
+            "sw_version": env!("CARGO_PKG_VERSION"),
+        },
+    });
+    if let Some(class) = ha_device_class(channel) {
+        payload["device_class"] = class.into();
+    }
+
+    (
+        format!("{prefix}/sensor/{node_id}/{}/config", channel.id()),
+        payload,
+    )
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    timestamp: String,
+    value: f32,
+    unit: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    site: Option<&'a Site>,
+}
+
+/// Connection errors are reported to `status`, once per outage
+pub fn connect(config: &MqttConfig, status: Sender<AppMsg>) -> Client {
+    let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if let Some(user) = &config.username {
+        options.set_credentials(user, config.password.clone().unwrap_or_default());
+    }
+
+    let (client, mut connection) = Client::new(options, 64);
+
+    // The connection has to be polled to make progress, it also reconnects on errors
+    thread::spawn(move || {
+        let mut connected = true;
+        for event in connection.iter() {
+            match event {
+                Ok(_) => connected = true,
+                Err(e) => {
+                    if connected {
+                        let _ = status.send(AppMsg::Status(format!("MQTT connection error: {e}")));
+                    }
+                    connected = false;
+                    thread::sleep(Duration::from_secs(5));
+                }
+            }
+        }
+    });
+
+    client
+}
+
+pub fn spawn_mqtt_thread(
+    model: SensorModel,
+    flag: Arc<AtomicBool>,
+    mut rx: BusReader<AppMsg>,
+    channels: &[SensorChannel],
+    config: MqttConfig,
+    site: Option<Site>,
+    status: Sender<AppMsg>,
+) {
+    let topics = channels
+        .iter()
+        .map(|ch| config.topic(site.as_ref(), model, ch))
+        .collect::<Vec<_>>();
+
+    let discovery = config
+        .discovery_prefix
+        .as_ref()
+        .map(|prefix| {
+            channels
+                .iter()
+                .map(|ch| discovery_message(&config, prefix, site.as_ref(), model, ch))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    thread::spawn(move || -> Result<()> {
+        let client = connect(&config, status.clone());
+        // Only the first failure while the broker is unreachable is reported
+        let mut published = true;
+
+        // Retained, so Home Assistant picks the entities up again after its own restart
+        // Without discovery the samples are still published, only the entities are missing
+        for (topic, payload) in discovery {
+            if let Err(e) = client.publish(&topic, QoS::AtLeastOnce, true, payload.to_string()) {
+                let _ = status.send(AppMsg::Status(format!(
+                    "MQTT discovery to {topic} failed: {e}"
+                )));
+            }
+        }
+
+        while !flag.load(Ordering::SeqCst) {
+            match rx.recv_timeout(Duration::from_millis(500)) {
+                Ok(AppMsg::Sample(sample)) => {
+                    let timestamp = config.timestamp.format(&sample.timestamp);
+
+                    for (topic, d) in topics.iter().zip(&sample.data) {
+                        let payload = serde_json::to_vec(&Payload {
+                            timestamp: timestamp.clone(),
+                            value: d.value,
+                            unit: d.unit.as_ref(),
+                            site: site.as_ref(),
+                        })?;
+
+                        // Drop samples rather than stall while the broker is unreachable
+                        match client.try_publish(topic, config.qos(), config.retain, payload) {
+                            Ok(()) => published = true,
+                            Err(e) => {
+                                if published {
+                                    let _ = status.send(AppMsg::Status(format!(
+                                        "MQTT publish to {topic} failed: {e}"
+                                    )));
+                                }
+                                published = false;
+                            }
+                        }
+                    }
+                }
+                Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        client.disconnect()?;
+
+        Ok(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ha_discovery() {
+        let config = MqttConfig::default();
+        let channel = SensorChannel::new(SensorType::PM2_5, Unit::UgPerM3);
+
+        let (topic, payload) = discovery_message(
+            &config,
+            "homeassistant",
+            None,
+            SensorModel::TERA_NextPM,
+            &channel,
+        );
+
+        assert_eq!(
+            topic,
+            "homeassistant/sensor/envsensor-demo_TERA_NextPM/pm2_5_ugperm3/config"
+        );
+        assert_eq!(payload["device_class"], "pm25");
+        assert_eq!(payload["unit_of_measurement"], "µg/m³");
+        assert_eq!(
+            payload["state_topic"],
+            "envsensor/TERA_NextPM/pm2_5_ugperm3"
+        );
+
+        let channel = SensorChannel::new(SensorType::CO, Unit::MgPerM3);
+        let (_, payload) = discovery_message(
+            &config,
+            "homeassistant",
+            None,
+            SensorModel::EC_TB600BC,
+            &channel,
+        );
+        assert!(payload.get("device_class").is_none());
+    }
+}
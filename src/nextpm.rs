@@ -1,11 +1,15 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use binrw::BinRead;
-use std::io::Cursor;
+use std::io::{Cursor, Read, Write};
+use std::sync::mpsc::Sender;
 use std::time::Duration;
 
-use serialport::SerialPort;
-
-use crate::sensor::{SensorChannel, SensorData, SensorDriver, SensorModel, SensorType, Unit};
+use crate::sensor::{
+    SensorChannel, SensorData, SensorDriver, SensorInfo, SensorModel, SensorType, SerialSettings,
+    Unit,
+};
+use crate::traffic::{Frame, Tap};
+use crate::transport;
 
 #[allow(dead_code)]
 #[derive(BinRead)]
@@ -14,23 +18,80 @@ struct ReadingReply {
     addr: u8,
     cmd: u8,
     state: u8,
-    unused_pm: [u8; 6],
+    /// Particles per cm³ below 1, 2.5 and 10 µm
+    n1: u16,
+    n2_5: u16,
+    n10: u16,
     pm1: u16,
     pm2_5: u16,
     pm10: u16,
     checksum: u8,
 }
 
+#[allow(dead_code)]
+#[derive(BinRead)]
+#[brw(big)]
+struct TempHumidityReply {
+    addr: u8,
+    cmd: u8,
+    state: u8,
+    /// Hundredths of °C
+    temperature: u16,
+    /// Hundredths of %RH
+    humidity: u16,
+    checksum: u8,
+}
+
+#[allow(dead_code)]
+#[derive(BinRead)]
+#[brw(big)]
+struct FirmwareReply {
+    addr: u8,
+    cmd: u8,
+    state: u8,
+    version: u16,
+    checksum: u8,
+}
+
+/// Mass concentrations of PM1, PM2.5 and PM10 and the matching particle counts of a reading
+/// reply
+pub(crate) fn decode_reading(reply: &[u8]) -> Result<([f32; 3], [f32; 3])> {
+    //TODO verify the checksum
+    let value = ReadingReply::read(&mut Cursor::new(reply))?;
+
+    let pm1 = value.pm1 as f32 / 10.0;
+    let pm2_5 = value.pm2_5 as f32 / 10.0;
+    let pm10 = value.pm10 as f32 / 10.0;
+
+    let counts = [value.n1, value.n2_5, value.n10].map(f32::from);
+
+    Ok(([pm1, pm2_5, pm10], counts))
+}
+
+/// Temperature and relative humidity of a reply
+pub(crate) fn decode_temp_humidity(reply: &[u8]) -> Result<(f32, f32)> {
+    let value = TempHumidityReply::read(&mut Cursor::new(reply))?;
+
+    Ok((
+        value.temperature as f32 / 100.0,
+        value.humidity as f32 / 100.0,
+    ))
+}
+
+/// Firmware version of a reply
+pub(crate) fn decode_firmware(reply: &[u8]) -> Result<u16> {
+    Ok(FirmwareReply::read(&mut Cursor::new(reply))?.version)
+}
+
 pub struct NextPM {
-    dev: Box<dyn SerialPort>,
+    dev: Tap,
     channels: Vec<SensorChannel>,
+    firmware: Option<u16>,
+    /// Why the firmware version couldn't be read
+    firmware_error: Option<String>,
 }
 
-fn simple_read(
-    port: &mut Box<dyn SerialPort>,
-    query: &[u8],
-    resp_len: usize,
-) -> Result<Cursor<Vec<u8>>> {
+fn simple_read(port: &mut Tap, query: &[u8], resp_len: usize) -> Result<Cursor<Vec<u8>>> {
     // Write the query command
     port.write_all(query)?;
 
@@ -45,49 +106,93 @@ fn simple_read(
 }
 
 impl NextPM {
-    pub fn new(port: &str) -> Result<Self> {
+    pub fn new(
+        port: &str,
+        serial: &SerialSettings,
+        capture: Option<Sender<Frame>>,
+    ) -> Result<Self> {
         let builder = serialport::new(port, 115200)
             .data_bits(serialport::DataBits::Eight)
             .parity(serialport::Parity::Even)
             .stop_bits(serialport::StopBits::One)
             .timeout(Duration::from_secs(5));
+        let builder = serial.apply(builder);
         println!("{:?}", &builder);
 
-        let port = builder.open().unwrap_or_else(|e| {
-            eprintln!("Failed to open \"{}\". Error: {}", port, e);
-            ::std::process::exit(1);
-        });
+        let port = transport::open(port, builder)
+            .map_err(|e| anyhow!("Failed to open \"{port}\": {e}"))?;
+        let port = Tap::new(port, capture);
 
         // Build channel metadata
         let channels = vec![
             SensorChannel::new(SensorType::PM1, Unit::UgPerM3),
             SensorChannel::new(SensorType::PM2_5, Unit::UgPerM3),
             SensorChannel::new(SensorType::PM10, Unit::UgPerM3),
+            SensorChannel::new(SensorType::Temperature, Unit::Celsius),
+            SensorChannel::new(SensorType::Humidity, Unit::PercentRH),
+            // Counted from the smallest size the sensor sees, so the ranges overlap
+            SensorChannel::count(0.3, 1.0),
+            SensorChannel::count(0.3, 2.5),
+            SensorChannel::count(0.3, 10.0),
         ];
 
         Ok(NextPM {
             dev: port,
             channels,
+            firmware: None,
+            firmware_error: None,
         })
     }
 
-    pub fn read_measured_value(&mut self) -> Result<(f32, f32, f32)> {
-        let mut buffer = simple_read(&mut self.dev, &[0x81, 0x11, 0x6E], 16)?;
+    pub fn read_firmware_version(&mut self) -> Result<u16> {
+        let buffer = simple_read(&mut self.dev, &[0x81, 0x17, 0x68], 6)?;
+
+        decode_firmware(buffer.get_ref())
+    }
+
+    /// Mass concentrations of PM1, PM2.5 and PM10 and the matching particle counts
+    pub fn read_measured_value(&mut self) -> Result<([f32; 3], [f32; 3])> {
+        let buffer = simple_read(&mut self.dev, &[0x81, 0x11, 0x6E], 16)?;
 
-        //TODO verify the checksum
-        let value = ReadingReply::read(&mut buffer)?;
+        decode_reading(buffer.get_ref())
+    }
 
-        let pm1 = value.pm1 as f32 / 10.0;
-        let pm2_5 = value.pm2_5 as f32 / 10.0;
-        let pm10 = value.pm10 as f32 / 10.0;
+    /// Temperature and relative humidity inside the sensor
+    pub fn read_temp_humidity(&mut self) -> Result<(f32, f32)> {
+        let buffer = simple_read(&mut self.dev, &[0x81, 0x14, 0x6B], 8)?;
 
-        Ok((pm1, pm2_5, pm10))
+        decode_temp_humidity(buffer.get_ref())
     }
 }
 
 impl SensorDriver for NextPM {
-    fn new(port: &str) -> Result<Self> {
-        NextPM::new(port)
+    fn new(port: &str, serial: &SerialSettings, capture: Option<Sender<Frame>>) -> Result<Self> {
+        NextPM::new(port, serial, capture)
+    }
+
+    fn initialize(&mut self) -> Result<()> {
+        // Only informative, a sensor that doesn't answer it can still measure
+        match self.read_firmware_version() {
+            Ok(version) => self.firmware = Some(version),
+            Err(e) => {
+                self.firmware_error = Some(format!("Failed to read NextPM firmware version: {e}"))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        self.firmware_error.iter().cloned().collect()
+    }
+
+    fn info(&self) -> SensorInfo {
+        SensorInfo {
+            firmware: self.firmware.map(|v| v.to_string()),
+            // Readings are sent in tenths of µg/m³
+            scale: Some(10),
+            ..Default::default()
+        }
     }
 
     fn get_metadata(&self) -> &[SensorChannel] {
@@ -95,28 +200,38 @@ impl SensorDriver for NextPM {
     }
 
     fn read_data(&mut self) -> Result<Vec<SensorData>> {
-        let (pm1, pm2_5, pm10) = self.read_measured_value()?;
+        let ([pm1, pm2_5, pm10], counts) = self.read_measured_value()?;
+        let (temperature, humidity) = self.read_temp_humidity()?;
 
         // NextPM needs polling delay
         std::thread::sleep(std::time::Duration::from_secs(1));
 
-        Ok(vec![
-            SensorData {
-                ty: self.channels[0].sensor_type,
-                value: pm1,
-                unit: self.channels[0].unit,
-            },
-            SensorData {
-                ty: self.channels[1].sensor_type,
-                value: pm2_5,
-                unit: self.channels[1].unit,
-            },
-            SensorData {
-                ty: self.channels[2].sensor_type,
-                value: pm10,
-                unit: self.channels[2].unit,
-            },
-        ])
+        let mut data = vec![
+            SensorData::new(self.channels[0].sensor_type, pm1, self.channels[0].unit),
+            SensorData::new(self.channels[1].sensor_type, pm2_5, self.channels[1].unit),
+            SensorData::new(self.channels[2].sensor_type, pm10, self.channels[2].unit),
+            SensorData::new(
+                self.channels[3].sensor_type,
+                temperature,
+                self.channels[3].unit,
+            ),
+            SensorData::new(
+                self.channels[4].sensor_type,
+                humidity,
+                self.channels[4].unit,
+            ),
+        ];
+        data.extend(
+            self.channels[5..]
+                .iter()
+                .zip(counts)
+                .map(|(ch, value)| SensorData {
+                    bin: ch.bin,
+                    ..SensorData::new(ch.sensor_type, value, ch.unit)
+                }),
+        );
+
+        Ok(data)
     }
 
     fn model() -> SensorModel {
@@ -0,0 +1,84 @@
+use std::{
+    collections::VecDeque,
+    sync::{Condvar, Mutex},
+};
+
+/// Bounded queue between a bus reader and a worker doing network I/O
+///
+/// `push` never blocks, when the queue is full the oldest item is dropped. This way a slow
+/// or unreachable endpoint can't keep the reader from draining the bus.
+pub struct Outbox<T> {
+    state: Mutex<State<T>>,
+    ready: Condvar,
+    capacity: usize,
+}
+
+struct State<T> {
+    items: VecDeque<T>,
+    closed: bool,
+}
+
+impl<T> Outbox<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                items: VecDeque::new(),
+                closed: false,
+            }),
+            ready: Condvar::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Queue `item`, returns true when the oldest item had to be dropped for it
+    pub fn push(&self, item: T) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let dropped = state.items.len() >= self.capacity;
+        if dropped {
+            state.items.pop_front();
+        }
+        state.items.push_back(item);
+        self.ready.notify_one();
+
+        dropped
+    }
+
+    /// No more items follow, `pop` returns the queued ones and then `None`
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.ready.notify_all();
+    }
+
+    /// Wait for the next item, `None` once the outbox is closed and empty
+    pub fn pop(&self) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(item) = state.items.pop_front() {
+                return Some(item);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.ready.wait(state).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drop_oldest() {
+        let outbox = Outbox::new(2);
+
+        assert!(!outbox.push(1));
+        assert!(!outbox.push(2));
+        assert!(outbox.push(3));
+        outbox.close();
+
+        assert_eq!(outbox.pop(), Some(2));
+        assert_eq!(outbox.pop(), Some(3));
+        assert_eq!(outbox.pop(), None);
+    }
+}
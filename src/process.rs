@@ -0,0 +1,729 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::calibration::{Calibration, ChannelCalibration};
+use crate::convert::{Ambient, absolute_humidity, convert, convert_at, dew_point};
+use crate::sensor::{Quality, SensorChannel, SensorData, SensorType, Unit};
+
+/// Scale of the median absolute deviation to the standard deviation of normal noise
+const MAD_SCALE: f32 = 1.4826;
+
+/// What happens to a spike
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum DespikeMode {
+    /// Keep the value
+    Flag,
+    /// Replace the value with the median of the preceding samples
+    #[default]
+    Replace,
+}
+
+/// Rejection of single-sample spikes, typical of optical PM sensors
+///
+/// A value is a spike when it is further than `threshold` times the scaled median absolute
+/// deviation from the median of the preceding `window` samples (a Hampel filter). Spikes are
+/// reported as markers in either mode.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Despike {
+    pub window: usize,
+    pub threshold: f32,
+    pub mode: DespikeMode,
+}
+
+impl Default for Despike {
+    fn default() -> Self {
+        Self {
+            window: 7,
+            threshold: 3.0,
+            mode: DespikeMode::default(),
+        }
+    }
+}
+
+fn median(values: &mut [f32]) -> f32 {
+    values.sort_by(f32::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+impl Despike {
+    /// Median of `recent` when `value` is a spike
+    fn check(&self, recent: &VecDeque<f32>, value: f32) -> Option<f32> {
+        if recent.len() < self.window.max(3) {
+            return None;
+        }
+
+        let mut values = recent.iter().copied().collect::<Vec<_>>();
+        let center = median(&mut values);
+        let mut deviations = values
+            .iter()
+            .map(|v| (v - center).abs())
+            .collect::<Vec<_>>();
+        // A flat signal has no deviation at all, 10% of the median is the least spread
+        // accepted, a signal sitting at zero isn't despiked
+        let spread = (MAD_SCALE * median(&mut deviations)).max(0.1 * center.abs());
+
+        (spread > 0.0 && (value - center).abs() > self.threshold * spread).then_some(center)
+    }
+}
+
+/// Automatic zeroing of electrochemical gas sensors, as commercial CO monitors do
+///
+/// When the raw values of a gas channel stay within `tolerance` of each other for `samples`
+/// readings, clean air is assumed and their mean becomes the new zero. The plateau has to
+/// sit within `tolerance` of the lowest value of the last `FLOOR_PLATEAUS` such stretches,
+/// and no further than `max_offset` from zero, otherwise it is taken for real gas and leaves
+/// the offset alone. Both limits are in the unit the sensor delivers.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct AutoZero {
+    pub samples: usize,
+    pub tolerance: f32,
+    pub max_offset: f32,
+}
+
+impl Default for AutoZero {
+    fn default() -> Self {
+        Self {
+            // An hour at the default poll interval
+            samples: 3600,
+            tolerance: 1.0,
+            max_offset: 5.0,
+        }
+    }
+}
+
+/// Stretches of `AutoZero::samples` readings the running minimum looks back over, a day at
+/// the default length
+const FLOOR_PLATEAUS: usize = 24;
+
+/// Current stable run of raw values and the zero found so far
+#[derive(Clone, Debug, Default)]
+struct ZeroState {
+    offset: f32,
+    min: f32,
+    max: f32,
+    sum: f32,
+    count: usize,
+    /// Lowest raw value of each of the last `FLOOR_PLATEAUS` stretches
+    floors: VecDeque<f32>,
+    /// Lowest raw value and length of the current stretch
+    floor: f32,
+    floor_len: usize,
+}
+
+impl ZeroState {
+    fn running_min(&self) -> f32 {
+        let current = if self.floor_len > 0 {
+            self.floor
+        } else {
+            f32::INFINITY
+        };
+        self.floors.iter().copied().fold(current, f32::min)
+    }
+}
+
+impl AutoZero {
+    /// Take in a raw value, returns it corrected by the current offset
+    fn apply(&self, state: &mut ZeroState, raw: f32) -> f32 {
+        let samples = self.samples.max(1);
+
+        if state.count == 0 || raw.max(state.max) - raw.min(state.min) > self.tolerance {
+            state.min = raw;
+            state.max = raw;
+            state.sum = 0.0;
+            state.count = 0;
+        }
+        state.min = state.min.min(raw);
+        state.max = state.max.max(raw);
+        state.sum += raw;
+        state.count += 1;
+
+        state.floor = if state.floor_len == 0 {
+            raw
+        } else {
+            state.floor.min(raw)
+        };
+        state.floor_len += 1;
+        if state.floor_len >= samples {
+            if state.floors.len() == FLOOR_PLATEAUS {
+                state.floors.pop_front();
+            }
+            state.floors.push_back(state.floor);
+            state.floor_len = 0;
+        }
+
+        if state.count >= samples {
+            let mean = state.sum / state.count as f32;
+            // A steady concentration above the clean air seen lately is real gas
+            if mean.abs() <= self.max_offset && mean - state.running_min() <= self.tolerance {
+                state.offset = mean;
+            }
+            state.count = 0;
+        }
+
+        raw - state.offset
+    }
+}
+
+/// Channels of electrochemical cells, the ones auto-zeroing applies to
+fn is_gas(ty: SensorType) -> bool {
+    matches!(ty, SensorType::CO | SensorType::NO2)
+}
+
+/// Latest temperature measured by any running sensor, shared with the others for their
+/// compensation
+pub type SharedTemperature = Arc<Mutex<Option<f32>>>;
+
+/// Temperature the compensation coefficients are relative to, in °C
+const REFERENCE_TEMPERATURE: f32 = 20.0;
+
+/// Correction of the readings for the ambient temperature and pressure
+///
+/// The temperature comes from the sensor itself or another running one when either measures
+/// it, from `manual` otherwise. The pressure is always the manual one.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Compensation {
+    pub manual: Ambient,
+    /// Relative change of the reading per °C above 20 °C, divided out, e.g. 0.005 for +0.5 %/°C
+    pub temperature_coefficients: BTreeMap<SensorType, f32>,
+    /// Scale particulate mass concentrations to 25 °C and 1013.25 hPa
+    pub pm_standard: bool,
+}
+
+impl Compensation {
+    fn apply(&self, ty: SensorType, value: f32, ambient: Ambient) -> f32 {
+        let mut value = value;
+        if let Some(k) = self.temperature_coefficients.get(&ty) {
+            value /= 1.0 + k * (ambient.temperature - REFERENCE_TEMPERATURE);
+        }
+        if self.pm_standard && matches!(ty, SensorType::PM1 | SensorType::PM2_5 | SensorType::PM10)
+        {
+            // The same particles per volume of air compressed to standard conditions, warm or
+            // thin air reads low
+            value *= ambient.molar_volume() / Ambient::default().molar_volume();
+        }
+        value
+    }
+}
+
+/// Relative humidity above which the growth correction holds the value at, in %
+const MAX_RH: f32 = 95.0;
+
+/// Density of dry particles relative to water, as assumed by the κ-Köhler correction
+const PARTICLE_DENSITY: f32 = 1.65;
+
+/// Correction of optical PM readings for the water particles take up in humid air
+///
+/// The reading is divided by the mass growth factor at the measured relative humidity `aw`,
+/// `1 + κ / 1.65 · aw / (1 - aw)` by κ-Köhler theory or a fitted `1 + a · aw^b / (1 - aw)`.
+/// The corrected values are appended as derived channels.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "model")]
+pub enum HumidityCorrection {
+    /// Hygroscopicity of the aerosol, around 0.4 for urban background air
+    Kohler { kappa: f32 },
+    /// Fitted against a reference, e.g. a = 0.25 and b = 2
+    Empirical { a: f32, b: f32 },
+}
+
+impl Default for HumidityCorrection {
+    fn default() -> Self {
+        Self::Kohler { kappa: 0.4 }
+    }
+}
+
+impl HumidityCorrection {
+    /// Dry mass of `value` measured at `rh` % relative humidity
+    pub fn apply(&self, value: f32, rh: f32) -> f32 {
+        // Both models diverge towards saturation
+        let aw = rh.clamp(0.0, MAX_RH) / 100.0;
+        let growth = match *self {
+            Self::Kohler { kappa } => 1.0 + kappa / PARTICLE_DENSITY * aw / (1.0 - aw),
+            Self::Empirical { a, b } => 1.0 + a * aw.powf(b) / (1.0 - aw),
+        };
+        value / growth
+    }
+}
+
+/// Derived type of the humidity corrected readings of a PM type
+fn dry_type(ty: SensorType) -> Option<SensorType> {
+    match ty {
+        SensorType::PM1 => Some(SensorType::PM1Dry),
+        SensorType::PM2_5 => Some(SensorType::PM2_5Dry),
+        SensorType::PM10 => Some(SensorType::PM10Dry),
+        _ => None,
+    }
+}
+
+/// Processing applied to readings before they are broadcast
+#[derive(Clone, Debug, Default)]
+pub struct ProcessConfig {
+    /// Convert channels of this type to the given unit
+    pub units: Vec<(SensorType, Unit)>,
+    /// Moving average over this many samples, 0 or 1 disables it
+    pub smoothing: usize,
+    /// Spike rejection, before the moving average
+    pub despike: Option<Despike>,
+    /// Baseline correction of the gas channels, before everything else
+    pub auto_zero: Option<AutoZero>,
+    /// Temperature and pressure correction, before the unit conversion
+    pub compensation: Option<Compensation>,
+    /// Linear correction of single channels, after everything else. The sensor thread only
+    /// keeps those of its own device.
+    pub calibration: Vec<ChannelCalibration>,
+    /// Humidity growth correction of the PM channels, derived from the processed values
+    pub humidity_correction: Option<HumidityCorrection>,
+    /// Shared by the sensors of one frontend, see `Controller`
+    pub temperature: SharedTemperature,
+}
+
+/// Per-session state of the processing stages
+///
+/// When any stage is active every channel keeps its raw value next to the processed one,
+/// so the log always contains the source data. Dew point and absolute humidity are appended
+/// as derived channels, without a raw value, whenever the driver reports temperature and
+/// relative humidity, and so are humidity corrected PM channels when configured.
+pub struct Pipeline {
+    channels: Vec<SensorChannel>,
+    /// Indices of the temperature and humidity channels the derived ones come from
+    humidity: Option<(usize, usize)>,
+    humidity_correction: Option<HumidityCorrection>,
+    /// Index of the relative humidity channel and of the PM channels corrected with it,
+    /// with their derived type
+    dry: Option<(usize, Vec<(usize, SensorType)>)>,
+    smoothing: usize,
+    windows: Vec<VecDeque<f32>>,
+    despike: Option<Despike>,
+    /// Raw values preceding the current one, for the spike rejection
+    recent: Vec<VecDeque<f32>>,
+    auto_zero: Option<AutoZero>,
+    zeros: Vec<ZeroState>,
+    compensation: Option<Compensation>,
+    temperature: SharedTemperature,
+    /// Calibration of each channel
+    calibrations: Vec<Option<Calibration>>,
+}
+
+impl Pipeline {
+    pub fn new(config: &ProcessConfig, channels: &[SensorChannel]) -> Self {
+        let mut channels = channels.to_vec();
+        let mut enabled = config.smoothing > 1
+            || config.despike.is_some()
+            || config.auto_zero.is_some()
+            || config.compensation.is_some()
+            || !config.calibration.is_empty();
+
+        for ch in &mut channels {
+            let target = config
+                .units
+                .iter()
+                .find(|(ty, _)| *ty == ch.sensor_type)
+                .map(|&(_, unit)| unit);
+
+            // Conversions the gas can't do (PM to ppm, ...) leave the channel alone
+            if let Some(unit) = target
+                && unit != ch.unit
+                && convert(1.0, ch.sensor_type, ch.unit, unit).is_some()
+            {
+                ch.raw_unit = Some(ch.unit);
+                ch.unit = unit;
+                enabled = true;
+            }
+        }
+
+        if enabled {
+            for ch in &mut channels {
+                ch.raw_unit.get_or_insert(ch.unit);
+            }
+        }
+
+        let find = |ty, unit| {
+            channels
+                .iter()
+                .position(|ch| ch.sensor_type == ty && ch.unit == unit)
+        };
+        let rh = find(SensorType::Humidity, Unit::PercentRH);
+        let humidity = find(SensorType::Temperature, Unit::Celsius).zip(rh);
+        let dry = rh
+            .filter(|_| config.humidity_correction.is_some())
+            .map(|rh| {
+                let pm = channels
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(idx, ch)| Some((idx, dry_type(ch.sensor_type)?)))
+                    .collect::<Vec<_>>();
+                (rh, pm)
+            });
+        if humidity.is_some() {
+            channels.push(SensorChannel::new(SensorType::DewPoint, Unit::Celsius));
+            channels.push(SensorChannel::new(
+                SensorType::AbsoluteHumidity,
+                Unit::GPerM3,
+            ));
+        }
+        for &(idx, ty) in dry.iter().flat_map(|(_, pm)| pm) {
+            channels.push(SensorChannel::new(ty, channels[idx].unit));
+        }
+
+        let calibrations = channels
+            .iter()
+            .map(|ch| {
+                let id = ch.id();
+                config
+                    .calibration
+                    .iter()
+                    .find(|c| c.channel == id)
+                    .map(|c| c.calibration)
+            })
+            .collect();
+
+        Self {
+            humidity,
+            humidity_correction: config.humidity_correction,
+            dry,
+            calibrations,
+            windows: vec![VecDeque::new(); channels.len()],
+            recent: vec![VecDeque::new(); channels.len()],
+            zeros: vec![ZeroState::default(); channels.len()],
+            channels,
+            smoothing: config.smoothing,
+            despike: config.despike,
+            auto_zero: config.auto_zero,
+            compensation: config.compensation.clone(),
+            temperature: config.temperature.clone(),
+        }
+    }
+
+    /// Channels as they look after processing
+    pub fn channels(&self) -> &[SensorChannel] {
+        &self.channels
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.channels.iter().any(|ch| ch.raw_unit.is_some())
+    }
+
+    /// Process `data` in place and append the derived channels, returns the types of the
+    /// channels with a spike
+    pub fn apply(&mut self, data: &mut Vec<SensorData>) -> Vec<SensorType> {
+        if let Some(t) = data.iter().find(|d| d.ty == SensorType::Temperature) {
+            *self.temperature.lock().unwrap() = Some(t.value);
+        }
+
+        let spikes = if self.is_enabled() {
+            self.process(data)
+        } else {
+            Vec::new()
+        };
+
+        if let Some((t, rh)) = self.humidity
+            && let (Some(t), Some(rh)) = (data.get(t), data.get(rh))
+        {
+            let (t, rh) = (t.value, rh.value);
+            for (ty, value, unit) in [
+                (SensorType::DewPoint, dew_point(t, rh), Unit::Celsius),
+                (
+                    SensorType::AbsoluteHumidity,
+                    absolute_humidity(t, rh),
+                    Unit::GPerM3,
+                ),
+            ] {
+                data.push(SensorData::new(ty, value, unit));
+            }
+        }
+
+        if let (Some(correction), Some((rh, pm))) = (&self.humidity_correction, &self.dry)
+            && let Some(rh) = data.get(*rh).map(|d| d.value)
+        {
+            for &(idx, ty) in pm {
+                let Some(d) = data.get(idx) else {
+                    continue;
+                };
+                let derived = SensorData {
+                    quality: d.quality,
+                    ..SensorData::new(ty, correction.apply(d.value, rh), d.unit)
+                };
+                data.push(derived);
+            }
+        }
+
+        spikes
+    }
+
+    fn process(&mut self, data: &mut [SensorData]) -> Vec<SensorType> {
+        let mut spikes = Vec::new();
+        let ambient = self.compensation.as_ref().map(|c| Ambient {
+            temperature: self
+                .temperature
+                .lock()
+                .unwrap()
+                .unwrap_or(c.manual.temperature),
+            ..c.manual
+        });
+
+        let channels = self
+            .channels
+            .iter()
+            .zip(&mut self.windows)
+            .zip(&mut self.recent)
+            .zip(&mut self.zeros)
+            .zip(&self.calibrations);
+        for (d, ((((ch, window), recent), zero), calibration)) in data.iter_mut().zip(channels) {
+            let raw = d.value;
+            let mut value = raw;
+
+            if let Some(auto_zero) = &self.auto_zero
+                && is_gas(ch.sensor_type)
+            {
+                value = auto_zero.apply(zero, raw);
+            }
+
+            if let Some(despike) = &self.despike {
+                if let Some(median) = despike.check(recent, raw) {
+                    spikes.push(ch.sensor_type);
+                    match despike.mode {
+                        DespikeMode::Flag => d.quality = Quality::Suspect,
+                        DespikeMode::Replace => {
+                            // The median is of raw values, zero it like the value itself
+                            value = median - zero.offset;
+                            d.quality = Quality::Interpolated;
+                        }
+                    }
+                }
+                if recent.len() == despike.window.max(3) {
+                    recent.pop_front();
+                }
+                recent.push_back(raw);
+            }
+
+            if self.smoothing > 1 {
+                if window.len() == self.smoothing {
+                    window.pop_front();
+                }
+                window.push_back(value);
+                value = window.iter().sum::<f32>() / window.len() as f32;
+            }
+
+            if let Some((compensation, ambient)) = self.compensation.as_ref().zip(ambient) {
+                value = compensation.apply(ch.sensor_type, value, ambient);
+            }
+
+            if let Some(from) = ch.raw_unit {
+                value = convert_at(
+                    value,
+                    ch.sensor_type,
+                    from,
+                    ch.unit,
+                    ambient.unwrap_or_default(),
+                )
+                .unwrap_or(value);
+            }
+
+            if let Some(calibration) = calibration {
+                value = calibration.apply(ch.sensor_type, value, ch.unit);
+            }
+
+            d.raw = Some(raw);
+            d.value = value;
+            d.unit = ch.unit;
+        }
+
+        spikes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_raw_values() {
+        let config = ProcessConfig {
+            units: vec![(SensorType::CO, Unit::PPB)],
+            smoothing: 2,
+            despike: None,
+            auto_zero: None,
+            ..Default::default()
+        };
+        let channels = [
+            SensorChannel::new(SensorType::CO, Unit::PPM),
+            SensorChannel::new(SensorType::PM10, Unit::UgPerM3),
+        ];
+        let mut pipeline = Pipeline::new(&config, &channels);
+
+        assert_eq!(pipeline.channels()[0].unit, Unit::PPB);
+        assert_eq!(pipeline.channels()[1].raw_unit, Some(Unit::UgPerM3));
+
+        let mut last = Vec::new();
+        for (co, pm) in [(1.0, 10.0), (2.0, 20.0)] {
+            last = vec![
+                SensorData::new(SensorType::CO, co, Unit::PPM),
+                SensorData::new(SensorType::PM10, pm, Unit::UgPerM3),
+            ];
+            pipeline.apply(&mut last);
+        }
+
+        assert!((last[0].value - 1500.0).abs() < 1e-3);
+        assert_eq!(last[0].raw, Some(2.0));
+        assert_eq!((last[1].value, last[1].raw), (15.0, Some(20.0)));
+    }
+
+    #[test]
+    fn replaces_spikes() {
+        let config = ProcessConfig {
+            despike: Some(Despike::default()),
+            ..Default::default()
+        };
+        let channels = [SensorChannel::new(SensorType::PM2_5, Unit::UgPerM3)];
+        let mut pipeline = Pipeline::new(&config, &channels);
+
+        let mut values = Vec::new();
+        for raw in [10.0, 11.0, 10.0, 12.0, 11.0, 10.0, 11.0, 250.0, 11.0, 12.0] {
+            let mut data = vec![SensorData::new(SensorType::PM2_5, raw, Unit::UgPerM3)];
+            let spikes = pipeline.apply(&mut data);
+            assert_eq!(spikes.len(), usize::from(raw == 250.0));
+            values.push((data[0].value, data[0].raw, data[0].quality));
+        }
+
+        assert_eq!(values[7], (11.0, Some(250.0), Quality::Interpolated));
+        assert_eq!(values[9], (12.0, Some(12.0), Quality::Valid));
+    }
+
+    #[test]
+    fn zeroes_stable_baseline() {
+        let config = ProcessConfig {
+            auto_zero: Some(AutoZero {
+                samples: 5,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let channels = [SensorChannel::new(SensorType::CO, Unit::PPM)];
+        let mut pipeline = Pipeline::new(&config, &channels);
+
+        let mut apply = |raw| {
+            let mut data = vec![SensorData::new(SensorType::CO, raw, Unit::PPM)];
+            pipeline.apply(&mut data);
+            data[0].value
+        };
+
+        // A stable 2 ppm in clean air becomes the zero
+        for raw in [2.2, 1.8, 2.0, 2.1, 1.9] {
+            apply(raw);
+        }
+        assert!((apply(12.0) - 10.0).abs() < 1e-4);
+        // A long stable 30 ppm is real gas
+        for _ in 0..10 {
+            apply(30.0);
+        }
+        assert!((apply(2.0)).abs() < 1e-4);
+        // So is a steady 4 ppm, above the minimum of the clean air before
+        for _ in 0..10 {
+            apply(4.0);
+        }
+        assert!((apply(2.0)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn replaced_spike_is_zeroed() {
+        let config = ProcessConfig {
+            auto_zero: Some(AutoZero {
+                samples: 5,
+                ..Default::default()
+            }),
+            despike: Some(Despike::default()),
+            ..Default::default()
+        };
+        let channels = [SensorChannel::new(SensorType::CO, Unit::PPM)];
+        let mut pipeline = Pipeline::new(&config, &channels);
+
+        let mut last = Vec::new();
+        for raw in [2.0, 2.1, 1.9, 2.0, 2.0, 2.1, 1.9, 2.0, 250.0] {
+            last = vec![SensorData::new(SensorType::CO, raw, Unit::PPM)];
+            pipeline.apply(&mut last);
+        }
+
+        assert_eq!(last[0].quality, Quality::Interpolated);
+        assert!(last[0].value.abs() < 1e-4);
+    }
+
+    #[test]
+    fn pm_to_standard_conditions() {
+        let compensation = Compensation {
+            pm_standard: true,
+            ..Default::default()
+        };
+        let at = |temperature, pressure| Ambient {
+            temperature,
+            pressure,
+        };
+
+        // 35 °C and 900 hPa hold 1.034 * 1.126 times the standard volume of air
+        let pm = compensation.apply(SensorType::PM2_5, 10.0, at(35.0, 900.0));
+        assert!((pm - 11.636).abs() < 1e-3);
+        assert_eq!(
+            compensation.apply(SensorType::PM2_5, 10.0, Ambient::default()),
+            10.0
+        );
+        // Gases are converted by their own molar volume
+        assert_eq!(
+            compensation.apply(SensorType::CO, 10.0, at(35.0, 900.0)),
+            10.0
+        );
+    }
+
+    #[test]
+    fn derives_humidity() {
+        let channels = [
+            SensorChannel::new(SensorType::Temperature, Unit::Celsius),
+            SensorChannel::new(SensorType::Humidity, Unit::PercentRH),
+        ];
+        let mut pipeline = Pipeline::new(&ProcessConfig::default(), &channels);
+        assert_eq!(pipeline.channels().len(), 4);
+        assert_eq!(pipeline.channels()[3].unit, Unit::GPerM3);
+
+        let mut data = vec![
+            SensorData::new(SensorType::Temperature, 20.0, Unit::Celsius),
+            SensorData::new(SensorType::Humidity, 50.0, Unit::PercentRH),
+        ];
+        pipeline.apply(&mut data);
+
+        assert_eq!(data[2].ty, SensorType::DewPoint);
+        assert!((data[2].value - 9.26).abs() < 0.01);
+        assert_eq!((data[0].raw, data[3].raw), (None, None));
+    }
+
+    #[test]
+    fn corrects_humidity_growth() {
+        let config = ProcessConfig {
+            humidity_correction: Some(HumidityCorrection::default()),
+            ..Default::default()
+        };
+        let channels = [
+            SensorChannel::new(SensorType::PM2_5, Unit::UgPerM3),
+            SensorChannel::new(SensorType::Humidity, Unit::PercentRH),
+        ];
+        let mut pipeline = Pipeline::new(&config, &channels);
+        assert_eq!(pipeline.channels()[2].sensor_type, SensorType::PM2_5Dry);
+
+        let mut data = vec![
+            SensorData::new(SensorType::PM2_5, 20.0, Unit::UgPerM3),
+            SensorData::new(SensorType::Humidity, 80.0, Unit::PercentRH),
+        ];
+        pipeline.apply(&mut data);
+
+        // Growth of 1 + 0.4 / 1.65 * 0.8 / 0.2 at 80 %
+        assert_eq!(data[2].ty, SensorType::PM2_5Dry);
+        assert!((data[2].value - 10.154).abs() < 1e-3);
+        assert_eq!(data[0].value, 20.0);
+    }
+}
@@ -0,0 +1,135 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::sensor::{AppMsg, SensorChannel, SensorModel};
+
+/// First message of the WebSocket stream of envsensord
+#[derive(Clone, Debug, Deserialize)]
+pub struct Hello {
+    pub model: SensorModel,
+    pub channels: Vec<SensorChannel>,
+}
+
+/// Message of the WebSocket stream of envsensord
+pub enum RemoteMsg {
+    Hello(Hello),
+    Msg(AppMsg),
+}
+
+/// Parse one text message of the stream
+pub fn parse(text: &str) -> Result<RemoteMsg> {
+    let value: Value = serde_json::from_str(text)?;
+
+    if value["type"] == "Hello" {
+        let hello = Hello::deserialize(&value["data"]).context("Invalid Hello message")?;
+        return Ok(RemoteMsg::Hello(hello));
+    }
+    Ok(RemoteMsg::Msg(AppMsg::deserialize(value)?))
+}
+
+/// Forward a text message, false once the receiver is gone
+fn forward(tx: &Sender<RemoteMsg>, text: &str) -> bool {
+    match parse(text) {
+        Ok(msg) => tx.send(msg).is_ok(),
+        // Messages of a newer envsensord are skipped
+        Err(_) => true,
+    }
+}
+
+fn closed() -> RemoteMsg {
+    RemoteMsg::Msg(AppMsg::Error("Connection to envsensord closed".to_string()))
+}
+
+/// Connect to the WebSocket stream of envsensord at `url`, e.g. "ws://raspberrypi:9001"
+///
+/// The connection is closed once the receiver is dropped, an `AppMsg::Error` is sent when
+/// envsensord closes it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn connect(url: &str) -> Result<Receiver<RemoteMsg>> {
+    use tungstenite::Message;
+
+    let (mut socket, _) =
+        tungstenite::connect(url).with_context(|| format!("Failed to connect to {url}"))?;
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        loop {
+            match socket.read() {
+                Ok(Message::Text(text)) => {
+                    if !forward(&tx, &text) {
+                        let _ = socket.close(None);
+                        return;
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        let _ = tx.send(closed());
+    });
+
+    Ok(rx)
+}
+
+/// Connect to the WebSocket stream of envsensord at `url`, e.g. "ws://raspberrypi:9001"
+///
+/// The connection is closed once the receiver is dropped, an `AppMsg::Error` is sent when
+/// envsensord closes it.
+#[cfg(target_arch = "wasm32")]
+pub fn connect(url: &str) -> Result<Receiver<RemoteMsg>> {
+    use wasm_bindgen::{JsCast, closure::Closure};
+    use web_sys::{CloseEvent, MessageEvent, WebSocket};
+
+    let socket =
+        WebSocket::new(url).map_err(|e| anyhow::anyhow!("Failed to connect to {url}: {e:?}"))?;
+    let (tx, rx) = mpsc::channel();
+
+    let messages = tx.clone();
+    let this = socket.clone();
+    let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+        if let Some(text) = event.data().as_string()
+            && !forward(&messages, &text)
+        {
+            let _ = this.close();
+        }
+    });
+    let on_close = Closure::<dyn FnMut(CloseEvent)>::new(move |_: CloseEvent| {
+        let _ = tx.send(closed());
+    });
+    socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+    // The browser owns the socket, the handlers live as long as the page
+    on_message.forget();
+    on_close.forget();
+
+    Ok(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::SensorType;
+
+    #[test]
+    fn parse_stream() {
+        let hello = r#"{"type":"Hello","data":{"model":"RYDASON","site":null,
+            "channels":[{"sensor_type":"CO","unit":"ppm"}]}}"#;
+        let Ok(RemoteMsg::Hello(hello)) = parse(hello) else {
+            panic!("not a Hello");
+        };
+        assert_eq!(hello.model, SensorModel::RYDASON);
+        assert_eq!(hello.channels[0].sensor_type, SensorType::CO);
+
+        let sample = r#"{"type":"Sample","data":{"timestamp":"2025-01-02T03:04:05+01:00",
+            "data":[{"ty":"CO","value":1.5,"unit":"ppm"}]}}"#;
+        let Ok(RemoteMsg::Msg(AppMsg::Sample(sample))) = parse(sample) else {
+            panic!("not a Sample");
+        };
+        assert_eq!(sample.data[0].value, 1.5);
+
+        assert!(parse(r#"{"type":"Unknown"}"#).is_err());
+    }
+}
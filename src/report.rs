@@ -0,0 +1,277 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, Local, NaiveDate};
+
+use crate::alert::{Breach, Threshold, threshold_for, with_units};
+use crate::aqi::{Category, aqi};
+use crate::chart::chart_svg;
+use crate::sensor::{SampleData, SensorChannel};
+use crate::session::Session;
+use crate::stats::ChannelStats;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; } \
+    table { border-collapse: collapse; margin-bottom: 1em; } \
+    th, td { border: 1px solid #ccc; padding: 4px 10px; text-align: right; } \
+    th:first-child, td:first-child { text-align: left; } \
+    svg { max-width: 100%; height: auto; }";
+
+/// Time one channel spent beyond one of its limits
+struct Exceedance {
+    channel: usize,
+    breach: Breach,
+    limit: Option<f32>,
+    start: DateTime<Local>,
+    /// First sample back within the limit, the last one of the session while still beyond it
+    end: DateTime<Local>,
+    /// Furthest value from the limit
+    peak: f32,
+}
+
+/// Runs of samples beyond the limits of their channel
+fn exceedances(
+    channels: &[SensorChannel],
+    samples: &[&SampleData],
+    thresholds: &[Threshold],
+) -> Vec<Exceedance> {
+    let thresholds = with_units(thresholds, channels);
+    let mut exceedances = Vec::new();
+    for (idx, ch) in channels.iter().enumerate() {
+        let Some(threshold) = threshold_for(&thresholds, ch.sensor_type, ch.unit) else {
+            continue;
+        };
+
+        let mut open: Option<Exceedance> = None;
+        for s in samples {
+            let Some(d) = s.data.get(idx) else {
+                continue;
+            };
+            let breach = threshold.check_from(d.value, open.as_ref().map(|e| e.breach));
+            if let Some(e) = &mut open
+                && Some(e.breach) == breach
+            {
+                e.end = s.timestamp;
+                e.peak = match e.breach {
+                    Breach::Above => e.peak.max(d.value),
+                    Breach::Below => e.peak.min(d.value),
+                };
+                continue;
+            }
+
+            exceedances.extend(open.take().map(|e| Exceedance {
+                end: s.timestamp,
+                ..e
+            }));
+            open = breach.map(|breach| Exceedance {
+                channel: idx,
+                breach,
+                limit: threshold.limit(breach),
+                start: s.timestamp,
+                end: s.timestamp,
+                peak: d.value,
+            });
+        }
+        exceedances.extend(open);
+    }
+
+    exceedances.sort_by_key(|e| e.start);
+    exceedances
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// "<log stem>_report.html" next to `log`
+pub fn report_path(log: &Path) -> PathBuf {
+    let stem = log.file_stem().unwrap_or_default().to_string_lossy();
+    log.with_file_name(format!("{stem}_report.html"))
+}
+
+/// HTML summary of a session, or of one `day` of it: statistics per channel, the air quality,
+/// a chart and the time beyond the `thresholds`
+pub fn render_report(
+    session: &Session,
+    title: &str,
+    day: Option<NaiveDate>,
+    thresholds: &[Threshold],
+) -> Result<String> {
+    let samples = session
+        .samples
+        .iter()
+        .filter(|s| day.is_none_or(|day| s.timestamp.date_naive() == day))
+        .collect::<Vec<_>>();
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        bail!("No samples to report");
+    };
+    let channels = &session.channels;
+    let heading = escape(title);
+
+    let mut html = String::new();
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{heading}</title>\n\
+         <style>{STYLE}</style>\n</head>\n<body>\n<h1>{heading}</h1>"
+    )?;
+    writeln!(
+        html,
+        "<p>{} to {}, {} samples</p>",
+        first.timestamp.format("%Y-%m-%d %H:%M:%S"),
+        last.timestamp.format("%Y-%m-%d %H:%M:%S"),
+        samples.len()
+    )?;
+
+    writeln!(html, "<h2>Statistics</h2>\n<table>")?;
+    writeln!(
+        html,
+        "<tr><th>Channel</th><th>Min</th><th>Mean</th><th>Max</th><th>Std dev</th>\
+         <th>Samples</th></tr>"
+    )?;
+    for idx in 0..channels.len() {
+        let values = samples
+            .iter()
+            .filter_map(|s| s.data.get(idx).map(|d| d.value));
+        let Some(stats) = ChannelStats::from_values(values) else {
+            continue;
+        };
+        writeln!(
+            html,
+            "<tr><td>{}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td>\
+             <td>{}</td></tr>",
+            escape(&session.channel_name(idx)),
+            stats.min,
+            stats.mean,
+            stats.max,
+            stats.stddev,
+            stats.count
+        )?;
+    }
+    writeln!(html, "</table>")?;
+
+    // Instantaneous index of every sample
+    let indices = samples
+        .iter()
+        .filter_map(|s| Some((aqi(&s.data)?, s.timestamp)))
+        .collect::<Vec<_>>();
+    if let Some((worst, at)) = indices.iter().max_by_key(|(aqi, _)| aqi.index) {
+        writeln!(html, "<h2>Air quality</h2>")?;
+        writeln!(
+            html,
+            "<p>Highest AQI {} ({}) from {} at {}</p>\n<table>",
+            worst.index,
+            worst.category.label(),
+            worst.pollutant.as_ref(),
+            at.format("%H:%M:%S")
+        )?;
+        writeln!(html, "<tr><th>Category</th><th>Share of samples</th></tr>")?;
+        for category in Category::ALL {
+            let count = indices
+                .iter()
+                .filter(|(a, _)| a.category == category)
+                .count();
+            let [r, g, b] = category.rgb();
+            writeln!(
+                html,
+                "<tr><td style=\"border-left: 8px solid rgb({r},{g},{b})\">{}</td>\
+                 <td>{:.1} %</td></tr>",
+                category.label(),
+                count as f32 / indices.len() as f32 * 100.0
+            )?;
+        }
+        writeln!(html, "</table>")?;
+    }
+
+    writeln!(html, "<h2>Chart</h2>")?;
+    html.push_str(&chart_svg(title, channels, samples.iter().copied())?);
+
+    writeln!(html, "\n<h2>Exceedances</h2>")?;
+    let exceedances = exceedances(channels, &samples, thresholds);
+    if exceedances.is_empty() {
+        writeln!(html, "<p>No limit was exceeded</p>")?;
+    } else {
+        writeln!(html, "<table>")?;
+        writeln!(
+            html,
+            "<tr><th>Start</th><th>Channel</th><th>Side</th><th>Limit</th><th>Peak</th>\
+             <th>Duration (s)</th></tr>"
+        )?;
+        for e in exceedances {
+            let side = match e.breach {
+                Breach::Above => "Above",
+                Breach::Below => "Below",
+            };
+            writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{side}</td><td>{}</td><td>{:.2}</td>\
+                 <td>{}</td></tr>",
+                e.start.format("%Y-%m-%d %H:%M:%S"),
+                escape(&session.channel_name(e.channel)),
+                e.limit.map(|l| l.to_string()).unwrap_or_default(),
+                e.peak,
+                (e.end - e.start).num_seconds()
+            )?;
+        }
+        writeln!(html, "</table>")?;
+    }
+
+    writeln!(html, "</body>\n</html>")?;
+
+    Ok(html)
+}
+
+/// Write the report of the session logged at `log` next to it, returns its path
+pub fn write_report(
+    log: &Path,
+    day: Option<NaiveDate>,
+    thresholds: &[Threshold],
+) -> Result<PathBuf> {
+    let session = Session::load_csv(log)?;
+    let title = match day {
+        Some(day) => format!("Sensor report {day}"),
+        None => format!(
+            "Sensor report {}",
+            log.file_stem().unwrap_or_default().to_string_lossy()
+        ),
+    };
+    let path = report_path(log);
+    std::fs::write(&path, render_report(&session, &title, day, thresholds)?)?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::{SensorType, Unit};
+
+    #[test]
+    fn report_sections() {
+        let start = Local::now();
+        let samples = [10.0, 60.0, 70.0, 40.0]
+            .into_iter()
+            .enumerate()
+            .map(|(n, value)| {
+                SampleData::single(start, n as i64 * 10, SensorType::CO, value, Unit::PPM)
+            })
+            .collect();
+        let session = Session {
+            channels: vec![SensorChannel::new(SensorType::CO, Unit::PPM)],
+            samples,
+            markers: Vec::new(),
+        };
+        let thresholds = [Threshold {
+            high: Some(50.0),
+            ..Threshold::new(SensorType::CO)
+        }];
+
+        let html = render_report(&session, "Test <report>", None, &thresholds).unwrap();
+        assert!(html.contains("<h1>Test &lt;report&gt;</h1>"));
+        assert!(html.contains("<td>CO(ppm)</td><td>10.00</td><td>45.00</td><td>70.00</td>"));
+        assert!(html.contains("Highest AQI 500 (Hazardous) from CO"));
+        assert!(html.contains("<td>Above</td><td>50</td><td>70.00</td><td>20</td>"));
+        assert!(html.contains("<svg"));
+    }
+}
@@ -1,4 +1,5 @@
-use std::{io::Cursor, time::Duration};
+use std::io::{Cursor, Read, Write};
+use std::{sync::mpsc::Sender, time::Duration};
 
 use anyhow::{Result, anyhow};
 use binrw::BinRead;
@@ -6,9 +7,13 @@ use binrw::BinWrite;
 use binrw::binwrite;
 use crc::Crc;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
-use serialport::SerialPort;
 
-use crate::sensor::{SensorChannel, SensorData, SensorDriver, SensorModel, SensorType, Unit};
+use crate::sensor::{
+    SensorChannel, SensorData, SensorDriver, SensorInfo, SensorModel, SensorType, SerialSettings,
+    Unit,
+};
+use crate::traffic::{Frame, Tap};
+use crate::transport;
 
 const CRC_16_MODBUS: Crc<u16> = Crc::<u16>::new(&crc::CRC_16_MODBUS);
 
@@ -96,13 +101,13 @@ struct QueryRsp {
 }
 
 pub struct Rydason {
-    dev: Box<dyn SerialPort>,
+    dev: Tap,
     addr: u8,
     scale: u32,
     channels: Vec<SensorChannel>,
 }
 
-fn query(port: &mut Box<dyn SerialPort>, req: &QueryReq, len: usize) -> Result<QueryRsp> {
+fn query(port: &mut Tap, req: &QueryReq, len: usize) -> Result<Vec<u8>> {
     let mut buf = Cursor::new(Vec::new());
     req.write(&mut buf)?;
     port.write_all(buf.get_ref())?;
@@ -110,10 +115,45 @@ fn query(port: &mut Box<dyn SerialPort>, req: &QueryReq, len: usize) -> Result<Q
     let mut buf = vec![0u8; len];
     port.read_exact(&mut buf)?;
 
-    Ok(QueryRsp::read(&mut Cursor::new(buf))?)
+    Ok(buf)
 }
 
-fn read_type(port: &mut Box<dyn SerialPort>, addr: u8) -> Result<SensorType> {
+/// Gas of a sensor type register reply
+pub(crate) fn decode_type(reply: &[u8]) -> Result<SensorType> {
+    let rsp = QueryRsp::read(&mut Cursor::new(reply))?;
+
+    Ok(SensorType::from(RydasonType::try_from(
+        rsp.value.as_u16()?,
+    )?))
+}
+
+/// Unit of a unit register reply
+pub(crate) fn decode_unit(reply: &[u8]) -> Result<Unit> {
+    let rsp = QueryRsp::read(&mut Cursor::new(reply))?;
+
+    Ok(match RydasonUnit::try_from(rsp.value.as_u16()?)? {
+        RydasonUnit::PPB => Unit::PPB,
+        RydasonUnit::PPM => Unit::PPM,
+    })
+}
+
+/// Divisor of the readings from a decimals register reply
+pub(crate) fn decode_scale(reply: &[u8]) -> Result<u32> {
+    let decimals = QueryRsp::read(&mut Cursor::new(reply))?.value.as_u16()?;
+
+    10_u32
+        .checked_pow(decimals as u32)
+        .ok_or_else(|| anyhow!("Invalid number of decimals {decimals}"))
+}
+
+/// Concentration of a measured value reply
+pub(crate) fn decode_measured(reply: &[u8], scale: u32) -> Result<f32> {
+    let rsp = QueryRsp::read(&mut Cursor::new(reply))?;
+
+    Ok(rsp.value.as_u32()? as f32 / scale as f32)
+}
+
+fn read_type(port: &mut Tap, addr: u8) -> Result<SensorType> {
     let req = QueryReq {
         addr,
         func: 0x03,
@@ -121,14 +161,10 @@ fn read_type(port: &mut Box<dyn SerialPort>, addr: u8) -> Result<SensorType> {
         value: 0x0001,
     };
 
-    let rsp = query(port, &req, 7)?;
-
-    Ok(SensorType::from(RydasonType::try_from(
-        rsp.value.as_u16()?,
-    )?))
+    decode_type(&query(port, &req, 7)?)
 }
 
-fn read_unit(port: &mut Box<dyn SerialPort>, addr: u8) -> Result<RydasonUnit> {
+fn read_unit(port: &mut Tap, addr: u8) -> Result<Unit> {
     let req = QueryReq {
         addr,
         func: 0x03,
@@ -136,12 +172,10 @@ fn read_unit(port: &mut Box<dyn SerialPort>, addr: u8) -> Result<RydasonUnit> {
         value: 0x0001,
     };
 
-    let rsp = query(port, &req, 7)?;
-
-    Ok(RydasonUnit::try_from(rsp.value.as_u16()?)?)
+    decode_unit(&query(port, &req, 7)?)
 }
 
-fn read_scale(port: &mut Box<dyn SerialPort>, addr: u8) -> Result<u32> {
+fn read_scale(port: &mut Tap, addr: u8) -> Result<u32> {
     let req = QueryReq {
         addr,
         func: 0x03,
@@ -149,36 +183,35 @@ fn read_scale(port: &mut Box<dyn SerialPort>, addr: u8) -> Result<u32> {
         value: 0x0001,
     };
 
-    let rsp = query(port, &req, 7)?;
-
-    Ok(10_u32.pow(rsp.value.as_u16()? as u32))
+    decode_scale(&query(port, &req, 7)?)
 }
 
 impl Rydason {
-    pub fn new(port: &str, addr: u8) -> Result<Self> {
+    pub fn new(
+        port: &str,
+        addr: u8,
+        serial: &SerialSettings,
+        capture: Option<Sender<Frame>>,
+    ) -> Result<Self> {
         let builder = serialport::new(port, 9600)
             .stop_bits(serialport::StopBits::One)
             .data_bits(serialport::DataBits::Eight)
             .parity(serialport::Parity::Even)
             .timeout(Duration::from_secs(5));
+        let builder = serial.apply(builder);
         println!("{:?}", &builder);
 
-        let mut port = builder.open().inspect_err(|e| {
-            eprintln!("Failed to open \"{}\". Error: {}", port, e);
-        })?;
+        let port = transport::open(port, builder)
+            .map_err(|e| anyhow!("Failed to open \"{port}\": {e}"))?;
+        let mut port = Tap::new(port, capture);
 
         let sensor_type = read_type(&mut port, addr)?;
 
-        let sensor_unit = read_unit(&mut port, addr)?;
+        let unit = read_unit(&mut port, addr)?;
 
         let scale = read_scale(&mut port, addr)?;
 
         // Build channel metadata
-        let unit = match sensor_unit {
-            RydasonUnit::PPB => Unit::PPB,
-            RydasonUnit::PPM => Unit::PPM,
-        };
-
         let channels = vec![SensorChannel::new(sensor_type, unit)];
 
         Ok(Rydason {
@@ -197,32 +230,44 @@ impl Rydason {
             value: 0x0002,
         };
 
-        let rsp = query(&mut self.dev, &req, 9)?;
-
-        Ok(rsp.value.as_u32()? as f32 / self.scale as f32)
+        decode_measured(&query(&mut self.dev, &req, 9)?, self.scale)
     }
 }
 
 impl SensorDriver for Rydason {
-    fn new(port: &str) -> Result<Self> {
-        Rydason::new(port, 1) // Default address: 1
+    fn new(port: &str, serial: &SerialSettings, capture: Option<Sender<Frame>>) -> Result<Self> {
+        // Default address: 1
+        Rydason::new(port, serial.modbus_address.unwrap_or(1), serial, capture)
     }
 
     fn get_metadata(&self) -> &[SensorChannel] {
         &self.channels
     }
 
+    fn info(&self) -> SensorInfo {
+        SensorInfo {
+            sensor_type: Some(self.channels[0].sensor_type),
+            scale: Some(self.scale),
+            ..Default::default()
+        }
+    }
+
+    /// The electrochemical cell settles within about a minute of powering up
+    fn warm_up(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
     fn read_data(&mut self) -> Result<Vec<SensorData>> {
         let value = self.read_measured_value()?;
 
         // Rydason needs polling delay
         std::thread::sleep(std::time::Duration::from_secs(1));
 
-        Ok(vec![SensorData {
-            ty: self.channels[0].sensor_type,
+        Ok(vec![SensorData::new(
+            self.channels[0].sensor_type,
             value,
-            unit: self.channels[0].unit,
-        }])
+            self.channels[0].unit,
+        )])
     }
 
     fn model() -> SensorModel {
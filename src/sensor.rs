@@ -1,70 +1,277 @@
-use std::io::Write;
 use std::{
-    fs::File,
+    fmt,
+    path::PathBuf,
+    str::FromStr,
     sync::{
-        Arc,
+        Arc, Mutex,
         atomic::{AtomicBool, Ordering},
+        mpsc::{RecvTimeoutError, Sender},
     },
-    thread,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    sync::mpsc::{self, Receiver},
+    time::Instant,
 };
 
 use anyhow::Result;
 use bus::{Bus, BusReader};
 use chrono::DateTime;
 use chrono::Local;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "serial")]
+use serialport::{Parity, SerialPortBuilder, StopBits};
 use strum::{AsRefStr, IntoEnumIterator};
-use strum_macros::EnumIter;
+use strum_macros::{EnumIter, EnumString};
+
+use crate::aggregate::{Aggregate, SummaryCsv};
+use crate::alert::{Alert, EventsCsv};
+use crate::anomaly::Anomaly;
+use crate::calibration::device_id;
+use crate::config::Config;
+use crate::gap::GapDetector;
+use crate::logger::{CsvLogger, LogConfig};
+use crate::session::{Marker, SessionMeta};
+use crate::traffic::Frame;
+// The sensor thread and its sinks, local sensors aren't read in the browser
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    aggregate::Aggregator, alert::AlertMonitor, anomaly::AnomalyMonitor, convert::convert,
+    drift::DriftMonitor, metrics::spawn_metrics_thread, modbus_tcp::spawn_modbus_tcp_thread,
+    mqtt::spawn_mqtt_thread, process::Pipeline, sensor_community::spawn_sensor_community_thread,
+    simulator::Simulator, traffic::coalesce, webhook::spawn_webhook_thread, ws::spawn_ws_thread,
+};
+#[cfg(feature = "serial")]
+use crate::{nextpm::NextPM, rydason::Rydason, tb600b_c::TB600BC};
+
+/// Particle diameter range of a count channel, in µm
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SizeBin {
+    pub from: f32,
+    pub to: f32,
+}
+
+impl fmt::Display for SizeBin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.from, self.to)
+    }
+}
+
+impl FromStr for SizeBin {
+    type Err = anyhow::Error;
 
-use crate::nextpm::NextPM;
-use crate::rydason::Rydason;
-use crate::tb600b_c::TB600BC;
+    fn from_str(s: &str) -> Result<Self> {
+        let (from, to) = s
+            .split_once('-')
+            .ok_or_else(|| anyhow::anyhow!("Invalid size bin \"{s}\""))?;
+
+        Ok(Self {
+            from: from.parse()?,
+            to: to.parse()?,
+        })
+    }
+}
 
 /// Metadata for a single sensor channel (type and unit)
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 pub struct SensorChannel {
     pub sensor_type: SensorType,
     pub unit: Unit,
+    /// Unit of the unprocessed reading, set when the values are converted or filtered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_unit: Option<Unit>,
+    /// Particle sizes counted, only set on `SensorType::ParticleCount` channels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bin: Option<SizeBin>,
 }
 
+/// Column and label prefix of particle count channels, "PN0.3-1" counts 0.3 to 1 µm
+pub const COUNT_PREFIX: &str = "PN";
+
 impl SensorChannel {
     pub fn new(sensor_type: SensorType, unit: Unit) -> Self {
-        Self { sensor_type, unit }
+        Self {
+            sensor_type,
+            unit,
+            raw_unit: None,
+            bin: None,
+        }
+    }
+
+    /// Number concentration of particles between `from` and `to` µm, see
+    /// `convert::mass_from_counts` for the mass they make up
+    pub fn count(from: f32, to: f32) -> Self {
+        Self {
+            bin: Some(SizeBin { from, to }),
+            ..Self::new(SensorType::ParticleCount, Unit::PerCm3)
+        }
+    }
+
+    /// Type with the size range of count channels, e.g. "PM2_5" or "PN0.3-1"
+    pub fn name(&self) -> String {
+        match self.bin {
+            Some(bin) => format!("{COUNT_PREFIX}{bin}"),
+            None => self.sensor_type.as_ref().to_string(),
+        }
+    }
+
+    /// Identifier usable in topics, metric names and URLs, e.g. "pm2_5_ugperm3"
+    pub fn id(&self) -> String {
+        let name = match self.bin {
+            Some(bin) => format!("{COUNT_PREFIX}{bin}").replace(['.', '-'], "_"),
+            None => format!("{:?}", self.sensor_type),
+        };
+        format!("{name}_{:?}", self.unit).to_lowercase()
+    }
+}
+
+/// Name of the channel at `idx` that stays the same across units, a number tells channels of
+/// the same type apart, e.g. "CO 2"
+pub fn channel_key(channels: &[SensorChannel], idx: usize) -> String {
+    let ty = channels[idx].sensor_type;
+    match channels[..idx]
+        .iter()
+        .filter(|c| c.sensor_type == ty)
+        .count()
+    {
+        0 => ty.as_ref().to_string(),
+        n => format!("{} {}", ty.as_ref(), n + 1),
+    }
+}
+
+/// Device details reported by a driver, fields the sensor can't tell stay `None`
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SensorInfo {
+    /// Gas a configurable cell is set up for
+    pub sensor_type: Option<SensorType>,
+    pub firmware: Option<String>,
+    pub serial_number: Option<String>,
+    /// Full scale of the measuring range, in the unit of the first channel
+    pub range: Option<f32>,
+    /// Raw readings are divided by this
+    pub scale: Option<u32>,
+}
+
+impl SensorInfo {
+    /// Decimal places the readings resolve, from the scale
+    pub fn decimals(&self) -> Option<u32> {
+        self.scale.map(|scale| scale.max(1).ilog10())
+    }
+}
+
+/// Serial line settings overriding a driver's defaults, `None` keeps the default
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SerialSettings {
+    pub baud_rate: Option<u32>,
+    #[cfg(feature = "serial")]
+    pub parity: Option<Parity>,
+    #[cfg(feature = "serial")]
+    pub stop_bits: Option<StopBits>,
+    pub timeout: Option<Duration>,
+    /// Slave address of Modbus RTU sensors
+    pub modbus_address: Option<u8>,
+}
+
+#[cfg(feature = "serial")]
+impl SerialSettings {
+    pub fn apply(&self, mut builder: SerialPortBuilder) -> SerialPortBuilder {
+        if let Some(baud_rate) = self.baud_rate {
+            builder = builder.baud_rate(baud_rate);
+        }
+        if let Some(parity) = self.parity {
+            builder = builder.parity(parity);
+        }
+        if let Some(stop_bits) = self.stop_bits {
+            builder = builder.stop_bits(stop_bits);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        builder
     }
 }
 
 /// Trait that all sensor drivers must implement
 pub trait SensorDriver: Send + 'static {
     /// Create a new sensor instance
-    fn new(port: &str) -> Result<Self>
+    ///
+    /// Everything sent and received is reported to `capture` when given.
+    fn new(port: &str, serial: &SerialSettings, capture: Option<Sender<Frame>>) -> Result<Self>
     where
         Self: Sized;
 
     /// Get sensor metadata (channels with types and units)
     fn get_metadata(&self) -> &[SensorChannel];
 
+    /// Get device details such as firmware version and measuring range
+    fn info(&self) -> SensorInfo {
+        SensorInfo::default()
+    }
+
     /// Perform sensor-specific initialization
     fn initialize(&mut self) -> Result<()> {
         Ok(()) // Default: no initialization needed
     }
 
+    /// Problems found during initialization that don't stop the measurement
+    fn warnings(&self) -> Vec<String> {
+        Vec::new()
+    }
+
     /// Read sensor data
     fn read_data(&mut self) -> Result<Vec<SensorData>>;
 
+    /// Time after the start during which readings are flagged `Quality::WarmUp`
+    fn warm_up(&self) -> Duration {
+        Duration::ZERO
+    }
+
     /// Get the sensor model this driver handles
     fn model() -> SensorModel
     where
         Self: Sized;
 }
 
-#[derive(AsRefStr, Clone, Copy, Debug, EnumIter)]
+#[derive(
+    AsRefStr,
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
 pub enum SensorType {
     CO,
     NO2,
     PM1,
     PM2_5,
     PM10,
+    Temperature,
+    Humidity,
+    /// Derived from temperature and humidity by `Pipeline`
+    DewPoint,
+    /// Derived from temperature and humidity by `Pipeline`
+    AbsoluteHumidity,
+    /// PM1 corrected for humidity growth by `Pipeline`
+    PM1Dry,
+    /// PM2.5 corrected for humidity growth by `Pipeline`
+    PM2_5Dry,
+    /// PM10 corrected for humidity growth by `Pipeline`
+    PM10Dry,
+    /// Number concentration of one particle size range, see `SensorChannel::bin`
+    ParticleCount,
 }
 
-#[derive(Clone, Copy, Debug, AsRefStr)]
+#[derive(Clone, Copy, Debug, AsRefStr, EnumIter, EnumString, Eq, Ord, PartialEq, PartialOrd)]
 pub enum Unit {
     #[strum(serialize = "ppm")]
     PPM,
@@ -78,14 +285,40 @@ pub enum Unit {
     PercentVol,
     #[strum(serialize = "10g/m3")]
     TenGPerM3,
+    #[strum(serialize = "g/m3")]
+    GPerM3,
+    #[strum(serialize = "°C")]
+    Celsius,
+    /// Relative humidity
+    #[strum(serialize = "%RH")]
+    PercentRH,
+    /// Particles per cm³
+    #[strum(serialize = "#/cm3")]
+    PerCm3,
+}
+
+impl Serialize for Unit {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_ref())
+    }
+}
+
+impl<'de> Deserialize<'de> for Unit {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let unit = String::deserialize(deserializer)?;
+        unit.parse()
+            .map_err(|_| serde::de::Error::custom(format!("unknown unit {unit}")))
+    }
 }
 
 #[allow(non_camel_case_types)]
-#[derive(AsRefStr, Clone, Copy, EnumIter)]
+#[derive(AsRefStr, Clone, Copy, Debug, Deserialize, EnumIter, EnumString, PartialEq, Serialize)]
 pub enum SensorModel {
     EC_TB600BC,
     RYDASON,
     TERA_NextPM,
+    /// No hardware, see `simulator::Simulator`
+    SIMULATOR,
 }
 
 impl SensorModel {
@@ -97,89 +330,251 @@ impl SensorModel {
 pub struct Sensor {
     model: SensorModel,
     port: String,
+    config: Config,
     stop_flag: Arc<AtomicBool>,
     rx: BusReader<AppMsg>,
+    shared: Shared,
+}
+
+/// File the log thread currently writes to, `None` while not logging
+pub type LogPath = Arc<Mutex<Option<PathBuf>>>;
+
+/// Details the driver read from the device, `None` until it is initialized
+pub type DeviceInfo = Arc<Mutex<Option<SensorInfo>>>;
+
+/// Markers set since the sensor thread last broadcast them
+pub type PendingMarkers = Arc<Mutex<Vec<Marker>>>;
+
+/// Log thread of the running session, `None` while not logging
+pub type LogThread = Arc<Mutex<Option<JoinHandle<Result<()>>>>>;
+
+/// What a `Sensor` shares with its threads, kept across restarts
+#[derive(Clone)]
+pub struct Shared {
+    log_path: LogPath,
+    info: DeviceInfo,
+    markers: PendingMarkers,
+    log_thread: LogThread,
+    /// Spans restarts, so the time a reconnect took shows as a gap
+    gaps: Arc<Mutex<GapDetector>>,
 }
 
 #[allow(dead_code)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SensorData {
     pub ty: SensorType,
     pub value: f32,
     pub unit: Unit,
+    /// Value as read from the sensor, set when processing changed `value`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<f32>,
+    /// Particle sizes counted, see `SensorChannel::bin`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bin: Option<SizeBin>,
+    #[serde(default, skip_serializing_if = "Quality::is_valid")]
+    pub quality: Quality,
 }
 
-#[derive(Clone, Debug)]
+impl SensorData {
+    /// A valid reading as it comes from the sensor, unprocessed and not binned
+    pub fn new(ty: SensorType, value: f32, unit: Unit) -> Self {
+        Self {
+            ty,
+            value,
+            unit,
+            raw: None,
+            bin: None,
+            quality: Quality::Valid,
+        }
+    }
+
+    /// Channel the value belongs to, without processing details
+    pub fn channel(&self) -> SensorChannel {
+        SensorChannel {
+            bin: self.bin,
+            ..SensorChannel::new(self.ty, self.unit)
+        }
+    }
+}
+
+/// How far a value can be trusted, set by the driver and the sensor thread
+#[derive(AsRefStr, Clone, Copy, Debug, Default, Deserialize, EnumString, PartialEq, Serialize)]
+pub enum Quality {
+    #[default]
+    Valid,
+    /// Read while the sensor was still settling after the start
+    WarmUp,
+    /// Flagged as a spike, kept as read
+    Suspect,
+    /// Beyond the measuring range of the sensor
+    OutOfRange,
+    /// Not measured, filled in from the samples around it
+    Interpolated,
+}
+
+impl Quality {
+    pub fn is_valid(&self) -> bool {
+        *self == Quality::Valid
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SampleData {
     pub timestamp: DateTime<Local>,
     pub data: Vec<SensorData>,
 }
 
-#[derive(Clone)]
+impl SampleData {
+    /// A sample of one reading taken `secs` after `start`, for the unit tests
+    #[cfg(test)]
+    pub(crate) fn single(
+        start: DateTime<Local>,
+        secs: i64,
+        ty: SensorType,
+        value: f32,
+        unit: Unit,
+    ) -> Self {
+        Self {
+            timestamp: start + chrono::TimeDelta::seconds(secs),
+            data: vec![SensorData::new(ty, value, unit)],
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "type", content = "data")]
 pub enum AppMsg {
     Status(String),
+    /// The sensor thread stopped on this error
+    Error(String),
     Sample(SampleData),
+    Alert(Alert),
+    /// Reading far from the recent mean of its channel, sent when `Config::anomaly` is set
+    Anomaly(Anomaly),
+    /// Raw serial frame, sent when `Config::capture` is set
+    Traffic(Frame),
+    /// Marker set with `Sensor::mark`
+    Marker(Marker),
+    /// Rolling statistics, sent once a minute when `Config::aggregate` is set
+    Aggregate(Aggregate),
 }
 
 pub fn spawn_log_thread(
-    model: SensorModel,
     flag: Arc<AtomicBool>,
     mut rx: BusReader<AppMsg>,
     channels: &[SensorChannel],
-) {
-    let csv_head = format!(
-        "{},{}",
-        "Timestamp",
-        channels
-            .iter()
-            .map(|ch| format!("{}({})", ch.sensor_type.as_ref(), ch.unit.as_ref()))
-            .collect::<Vec<_>>()
-            .join(",")
-    );
-
-    thread::spawn(move || -> Result<()> {
-        let filename = format!(
-            "{}_{}.csv",
-            chrono::Local::now().format("%Y-%m-%d-%H-%M-%S"),
-            model.as_ref()
-        );
-
-        let mut csv = File::create(filename)?;
-        // Write CSV header
-        writeln!(csv, "{csv_head}")?;
-
+    config: LogConfig,
+    meta: SessionMeta,
+    path: LogPath,
+    status: Sender<AppMsg>,
+) -> Result<JoinHandle<Result<()>>> {
+    let timestamp = config.timestamp.clone();
+    let mut logger =
+        CsvLogger::new(meta.model, channels, config, meta.site.as_ref())?.with_meta(meta)?;
+    *path.lock().unwrap() = Some(logger.path().to_path_buf());
+    let channels = channels.to_vec();
+    // Created with the first aggregate or alert, next to the first part of the log
+    let mut summary: Option<SummaryCsv> = None;
+    let mut events: Option<EventsCsv> = None;
+
+    Ok(thread::spawn(move || -> Result<()> {
         while !flag.load(Ordering::SeqCst) {
-            if let Ok(AppMsg::Sample(sample)) = rx.recv() {
-                writeln!(
-                    csv,
-                    "{},{}",
-                    sample.timestamp.format("%m/%d/%Y %H:%M:%S"),
-                    sample
-                        .data
-                        .iter()
-                        .map(|d| d.value.to_string())
-                        .collect::<Vec<_>>()
-                        .join(",")
-                )?;
-
-                csv.flush()?;
+            for notice in logger.notices() {
+                let _ = status.send(AppMsg::Status(notice));
+            }
+
+            match rx.recv_timeout(Duration::from_millis(500)) {
+                Ok(AppMsg::Sample(sample)) => {
+                    logger.write_sample(&sample)?;
+
+                    // Rotation may have moved on to a new file
+                    let mut current = path.lock().unwrap();
+                    if current.as_deref() != Some(logger.path()) {
+                        *current = Some(logger.path().to_path_buf());
+                    }
+                }
+                Ok(AppMsg::Alert(alert)) => {
+                    // Make sure the samples leading up to an alert are on disk
+                    logger.flush()?;
+                    let events = match &mut events {
+                        Some(events) => events,
+                        None => events.insert(EventsCsv::create(logger.path(), timestamp.clone())?),
+                    };
+                    events.write(&alert)?;
+                }
+                Ok(AppMsg::Marker(marker)) => logger.mark(&marker.label),
+                Ok(AppMsg::Anomaly(anomaly)) => logger.mark(&anomaly.to_string()),
+                Ok(AppMsg::Aggregate(aggregate)) => {
+                    let summary = match &mut summary {
+                        Some(summary) => summary,
+                        None => summary.insert(SummaryCsv::create(
+                            logger.path(),
+                            &channels,
+                            timestamp.clone(),
+                        )?),
+                    };
+                    summary.write(&aggregate)?;
+                }
+                Ok(_) | Err(RecvTimeoutError::Timeout) => logger.flush_if_due()?,
+                // The sensor thread is gone, nothing more to log
+                Err(RecvTimeoutError::Disconnected) => break,
             }
         }
 
-        Ok(())
-    });
+        *path.lock().unwrap() = None;
+
+        // Always persist the tail of the session on stop
+        logger.finish()
+    }))
+}
+
+/// Broadcast the frames captured since the last call
+#[cfg(not(target_arch = "wasm32"))]
+fn broadcast_traffic(bus: &mut Bus<AppMsg>, capture: &Receiver<Frame>) {
+    for frame in coalesce(capture.try_iter()) {
+        bus.broadcast(AppMsg::Traffic(frame));
+    }
+}
+
+/// Broadcast what the sinks reported since the last call
+#[cfg(not(target_arch = "wasm32"))]
+fn broadcast_status(bus: &mut Bus<AppMsg>, status: &Receiver<AppMsg>) {
+    for msg in status.try_iter() {
+        bus.broadcast(msg);
+    }
+}
+
+/// Sets the stop flag when the sensor thread ends, also on an error, so the sinks and the
+/// owner of the `Sensor` see it
+#[cfg(not(target_arch = "wasm32"))]
+struct StopOnExit(Arc<AtomicBool>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for StopOnExit {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 pub fn spawn_sensor_thread<T: SensorDriver>(
     port: String,
     mut bus: Bus<AppMsg>,
     flag: Arc<AtomicBool>,
+    config: Config,
+    shared: Shared,
 ) {
     thread::spawn(move || -> Result<()> {
+        let _stop = StopOnExit(flag.clone());
         let model = T::model();
-
-        let mut sensor = T::new(&port).inspect_err(|e| {
-            bus.broadcast(AppMsg::Status(format!(
+        let (capture_tx, capture) = mpsc::channel();
+        // The sinks only read the bus, they report their problems through here
+        let (status_tx, status) = mpsc::channel();
+
+        let sensor = T::new(&port, &config.serial, config.capture.then_some(capture_tx));
+        broadcast_traffic(&mut bus, &capture);
+        let mut sensor = sensor.inspect_err(|e| {
+            bus.broadcast(AppMsg::Error(format!(
                 "Failed to create {} sensor: {e}",
                 model.as_ref()
             )));
@@ -187,27 +582,245 @@ pub fn spawn_sensor_thread<T: SensorDriver>(
 
         bus.broadcast(AppMsg::Status(format!("{} init", model.as_ref())));
 
-        sensor.initialize().inspect_err(|e| {
-            bus.broadcast(AppMsg::Status(format!(
+        let initialized = sensor.initialize();
+        broadcast_traffic(&mut bus, &capture);
+        initialized.inspect_err(|e| {
+            bus.broadcast(AppMsg::Error(format!(
                 "Failed to initialize {}: {e}",
                 model.as_ref()
             )));
         })?;
+        for warning in sensor.warnings() {
+            bus.broadcast(AppMsg::Status(warning));
+        }
+
+        *shared.info.lock().unwrap() = Some(sensor.info());
+
+        // Calibrations of other devices, e.g. the reference of a calibration, don't apply
+        let device = device_id(&port, Some(&sensor.info()));
+        let mut process = config.process.clone();
+        process
+            .calibration
+            .retain(|c| c.model == model && c.device == device);
+        let mut pipeline = Pipeline::new(&process, sensor.get_metadata());
+        let channels = pipeline.channels().to_vec();
+        let metadata = &channels[..];
+        let mut alerts = AlertMonitor::new(config.thresholds.clone());
+        let mut drift = DriftMonitor::new(config.drift.clone());
+        let mut anomalies = config.anomaly.map(AnomalyMonitor::new);
+        let mut aggregator = config.aggregate.then(Aggregator::new);
+        // Previous sample, the start of the interpolation over a gap
+        let mut previous: Option<SampleData> = None;
+
+        let meta = SessionMeta {
+            notes: config.notes.clone(),
+            site: config.site.clone(),
+            ..SessionMeta::new(model, &port, metadata, sensor.info())
+        };
+
+        if config.log.enabled {
+            let log_thread = spawn_log_thread(
+                flag.clone(),
+                bus.add_rx(),
+                metadata,
+                config.log,
+                meta,
+                shared.log_path.clone(),
+                status_tx.clone(),
+            )
+            .inspect_err(|e| {
+                bus.broadcast(AppMsg::Error(format!("Failed to create log file: {e}")));
+            })?;
+            *shared.log_thread.lock().unwrap() = Some(log_thread);
+        }
+
+        if let Some(mqtt) = config.mqtt {
+            let site = config.site.clone();
+            spawn_mqtt_thread(
+                model,
+                flag.clone(),
+                bus.add_rx(),
+                metadata,
+                mqtt,
+                site,
+                status_tx.clone(),
+            );
+        }
+
+        if let Some(webhook) = config.webhook {
+            let site = config.site.clone();
+            spawn_webhook_thread(
+                model,
+                flag.clone(),
+                bus.add_rx(),
+                metadata,
+                webhook,
+                site,
+                status_tx.clone(),
+            );
+        }
+
+        if let Some(upload) = config.sensor_community {
+            spawn_sensor_community_thread(
+                flag.clone(),
+                bus.add_rx(),
+                metadata,
+                upload,
+                status_tx.clone(),
+            );
+        }
+
+        if let Some(metrics) = config.metrics {
+            spawn_metrics_thread(
+                model,
+                flag.clone(),
+                bus.add_rx(),
+                metadata,
+                metrics,
+                status_tx.clone(),
+            )
+            .inspect_err(|e| {
+                bus.broadcast(AppMsg::Status(format!(
+                    "Failed to start metrics endpoint: {e}"
+                )));
+            })?;
+        }
+
+        if let Some(ws) = config.ws {
+            let site = config.site.clone();
+            spawn_ws_thread(
+                model,
+                flag.clone(),
+                bus.add_rx(),
+                metadata,
+                ws,
+                site,
+                status_tx.clone(),
+            )
+            .inspect_err(|e| {
+                bus.broadcast(AppMsg::Status(format!(
+                    "Failed to start WebSocket server: {e}"
+                )));
+            })?;
+        }
+
+        if let Some(modbus) = config.modbus_tcp {
+            spawn_modbus_tcp_thread(flag.clone(), bus.add_rx(), metadata, modbus).inspect_err(
+                |e| {
+                    bus.broadcast(AppMsg::Status(format!(
+                        "Failed to start Modbus TCP server: {e}"
+                    )));
+                },
+            )?;
+        }
 
-        let metadata = sensor.get_metadata();
+        #[cfg(all(feature = "dbus", target_os = "linux"))]
+        if config.dbus {
+            crate::dbus::spawn_dbus_thread(model, flag.clone(), bus.add_rx(), metadata)
+                .inspect_err(|e| {
+                    bus.broadcast(AppMsg::Status(format!("Failed to register on D-Bus: {e}")));
+                })?;
+        }
 
-        spawn_log_thread(model, flag.clone(), bus.add_rx(), metadata);
+        let deadline = config.duration.map(|d| Instant::now() + d);
+        let warm_until = Instant::now() + sensor.warm_up();
+        // Full scale of each channel, given in the unit of the first one and converted to the
+        // others of the same type
+        let range = sensor.info().range;
+        let read = sensor.get_metadata();
+        let ranges = read
+            .iter()
+            .map(|ch| {
+                let first = read.first().filter(|f| f.sensor_type == ch.sensor_type)?;
+                convert(range?, ch.sensor_type, first.unit, ch.unit)
+            })
+            .collect::<Vec<_>>();
 
         while !flag.load(Ordering::SeqCst) {
-            let data = sensor.read_data().map_err(|e| {
-                bus.broadcast(AppMsg::Status(format!("Failed to read data: {e}")));
+            let started = Instant::now();
+            let data = sensor.read_data();
+            broadcast_traffic(&mut bus, &capture);
+            broadcast_status(&mut bus, &status);
+            let mut data = data.map_err(|e| {
+                bus.broadcast(AppMsg::Error(format!("Failed to read data: {e}")));
                 e
             })?;
+            let warming = Instant::now() < warm_until;
+            for (n, d) in data.iter_mut().enumerate() {
+                if warming {
+                    d.quality = Quality::WarmUp;
+                }
+                if ranges
+                    .get(n)
+                    .copied()
+                    .flatten()
+                    .is_some_and(|range| d.value > range)
+                {
+                    d.quality = Quality::OutOfRange;
+                }
+            }
+            let spikes = pipeline.apply(&mut data);
+            let timestamp = chrono::Local::now();
+
+            // Before the sample, so the log puts them on its row
+            let pending = std::mem::take(&mut *shared.markers.lock().unwrap());
+            let spikes = spikes.iter().map(|ty| Marker {
+                timestamp,
+                label: format!("{} spike", ty.as_ref()),
+            });
+            let (step, gap) = {
+                let mut gaps = shared.gaps.lock().unwrap();
+                (gaps.expected_interval(), gaps.push(timestamp))
+            };
+            let gap_marker = gap.map(|gap| Marker {
+                timestamp,
+                label: gap.to_string(),
+            });
+            for marker in pending.into_iter().chain(spikes).chain(gap_marker) {
+                bus.broadcast(AppMsg::Marker(marker));
+            }
+
+            let sample = SampleData { timestamp, data };
+            for alert in alerts.check(&sample) {
+                bus.broadcast(AppMsg::Alert(alert));
+            }
+            for drift in drift.check(&sample) {
+                bus.broadcast(AppMsg::Status(drift.to_string()));
+            }
+            if let Some(anomalies) = anomalies.as_mut() {
+                for anomaly in anomalies.check(&sample) {
+                    bus.broadcast(AppMsg::Anomaly(anomaly));
+                }
+            }
+            let mut aggregates = Vec::new();
+            if let Some(aggregator) = aggregator.as_mut() {
+                // Bridged for the aggregates only, the samples themselves keep the gap
+                if config.interpolate_gaps
+                    && gap.is_some()
+                    && let (Some(prev), Some(step)) = (&previous, step)
+                {
+                    for filled in crate::gap::interpolate(prev, &sample, step) {
+                        aggregates.extend(aggregator.push(&filled));
+                    }
+                }
+                aggregates.extend(aggregator.push(&sample));
+            }
+            previous = Some(sample.clone());
+            bus.broadcast(AppMsg::Sample(sample));
+            for aggregate in aggregates {
+                bus.broadcast(AppMsg::Aggregate(aggregate));
+            }
 
-            bus.broadcast(AppMsg::Sample(SampleData {
-                timestamp: chrono::Local::now(),
-                data,
-            }));
+            // The sinks see the flag and finish like on a manual stop
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                bus.broadcast(AppMsg::Status("Timed run finished".to_string()));
+                flag.store(true, Ordering::SeqCst);
+                break;
+            }
+
+            if let Some(rest) = config.interval.checked_sub(started.elapsed()) {
+                thread::sleep(rest);
+            }
         }
 
         Ok(())
@@ -215,32 +828,128 @@ pub fn spawn_sensor_thread<T: SensorDriver>(
 }
 
 impl Sensor {
-    pub fn new(model: &SensorModel, port: &str, rx: BusReader<AppMsg>) -> Result<Self> {
+    pub fn new(
+        model: &SensorModel,
+        port: &str,
+        rx: BusReader<AppMsg>,
+        config: Config,
+    ) -> Result<Self> {
+        let interval = chrono::TimeDelta::from_std(config.interval).unwrap_or_default();
         Ok(Sensor {
             model: *model,
             port: port.to_string(),
+            config,
             stop_flag: Arc::new(AtomicBool::new(false)),
             rx,
+            shared: Shared {
+                log_path: LogPath::default(),
+                info: DeviceInfo::default(),
+                markers: PendingMarkers::default(),
+                log_thread: LogThread::default(),
+                gaps: Arc::new(Mutex::new(GapDetector::new(interval))),
+            },
         })
     }
 
+    #[cfg(feature = "serial")]
     pub fn start(&self, bus: Bus<AppMsg>) -> Result<()> {
         let port = self.port.clone();
         let flag = self.stop_flag.clone();
+        let config = self.config.clone();
+        let shared = self.shared.clone();
 
         match self.model {
-            SensorModel::EC_TB600BC => spawn_sensor_thread::<TB600BC>(port, bus, flag),
-            SensorModel::RYDASON => spawn_sensor_thread::<Rydason>(port, bus, flag),
-            SensorModel::TERA_NextPM => spawn_sensor_thread::<NextPM>(port, bus, flag),
+            SensorModel::EC_TB600BC => {
+                spawn_sensor_thread::<TB600BC>(port, bus, flag, config, shared)
+            }
+            SensorModel::RYDASON => spawn_sensor_thread::<Rydason>(port, bus, flag, config, shared),
+            SensorModel::TERA_NextPM => {
+                spawn_sensor_thread::<NextPM>(port, bus, flag, config, shared)
+            }
+            SensorModel::SIMULATOR => {
+                spawn_sensor_thread::<Simulator>(port, bus, flag, config, shared)
+            }
         }
 
         Ok(())
     }
 
+    /// Local sensors other than the simulator need serial port support, a remote envsensord is
+    /// reached through `remote` instead
+    #[cfg(not(feature = "serial"))]
+    pub fn start(&self, bus: Bus<AppMsg>) -> Result<()> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.model == SensorModel::SIMULATOR {
+            spawn_sensor_thread::<Simulator>(
+                self.port.clone(),
+                bus,
+                self.stop_flag.clone(),
+                self.config.clone(),
+                self.shared.clone(),
+            );
+            return Ok(());
+        }
+
+        drop(bus);
+        anyhow::bail!(
+            "Built without serial port support, can't start {} on {}",
+            self.model.as_ref(),
+            self.port
+        )
+    }
+
     pub fn stop(&self) {
         self.stop_flag.store(true, Ordering::SeqCst);
     }
 
+    /// Wait until the log thread has closed the log, call after `stop`
+    pub fn wait_logged(&self) {
+        if let Some(log_thread) = self.shared.log_thread.lock().unwrap().take() {
+            let _ = log_thread.join();
+        }
+    }
+
+    /// Stopped by `stop`, at the end of a timed run or by an error
+    pub fn is_stopped(&self) -> bool {
+        self.stop_flag.load(Ordering::SeqCst)
+    }
+
+    /// Start a stopped sensor again with the same settings, e.g. to retry after an error
+    pub fn restart(&mut self, mut bus: Bus<AppMsg>) -> Result<()> {
+        self.rx = bus.add_rx();
+        // A fresh flag, the old thread may still be winding down
+        self.stop_flag = Arc::new(AtomicBool::new(false));
+        *self.shared.info.lock().unwrap() = None;
+
+        self.start(bus)
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Drop a labeled marker at the current time, it is logged with the next sample
+    pub fn mark(&self, label: &str) {
+        self.shared.markers.lock().unwrap().push(Marker {
+            timestamp: chrono::Local::now(),
+            label: label.to_string(),
+        });
+    }
+
+    /// CSV file the samples are currently logged to
+    pub fn log_path(&self) -> Option<PathBuf> {
+        self.shared.log_path.lock().unwrap().clone()
+    }
+
+    /// Device details, once the driver is initialized
+    pub fn info(&self) -> Option<SensorInfo> {
+        self.shared.info.lock().unwrap().clone()
+    }
+
+    pub fn model(&self) -> SensorModel {
+        self.model
+    }
+
     pub fn try_recv(&mut self) -> Option<AppMsg> {
         if let Ok(s) = self.rx.try_recv() {
             return Some(s);
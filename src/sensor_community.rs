@@ -0,0 +1,191 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{RecvTimeoutError, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use bus::BusReader;
+use serde_json::json;
+use ureq::Agent;
+
+use crate::outbox::Outbox;
+use crate::sensor::{AppMsg, SensorChannel, SensorType, Unit};
+
+/// Sensor.Community (formerly Luftdaten) upload settings
+#[derive(Clone, Debug)]
+pub struct SensorCommunityConfig {
+    /// Node ID registered at devices.sensor.community, e.g. "raspi-0000000012345678"
+    pub node_id: String,
+    pub url: String,
+    /// Readings are averaged over this period, the stock firmware uses 145 s
+    pub interval: Duration,
+}
+
+impl Default for SensorCommunityConfig {
+    fn default() -> Self {
+        Self {
+            node_id: String::new(),
+            url: "https://api.sensor.community/v1/push-sensor-data/".to_string(),
+            interval: Duration::from_secs(145),
+        }
+    }
+}
+
+/// Sensor.Community value type of a channel, only PM in µg/m³ is accepted
+fn value_type(channel: &SensorChannel) -> Option<&'static str> {
+    match (channel.sensor_type, channel.unit) {
+        (SensorType::PM10, Unit::UgPerM3) => Some("P1"),
+        (SensorType::PM2_5, Unit::UgPerM3) => Some("P2"),
+        (SensorType::PM1, Unit::UgPerM3) => Some("P0"),
+        _ => None,
+    }
+}
+
+/// Running mean of each uploaded channel over the current interval
+struct Averages {
+    /// (channel index, value type, sum, count)
+    slots: Vec<(usize, &'static str, f32, u32)>,
+}
+
+impl Averages {
+    fn new(channels: &[SensorChannel]) -> Self {
+        Self {
+            slots: channels
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, ch)| value_type(ch).map(|ty| (idx, ty, 0.0, 0)))
+                .collect(),
+        }
+    }
+
+    fn add(&mut self, values: &[f32]) {
+        for (idx, _, sum, n) in &mut self.slots {
+            // A single NaN would spoil the mean of the whole interval
+            if let Some(v) = values.get(*idx).filter(|v| v.is_finite()) {
+                *sum += v;
+                *n += 1;
+            }
+        }
+    }
+
+    /// Upload body for the elapsed interval, `None` when nothing was measured
+    fn take(&mut self) -> Option<serde_json::Value> {
+        let values = self
+            .slots
+            .iter_mut()
+            .filter(|(_, _, _, n)| *n > 0)
+            .map(|(_, ty, sum, n)| {
+                let mean = *sum / *n as f32;
+                (*sum, *n) = (0.0, 0);
+                json!({ "value_type": ty, "value": format!("{mean:.2}") })
+            })
+            .collect::<Vec<_>>();
+
+        (!values.is_empty()).then(|| {
+            json!({
+                "software_version": format!("envsensor-demo-{}", env!("CARGO_PKG_VERSION")),
+                "sensordatavalues": values,
+            })
+        })
+    }
+}
+
+/// Uploads kept while the API is slow or unreachable, the oldest are dropped first
+const QUEUED_UPLOADS: usize = 10;
+
+/// Push averaged PM readings to the Sensor.Community API every `interval`
+///
+/// Channels other than PM1/PM2.5/PM10 in µg/m³ are ignored.
+pub fn spawn_sensor_community_thread(
+    flag: Arc<AtomicBool>,
+    mut rx: BusReader<AppMsg>,
+    channels: &[SensorChannel],
+    config: SensorCommunityConfig,
+    status: Sender<AppMsg>,
+) {
+    let mut averages = Averages::new(channels);
+    let outbox = Arc::new(Outbox::<serde_json::Value>::new(QUEUED_UPLOADS));
+
+    // Uploading on a worker of its own, a slow API must not stall the bus
+    let uploads = outbox.clone();
+    let worker_config = config.clone();
+    thread::spawn(move || {
+        let agent: Agent = Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(30)))
+            .build()
+            .into();
+
+        while let Some(body) = uploads.pop() {
+            // X-Pin 1 is the particulate matter "pin" of the Sensor.Community protocol
+            let result = agent
+                .post(&worker_config.url)
+                .header("Content-Type", "application/json")
+                .header("X-Pin", "1")
+                .header("X-Sensor", &worker_config.node_id)
+                .send(body.to_string());
+            if let Err(e) = result {
+                let _ = status.send(AppMsg::Status(format!(
+                    "Sensor.Community upload failed: {e}"
+                )));
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        let mut last_upload = Instant::now();
+
+        while !flag.load(Ordering::SeqCst) {
+            match rx.recv_timeout(Duration::from_millis(500)) {
+                Ok(AppMsg::Sample(sample)) => {
+                    averages.add(&sample.data.iter().map(|d| d.value).collect::<Vec<_>>())
+                }
+                Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            if last_upload.elapsed() < config.interval {
+                continue;
+            }
+            last_upload = Instant::now();
+
+            if let Some(body) = averages.take() {
+                outbox.push(body);
+            }
+        }
+
+        // The worker sends what is still queued and ends
+        outbox.close();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn average_pm_values() {
+        let channels = [
+            SensorChannel::new(SensorType::PM1, Unit::UgPerM3),
+            SensorChannel::new(SensorType::PM2_5, Unit::UgPerM3),
+            SensorChannel::new(SensorType::PM10, Unit::UgPerM3),
+            SensorChannel::new(SensorType::CO, Unit::PPM),
+        ];
+        let mut averages = Averages::new(&channels);
+
+        averages.add(&[1.0, 2.0, 3.0, 9.0]);
+        averages.add(&[2.0, 4.0, 6.0, 9.0]);
+        averages.add(&[f32::NAN, f32::NAN, f32::INFINITY, 9.0]);
+
+        let body = averages.take().unwrap();
+        let values = body["sensordatavalues"].as_array().unwrap();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values[1], json!({ "value_type": "P2", "value": "3.00" }));
+        assert_eq!(values[2], json!({ "value_type": "P1", "value": "4.50" }));
+
+        assert!(averages.take().is_none());
+    }
+}
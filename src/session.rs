@@ -0,0 +1,279 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Site;
+use crate::logger::{EVENT_COLUMN, QUALITY_COLUMN, RAW_PREFIX, split_csv_row};
+use crate::sensor::{
+    COUNT_PREFIX, SampleData, SensorChannel, SensorData, SensorInfo, SensorModel, SensorType,
+    SizeBin, Unit,
+};
+
+/// Context of a recording, written as a .meta.json sidecar next to every log file
+#[derive(Clone, Debug, Serialize)]
+pub struct SessionMeta {
+    pub model: SensorModel,
+    pub port: String,
+    pub started: DateTime<Local>,
+    pub channels: Vec<SensorChannel>,
+    pub info: SensorInfo,
+    pub software_version: String,
+    /// Free text entered by the operator
+    pub notes: String,
+    pub site: Option<Site>,
+}
+
+/// "2025-11-25-14-03-07_RYDASON.csv" -> "2025-11-25-14-03-07_RYDASON.meta.json"
+pub fn meta_path(log: &Path) -> PathBuf {
+    let name = log.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let stem = name.split_once('.').map_or(name, |(stem, _)| stem);
+
+    log.with_file_name(format!("{stem}.meta.json"))
+}
+
+impl SessionMeta {
+    pub fn new(
+        model: SensorModel,
+        port: &str,
+        channels: &[SensorChannel],
+        info: SensorInfo,
+    ) -> Self {
+        Self {
+            model,
+            port: port.to_string(),
+            started: Local::now(),
+            channels: channels.to_vec(),
+            info,
+            software_version: env!("CARGO_PKG_VERSION").to_string(),
+            notes: String::new(),
+            site: None,
+        }
+    }
+
+    /// Write the sidecar belonging to the log file `log`
+    pub fn write(&self, log: &Path) -> Result<()> {
+        let file = std::fs::File::create(meta_path(log))?;
+        serde_json::to_writer_pretty(file, self)?;
+
+        Ok(())
+    }
+}
+
+/// A completed recording, loaded back from a CSV log
+pub struct Session {
+    pub channels: Vec<SensorChannel>,
+    pub samples: Vec<SampleData>,
+    pub markers: Vec<Marker>,
+}
+
+/// Labeled point in time set by the operator, e.g. "opened window"
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Marker {
+    pub timestamp: DateTime<Local>,
+    pub label: String,
+}
+
+/// Parse a "CO(ppm)" style column header
+fn parse_channel(column: &str) -> Result<SensorChannel> {
+    let (ty, unit) = column
+        .trim()
+        .strip_suffix(')')
+        .and_then(|c| c.split_once('('))
+        .ok_or_else(|| anyhow!("Invalid column \"{column}\""))?;
+
+    let unit = unit
+        .parse::<Unit>()
+        .with_context(|| format!("Unknown unit \"{unit}\""))?;
+    if let Some(bin) = ty.strip_prefix(COUNT_PREFIX) {
+        let bin = bin.parse::<SizeBin>()?;
+        return Ok(SensorChannel {
+            unit,
+            ..SensorChannel::count(bin.from, bin.to)
+        });
+    }
+
+    Ok(SensorChannel::new(
+        ty.parse::<SensorType>()
+            .with_context(|| format!("Unknown sensor type \"{ty}\""))?,
+        unit,
+    ))
+}
+
+/// Parse a timestamp written with any of the built-in `TimestampFormat`s
+pub fn parse_timestamp(s: &str) -> Result<DateTime<Local>> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(s) {
+        return Ok(ts.with_timezone(&Local));
+    }
+
+    let naive = NaiveDateTime::parse_from_str(s, "%m/%d/%Y %H:%M:%S")
+        .with_context(|| format!("Invalid timestamp \"{s}\""))?;
+
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| anyhow!("Nonexistent local time \"{s}\""))
+}
+
+impl Session {
+    pub fn from_csv(content: &str) -> Result<Self> {
+        let mut lines = content.lines();
+
+        let header = lines.next().ok_or_else(|| anyhow!("Empty CSV file"))?;
+
+        // Column index of every channel, other columns (raw values, site, ...) are skipped
+        let mut columns = Vec::new();
+        let mut channels = Vec::new();
+        let mut events = None;
+        let mut quality = None;
+        for (idx, column) in header.split(',').enumerate().skip(1) {
+            if column.trim() == EVENT_COLUMN {
+                events = Some(idx);
+                continue;
+            }
+            if column.trim() == QUALITY_COLUMN {
+                quality = Some(idx);
+                continue;
+            }
+            if column.starts_with(RAW_PREFIX) || Site::CSV_COLUMNS.contains(&column.trim()) {
+                continue;
+            }
+            columns.push(idx);
+            channels.push(parse_channel(column)?);
+        }
+
+        let mut samples = Vec::new();
+        let mut markers = Vec::new();
+        for (n, line) in lines.enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let fields = split_csv_row(line);
+            let timestamp =
+                parse_timestamp(&fields[0]).with_context(|| format!("Line {}", n + 2))?;
+
+            // "CO=WarmUp;NO2=WarmUp", channels not named are valid
+            let flags = quality
+                .and_then(|idx| fields.get(idx))
+                .map(|cell| {
+                    cell.split(';')
+                        .filter_map(|flag| flag.trim().split_once('='))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+
+            let data = channels
+                .iter()
+                .zip(columns.iter().filter_map(|&idx| fields.get(idx)))
+                .map(|(ch, v)| {
+                    let name = ch.name();
+                    let quality = flags
+                        .iter()
+                        .find(|(channel, _)| *channel == name)
+                        .and_then(|(_, q)| q.parse().ok())
+                        .unwrap_or_default();
+
+                    Ok(SensorData {
+                        bin: ch.bin,
+                        quality,
+                        ..SensorData::new(ch.sensor_type, v.trim().parse()?, ch.unit)
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("Line {}", n + 2))?;
+
+            // Markers set since the previous sample share one cell
+            if let Some(cell) = events.and_then(|idx| fields.get(idx)) {
+                markers.extend(
+                    cell.split(';')
+                        .filter(|l| !l.trim().is_empty())
+                        .map(|label| Marker {
+                            timestamp,
+                            label: label.trim().to_string(),
+                        }),
+                );
+            }
+
+            samples.push(SampleData { timestamp, data });
+        }
+
+        Ok(Session {
+            channels,
+            samples,
+            markers,
+        })
+    }
+
+    /// Load a .csv or a compressed .csv.gz log
+    pub fn load_csv(path: &Path) -> Result<Self> {
+        let mut content = String::new();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        if path.extension().is_some_and(|ext| ext == "gz") {
+            GzDecoder::new(file).read_to_string(&mut content)?;
+        } else {
+            std::io::BufReader::new(file).read_to_string(&mut content)?;
+        }
+
+        Self::from_csv(&content)
+    }
+
+    /// Column label, e.g. "PM2_5(µg/m3)"
+    pub fn channel_name(&self, idx: usize) -> String {
+        let ch = &self.channels[idx];
+        format!("{}({})", ch.name(), ch.unit.as_ref())
+    }
+
+    /// All values of one channel, in sample order
+    pub fn values(&self, idx: usize) -> impl Iterator<Item = f32> + '_ {
+        self.samples
+            .iter()
+            .filter_map(move |s| s.data.get(idx).map(|d| d.value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::Quality;
+
+    #[test]
+    fn load_logged_csv() {
+        let csv = "Timestamp,PM1(µg/m3),PM10(µg/m3),Site,Latitude,Longitude,Quality,Event\n\
+                   11/25/2025 14:03:07,1.5,3,Lab,,,PM10=WarmUp,\n\
+                   2025-11-25T13:03:08Z,1.6,3.1,Lab,,,,\"opened window, \"\"north\"\"\"\n";
+
+        let session = Session::from_csv(csv).unwrap();
+
+        assert_eq!(session.channels.len(), 2);
+        assert_eq!(session.channels[1].sensor_type, SensorType::PM10);
+        assert_eq!(session.channels[1].unit, Unit::UgPerM3);
+        assert_eq!(session.samples.len(), 2);
+        assert_eq!(session.values(0).collect::<Vec<_>>(), vec![1.5, 1.6]);
+        assert_eq!(session.samples[0].data[1].quality, Quality::WarmUp);
+        assert!(session.samples[1].data[1].quality.is_valid());
+        assert_eq!(session.markers.len(), 1);
+        assert_eq!(session.markers[0].label, "opened window, \"north\"");
+    }
+
+    #[test]
+    fn count_columns() {
+        let csv = "Timestamp,PN0.3-2.5(#/cm3)\n11/25/2025 14:03:07,42\n";
+        let session = Session::from_csv(csv).unwrap();
+
+        let ch = session.channels[0];
+        assert_eq!(ch.sensor_type, SensorType::ParticleCount);
+        assert_eq!(ch.bin, Some(SizeBin { from: 0.3, to: 2.5 }));
+        assert_eq!(session.channel_name(0), "PN0.3-2.5(#/cm3)");
+        assert_eq!(ch.id(), "pn0_3_2_5_percm3");
+    }
+
+    #[test]
+    fn sidecar_path() {
+        assert_eq!(
+            meta_path(Path::new("logs/2025-11-25-14-03-07_RYDASON_1.csv")),
+            Path::new("logs/2025-11-25-14-03-07_RYDASON_1.meta.json")
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::alert::Threshold;
+use crate::anomaly::AnomalyConfig;
+use crate::calibration::ChannelCalibration;
+use crate::config::Config;
+use crate::drift::DriftLimit;
+use crate::i18n::Language;
+use crate::logger::TimestampFormat;
+use crate::process::{AutoZero, Compensation, Despike, HumidityCorrection};
+use crate::sensor::{SensorType, Unit};
+
+/// Behavior that can change without a rebuild, kept in a TOML file
+///
+/// ```toml
+/// poll_interval = 2.0
+/// flush_interval = 5
+/// timestamp = "Rfc3339"
+///
+/// [units]
+/// CO = "mg/m3"
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Seconds between readings
+    pub poll_interval: f32,
+    /// Seconds before buffered log rows are written out
+    pub flush_interval: u64,
+    /// Timestamp format of the CSV log
+    pub timestamp: TimestampFormat,
+    /// Journal unflushed log rows so a crash loses nothing, at one disk sync per sample
+    pub wal: bool,
+    /// Moving average over this many samples, 0 or 1 disables it
+    pub smoothing: usize,
+    /// Spike rejection, off when unset
+    pub despike: Option<Despike>,
+    /// Zeroing of the gas channels on long stable stretches, off when unset
+    pub auto_zero: Option<AutoZero>,
+    /// Temperature and pressure correction, off when unset
+    pub compensation: Option<Compensation>,
+    /// Derive humidity corrected PM channels, off when unset
+    pub humidity_correction: Option<HumidityCorrection>,
+    /// Convert channels of these types to another unit
+    pub units: BTreeMap<SensorType, Unit>,
+    /// Linear correction of single channels of single devices, written by the calibration
+    /// wizard
+    pub calibration: Vec<ChannelCalibration>,
+    pub thresholds: Vec<Threshold>,
+    /// Largest baseline change per day before a recalibration is recommended
+    pub drift: Vec<DriftLimit>,
+    /// Alert on readings far from their recent mean, off when unset
+    pub anomaly: Option<AnomalyConfig>,
+    /// Publish rolling 1-min/15-min/1-h means and log them to a summary CSV
+    pub aggregate: bool,
+    /// Bridge gaps in the readings with interpolated samples in the aggregates
+    pub interpolate_gaps: bool,
+    /// Language of the user interfaces
+    pub language: Language,
+    /// Minutes without input before the display dims, 0 never dims
+    pub dim_after: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            poll_interval: 1.0,
+            flush_interval: 5,
+            timestamp: TimestampFormat::default(),
+            wal: false,
+            smoothing: 0,
+            despike: None,
+            auto_zero: None,
+            compensation: None,
+            humidity_correction: None,
+            units: BTreeMap::new(),
+            calibration: Vec::new(),
+            thresholds: Vec::new(),
+            drift: Vec::new(),
+            anomaly: None,
+            aggregate: false,
+            interpolate_gaps: false,
+            language: Language::default(),
+            dim_after: 0,
+        }
+    }
+}
+
+impl Settings {
+    /// `config.toml` in the user's configuration directory
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("envsensor-demo").join("config.toml"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        let settings: Self = toml::from_str(&text)
+            .with_context(|| format!("Invalid settings in {}", path.display()))?;
+        settings
+            .validate()
+            .with_context(|| format!("Invalid settings in {}", path.display()))?;
+
+        Ok(settings)
+    }
+
+    /// Fails for values that parse but can't be used, e.g. a broken timestamp pattern
+    pub fn validate(&self) -> Result<()> {
+        self.timestamp.validate()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Time without input before the display dims
+    pub fn dim_timeout(&self) -> Option<Duration> {
+        (self.dim_after > 0).then(|| Duration::from_secs(u64::from(self.dim_after) * 60))
+    }
+
+    /// Copy the settings into the options of a sensor session
+    pub fn apply(&self, config: &mut Config) {
+        config.interval = Duration::from_secs_f32(self.poll_interval.max(0.0));
+        config.log.flush.interval = Duration::from_secs(self.flush_interval);
+        config.log.timestamp = self.timestamp.clone();
+        config.log.wal = self.wal;
+        config.process.smoothing = self.smoothing;
+        config.process.despike = self.despike;
+        config.process.auto_zero = self.auto_zero;
+        config.process.compensation = self.compensation.clone();
+        config.process.calibration = self.calibration.clone();
+        config.process.humidity_correction = self.humidity_correction;
+        config.process.units = self.units.iter().map(|(ty, unit)| (*ty, *unit)).collect();
+        config.thresholds = self.thresholds.clone();
+        config.drift = self.drift.clone();
+        config.anomaly = self.anomaly;
+        config.aggregate = self.aggregate;
+        config.interpolate_gaps = self.interpolate_gaps;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_toml() {
+        let mut settings = Settings {
+            poll_interval: 2.5,
+            timestamp: TimestampFormat::Custom("%H:%M".to_string()),
+            ..Default::default()
+        };
+        settings.units.insert(SensorType::CO, Unit::MgPerM3);
+        settings.thresholds.push(Threshold {
+            high: Some(50.0),
+            ..Threshold::new(SensorType::CO)
+        });
+
+        let text = toml::to_string_pretty(&settings).unwrap();
+        assert!(text.contains("CO = \"mg/m3\""));
+        assert_eq!(toml::from_str::<Settings>(&text).unwrap(), settings);
+
+        // Missing keys fall back to the defaults
+        let partial: Settings = toml::from_str("poll_interval = 10.0").unwrap();
+        assert_eq!(partial.flush_interval, 5);
+
+        let path =
+            std::env::temp_dir().join(format!("envsensor-settings-{}.toml", std::process::id()));
+        fs::write(&path, "timestamp = { Custom = \"%Q\" }").unwrap();
+        assert!(Settings::load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
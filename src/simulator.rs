@@ -0,0 +1,256 @@
+use std::io;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, bail};
+use chrono::Local;
+
+use crate::sensor::{
+    SensorChannel, SensorData, SensorDriver, SensorModel, SensorType, SerialSettings, Unit,
+};
+use crate::traffic::{Direction, Frame};
+
+/// Ports of the simulator start with this, faults to inject follow as a query, e.g.
+/// "sim://?timeout=0.05&nan=0.1"
+pub const SIM_PREFIX: &str = "sim://";
+
+/// Time between two readings
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a reading hangs before it times out, as long as the serial drivers wait
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// First byte of a simulated frame
+const HEADER: u8 = 0xAA;
+
+/// Faults the simulator injects, each the chance per reading from 0 to 1
+///
+/// Timeouts, truncated frames and bad checksums fail the reading like they would with a
+/// real sensor, which stops the sensor thread until it is retried. NaN values are delivered.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Chaos {
+    pub timeout: f32,
+    pub truncated: f32,
+    pub bad_checksum: f32,
+    pub nan: f32,
+}
+
+impl Chaos {
+    /// Faults of a simulator port name, none without a query
+    pub fn from_port(port: &str) -> Result<Self> {
+        let mut chaos = Self::default();
+        let Some((_, query)) = port.split_once('?') else {
+            return Ok(chaos);
+        };
+
+        for option in query.split('&').filter(|o| !o.is_empty()) {
+            let (name, value) = option
+                .split_once('=')
+                .with_context(|| format!("Expected <fault>=<chance>, got \"{option}\""))?;
+            let chance = value
+                .parse()
+                .ok()
+                .filter(|chance| (0.0..=1.0).contains(chance))
+                .with_context(|| format!("The chance of {name} must be between 0 and 1"))?;
+            match name {
+                "timeout" => chaos.timeout = chance,
+                "truncated" => chaos.truncated = chance,
+                "checksum" => chaos.bad_checksum = chance,
+                "nan" => chaos.nan = chance,
+                _ => {
+                    bail!("Unknown fault \"{name}\", expected timeout, truncated, checksum or nan")
+                }
+            }
+        }
+
+        Ok(chaos)
+    }
+}
+
+/// Xorshift generator, enough for noise and dice
+struct Rng(u64);
+
+impl Rng {
+    fn seeded() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Self(nanos | 1)
+    }
+
+    /// Uniform in [0, 1)
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1 << 24) as f32
+    }
+
+    fn chance(&mut self, p: f32) -> bool {
+        self.next() < p
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, b| sum.wrapping_add(*b))
+}
+
+/// Header, number of values, the values as little endian f32 and the checksum
+fn encode(values: &[f32]) -> Vec<u8> {
+    let mut frame = vec![HEADER, values.len() as u8];
+    for value in values {
+        frame.extend_from_slice(&value.to_le_bytes());
+    }
+    frame.push(checksum(&frame));
+    frame
+}
+
+/// The `count` values of a frame
+fn decode(frame: &[u8], count: usize) -> Result<Vec<f32>> {
+    let len = 3 + 4 * count;
+    if frame.len() != len {
+        bail!("Truncated frame, {} of {len} bytes", frame.len());
+    }
+    if frame[0] != HEADER || usize::from(frame[1]) != count {
+        bail!("Invalid frame header {:02X} {:02X}", frame[0], frame[1]);
+    }
+    let (body, sum) = frame.split_at(len - 1);
+    if checksum(body) != sum[0] {
+        bail!(
+            "Checksum mismatch, {:02X} instead of {:02X}",
+            sum[0],
+            checksum(body)
+        );
+    }
+
+    Ok(body[2..]
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+/// Sensor without hardware for demos and for trying out error handling
+///
+/// CO, PM2.5, temperature and humidity follow slow cycles with some noise. Each reading goes
+/// through a frame like a serial sensor's, which `Chaos` can corrupt.
+pub struct Simulator {
+    channels: Vec<SensorChannel>,
+    chaos: Chaos,
+    rng: Rng,
+    capture: Option<Sender<Frame>>,
+    /// Readings so far, the time of the cycles
+    readings: u32,
+}
+
+impl Simulator {
+    /// Values of the next reading
+    fn values(&mut self) -> Vec<f32> {
+        self.readings += 1;
+        // One cycle every 10 minutes, temperature and humidity a third as fast
+        let t = self.readings as f32 / 600.0 * std::f32::consts::TAU;
+        let mut noise = |amplitude: f32| (self.rng.next() - 0.5) * 2.0 * amplitude;
+
+        vec![
+            (2.0 + 1.5 * t.sin() + noise(0.2)).max(0.0),
+            (12.0 + 8.0 * (t + 1.0).sin() + noise(1.0)).max(0.0),
+            22.0 + 2.0 * (t / 3.0).sin() + noise(0.1),
+            45.0 + 10.0 * (t / 3.0 + 2.0).sin() + noise(0.5),
+        ]
+    }
+
+    fn record(&self, bytes: &[u8]) {
+        if let Some(capture) = &self.capture {
+            let _ = capture.send(Frame {
+                timestamp: Local::now(),
+                direction: Direction::Rx,
+                bytes: bytes.to_vec(),
+            });
+        }
+    }
+}
+
+impl SensorDriver for Simulator {
+    fn new(port: &str, _serial: &SerialSettings, capture: Option<Sender<Frame>>) -> Result<Self> {
+        Ok(Simulator {
+            channels: vec![
+                SensorChannel::new(SensorType::CO, Unit::PPM),
+                SensorChannel::new(SensorType::PM2_5, Unit::UgPerM3),
+                SensorChannel::new(SensorType::Temperature, Unit::Celsius),
+                SensorChannel::new(SensorType::Humidity, Unit::PercentRH),
+            ],
+            chaos: Chaos::from_port(port)?,
+            rng: Rng::seeded(),
+            capture,
+            readings: 0,
+        })
+    }
+
+    fn get_metadata(&self) -> &[SensorChannel] {
+        &self.channels
+    }
+
+    fn read_data(&mut self) -> Result<Vec<SensorData>> {
+        thread::sleep(INTERVAL);
+        if self.rng.chance(self.chaos.timeout) {
+            thread::sleep(TIMEOUT);
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "Operation timed out").into());
+        }
+
+        let mut values = self.values();
+        for value in &mut values {
+            if self.rng.chance(self.chaos.nan) {
+                *value = f32::NAN;
+            }
+        }
+
+        let mut frame = encode(&values);
+        if self.rng.chance(self.chaos.bad_checksum)
+            && let Some(sum) = frame.last_mut()
+        {
+            *sum = sum.wrapping_add(1);
+        }
+        if self.rng.chance(self.chaos.truncated) {
+            let len = (self.rng.next() * frame.len() as f32) as usize;
+            frame.truncate(len);
+        }
+        self.record(&frame);
+
+        Ok(decode(&frame, self.channels.len())?
+            .into_iter()
+            .zip(&self.channels)
+            .map(|(value, ch)| SensorData::new(ch.sensor_type, value, ch.unit))
+            .collect())
+    }
+
+    fn model() -> SensorModel {
+        SensorModel::SIMULATOR
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chaos_and_frames() {
+        let chaos = Chaos::from_port("sim://?timeout=0.05&nan=1").unwrap();
+        assert_eq!(
+            (chaos.timeout, chaos.nan, chaos.truncated),
+            (0.05, 1.0, 0.0)
+        );
+        assert_eq!(Chaos::from_port("sim://").unwrap(), Chaos::default());
+        assert!(Chaos::from_port("sim://?nan=2").is_err());
+        assert!(Chaos::from_port("sim://?smoke=0.1").is_err());
+
+        let frame = encode(&[1.5, f32::NAN]);
+        let values = decode(&frame, 2).unwrap();
+        assert_eq!(values[0], 1.5);
+        assert!(values[1].is_nan());
+
+        assert!(decode(&frame[..5], 2).is_err());
+        let mut corrupt = frame.clone();
+        corrupt[3] ^= 0x01;
+        assert!(decode(&corrupt, 2).is_err());
+    }
+}
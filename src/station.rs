@@ -0,0 +1,294 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use bus::Bus;
+
+use crate::{
+    aggregate::Period,
+    alert::{Threshold, with_units},
+    calibration::device_id,
+    convert::convert,
+    history::History,
+    sensor::{AppMsg, SampleData, Sensor, SensorChannel, SensorModel, Unit, channel_key},
+    session::Marker,
+    stats::RunningStats,
+    traffic::Frame,
+};
+
+/// Serial frames kept for the traffic viewer
+const TRAFFIC_LEN: usize = 500;
+
+/// 1-min means kept for views over the whole run, a month
+const MINUTE_MEANS: usize = 31 * 24 * 60;
+
+/// One sensor of the dashboard, either running or loaded from a log file
+pub struct Station {
+    pub name: String,
+    /// Serial port of a live sensor
+    pub port: Option<String>,
+    /// Model of a live sensor, loaded logs have none
+    pub model: Option<SensorModel>,
+    pub sensor: Option<Sensor>,
+    /// End of a timed run
+    pub deadline: Option<Instant>,
+    /// When the sensor was started, for the elapsed time
+    pub started: Option<Instant>,
+    /// Samples received since the start, not limited by the history length
+    pub received: usize,
+    /// Samples in the display units
+    pub history: History,
+    /// 1-min means of the aggregator in the display units, far longer than the history
+    pub minute_means: History,
+    pub channels: Vec<SensorChannel>,
+    /// The channels as the sensor delivers and logs them
+    sources: Vec<SensorChannel>,
+    /// Shown in another unit than delivered, by `channel_key`, for display only
+    display_units: BTreeMap<String, Unit>,
+    /// Statistics since the sensor was started, not limited by the history length
+    pub session_stats: Vec<RunningStats>,
+    /// Most recent serial frames, oldest first
+    pub traffic: VecDeque<Frame>,
+    pub markers: Vec<Marker>,
+    /// Last error of the sensor, shown until dismissed
+    pub error: Option<String>,
+    /// Sensor that stopped on an error, kept to retry it
+    failed: Option<Sensor>,
+}
+
+impl Station {
+    pub fn new(name: String, capacity: usize) -> Self {
+        Self {
+            name,
+            port: None,
+            model: None,
+            sensor: None,
+            deadline: None,
+            started: None,
+            received: 0,
+            history: History::new(capacity),
+            minute_means: History::new(MINUTE_MEANS),
+            channels: Vec::new(),
+            sources: Vec::new(),
+            display_units: BTreeMap::new(),
+            session_stats: Vec::new(),
+            traffic: VecDeque::new(),
+            markers: Vec::new(),
+            error: None,
+            failed: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.sensor.is_some()
+    }
+
+    /// File the sensor is being logged to
+    pub fn log_path(&self) -> Option<PathBuf> {
+        self.sensor.as_ref().and_then(Sensor::log_path)
+    }
+
+    /// Model and `calibration::device_id` of the live sensor, once it is initialized
+    pub fn device(&self) -> Option<(SensorModel, String)> {
+        let sensor = self.sensor.as_ref()?;
+        let info = sensor.info()?;
+
+        Some((
+            sensor.model(),
+            device_id(self.port.as_deref()?, Some(&info)),
+        ))
+    }
+
+    /// Channel `idx` in the unit the sensor delivers and logs it in
+    pub fn source_channel(&self, idx: usize) -> Option<SensorChannel> {
+        self.sources.get(idx).copied()
+    }
+
+    /// Time left of a timed run
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .filter(|_| self.is_running())
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(sensor) = self.sensor.take() {
+            sensor.stop();
+        }
+        self.deadline = None;
+    }
+
+    /// Stop the sensor and wait until its log is closed, e.g. before the application exits
+    pub fn shut_down(&mut self) {
+        let sensor = self.sensor.take().or_else(|| self.failed.take());
+        if let Some(sensor) = sensor {
+            sensor.stop();
+            sensor.wait_logged();
+        }
+        self.deadline = None;
+    }
+
+    /// The sensor stopped on an error and can be started again
+    pub fn can_retry(&self) -> bool {
+        self.failed.is_some()
+    }
+
+    /// Start the failed sensor again with the same settings
+    pub fn retry(&mut self) -> Result<()> {
+        let Some(mut sensor) = self.failed.take() else {
+            return Ok(());
+        };
+        self.error = None;
+
+        if let Err(e) = sensor.restart(Bus::new(10)) {
+            self.error = Some(e.to_string());
+            self.failed = Some(sensor);
+            return Err(e);
+        }
+        self.deadline = sensor.config().duration.map(|d| Instant::now() + d);
+        // The sample count starts over with the elapsed time
+        self.started = Some(Instant::now());
+        self.received = 0;
+        self.sensor = Some(sensor);
+
+        Ok(())
+    }
+
+    /// Hide the error, giving up on retrying
+    pub fn dismiss(&mut self) {
+        self.error = None;
+        self.failed = None;
+    }
+
+    /// Unit channel `idx` is shown in, the delivered one when it can't be converted
+    fn display_unit(&self, idx: usize) -> Option<Unit> {
+        let source = self.sources.get(idx)?;
+
+        Some(
+            self.display_units
+                .get(&channel_key(&self.sources, idx))
+                .copied()
+                .filter(|&unit| convert(1.0, source.sensor_type, source.unit, unit).is_some())
+                .unwrap_or(source.unit),
+        )
+    }
+
+    /// Show the channels in another unit, by `channel_key`, converting what's buffered
+    pub fn set_display_units(&mut self, units: &BTreeMap<String, Unit>) {
+        self.display_units = units.clone();
+
+        for idx in 0..self.channels.len() {
+            let ch = self.channels[idx];
+            let Some(unit) = self.display_unit(idx).filter(|&u| u != ch.unit) else {
+                continue;
+            };
+            let Some(factor) = convert(1.0, ch.sensor_type, ch.unit, unit) else {
+                continue;
+            };
+
+            for sample in self.history.iter_mut().chain(self.minute_means.iter_mut()) {
+                if let Some(d) = sample.data.get_mut(idx) {
+                    d.value *= factor;
+                    d.unit = unit;
+                }
+            }
+            self.session_stats[idx].scale(factor);
+            self.channels[idx].unit = unit;
+        }
+    }
+
+    /// `thresholds` with the unit set where it isn't, to the delivered one of the first channel
+    /// of the type, ready for `threshold_for` in the display units
+    pub fn display_thresholds(&self, thresholds: &[Threshold]) -> Vec<Threshold> {
+        with_units(thresholds, &self.sources)
+    }
+
+    /// Convert `sample` from the delivered to the display units
+    fn to_display(&self, sample: &mut SampleData) {
+        for (idx, d) in sample.data.iter_mut().enumerate() {
+            if let Some(unit) = self.display_unit(idx)
+                && let Some(value) = convert(d.value, d.ty, d.unit, unit)
+            {
+                d.value = value;
+                d.unit = unit;
+            }
+        }
+    }
+
+    pub fn push_sample(&mut self, mut sample: SampleData) {
+        if self.sources.len() != sample.data.len() {
+            self.sources = sample.data.iter().map(|d| d.channel()).collect();
+        }
+        self.to_display(&mut sample);
+
+        // Channels as shown, after any unit conversion in the pipeline and for display
+        if self.channels.len() != sample.data.len() {
+            self.channels = sample.data.iter().map(|d| d.channel()).collect();
+            self.session_stats = vec![RunningStats::default(); self.channels.len()];
+        }
+
+        self.received += 1;
+        for (stats, d) in self.session_stats.iter_mut().zip(&sample.data) {
+            stats.push(d.value);
+        }
+        self.history.push(sample);
+    }
+
+    pub fn push_traffic(&mut self, frame: Frame) {
+        if self.traffic.len() == TRAFFIC_LEN {
+            self.traffic.pop_front();
+        }
+        self.traffic.push_back(frame);
+    }
+
+    /// Take in `messages`, from the own sensor or a remote one, returns all but the samples
+    pub fn receive(&mut self, messages: impl IntoIterator<Item = AppMsg>) -> Vec<AppMsg> {
+        let mut events = Vec::new();
+        for msg in messages {
+            match msg {
+                AppMsg::Sample(sample) => self.push_sample(sample),
+                AppMsg::Aggregate(aggregate) => {
+                    if aggregate.period == Period::OneMinute {
+                        let mut means = aggregate.means();
+                        self.to_display(&mut means);
+                        self.minute_means.push(means);
+                    }
+                    events.push(AppMsg::Aggregate(aggregate));
+                }
+                AppMsg::Error(e) => {
+                    self.error = Some(e.clone());
+                    events.push(AppMsg::Error(e));
+                }
+                msg => events.push(msg),
+            }
+        }
+
+        events
+    }
+
+    /// Take in everything the sensor sent since the last frame, returns all but the samples
+    pub fn poll(&mut self) -> Vec<AppMsg> {
+        // Checked first so the messages sent before a timed run ended are still taken in
+        let stopped = self.sensor.as_ref().is_some_and(Sensor::is_stopped);
+        let messages = match &mut self.sensor {
+            Some(s) => std::iter::from_fn(|| s.try_recv()).collect(),
+            None => Vec::new(),
+        };
+        let events = self.receive(messages);
+
+        if stopped {
+            // An error ends the thread, the sensor is kept for a retry
+            if self.error.is_some() {
+                self.failed = self.sensor.take();
+                self.deadline = None;
+            } else {
+                self.stop();
+            }
+        }
+
+        events
+    }
+}
@@ -0,0 +1,429 @@
+use chrono::{DateTime, Local, NaiveDate, Timelike};
+
+use crate::history::History;
+use crate::sensor::SampleData;
+
+/// Basic statistics of one channel
+#[derive(Clone, Copy, Debug)]
+pub struct ChannelStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    /// Population standard deviation
+    pub stddev: f32,
+    pub count: usize,
+}
+
+impl ChannelStats {
+    pub fn from_values(values: impl Iterator<Item = f32>) -> Option<Self> {
+        let mut stats = RunningStats::default();
+        values.for_each(|v| stats.push(v));
+
+        stats.summary()
+    }
+}
+
+/// Statistics updated one value at a time, without keeping the values (Welford's method)
+#[derive(Clone, Debug, Default)]
+pub struct RunningStats {
+    count: usize,
+    mean: f64,
+    m2: f64,
+    min: f32,
+    max: f32,
+}
+
+impl RunningStats {
+    pub fn push(&mut self, value: f32) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        }
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+
+        self.count += 1;
+        let delta = value as f64 - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value as f64 - self.mean);
+    }
+
+    /// Statistics of the values multiplied by `factor`, e.g. after a unit conversion
+    pub fn scale(&mut self, factor: f32) {
+        let (a, b) = (self.min * factor, self.max * factor);
+        self.min = a.min(b);
+        self.max = a.max(b);
+        self.mean *= factor as f64;
+        self.m2 *= (factor as f64).powi(2);
+    }
+
+    pub fn summary(&self) -> Option<ChannelStats> {
+        (self.count > 0).then(|| ChannelStats {
+            min: self.min,
+            max: self.max,
+            mean: self.mean as f32,
+            stddev: (self.m2 / self.count as f64).sqrt() as f32,
+            count: self.count,
+        })
+    }
+}
+
+/// Counts of values in equally wide bins between their minimum and maximum
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    /// Lower edge of the first bin
+    pub min: f32,
+    pub bin_width: f32,
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    /// `None` without values or bins, all equal values share one bin
+    pub fn of(values: &[f32], bins: usize) -> Option<Self> {
+        let min = values.iter().copied().reduce(f32::min)?;
+        let max = values.iter().copied().reduce(f32::max)?;
+        if bins == 0 {
+            return None;
+        }
+        let bins = if max > min { bins } else { 1 };
+        let bin_width = ((max - min) / bins as f32).max(f32::EPSILON);
+
+        let mut counts = vec![0; bins];
+        for v in values {
+            // The maximum belongs to the last bin
+            let n = (((*v - min) / bin_width) as usize).min(bins - 1);
+            counts[n] += 1;
+        }
+
+        Some(Self {
+            min,
+            bin_width,
+            counts,
+        })
+    }
+
+    /// Center of bin `n`
+    pub fn center(&self, n: usize) -> f32 {
+        self.min + self.bin_width * (n as f32 + 0.5)
+    }
+}
+
+/// Mean of one channel per hour of the day, one row per calendar day
+#[derive(Clone, Debug, PartialEq)]
+pub struct DailyPattern {
+    /// Days with samples, oldest first
+    pub days: Vec<NaiveDate>,
+    /// Mean per hour of each day, `None` for hours without samples
+    pub means: Vec<[Option<f32>; 24]>,
+}
+
+impl DailyPattern {
+    /// Bin channel `idx` by local day and hour, the samples must be in time order
+    pub fn of<'a>(samples: impl Iterator<Item = &'a SampleData>, idx: usize) -> Self {
+        let mut days = Vec::new();
+        let mut sums: Vec<[(f64, usize); 24]> = Vec::new();
+
+        for s in samples {
+            let Some(d) = s.data.get(idx) else {
+                continue;
+            };
+            let day = s.timestamp.date_naive();
+            if days.last() != Some(&day) {
+                days.push(day);
+                sums.push([(0.0, 0); 24]);
+            }
+            let (sum, count) = &mut sums.last_mut().unwrap()[s.timestamp.hour() as usize];
+            *sum += d.value as f64;
+            *count += 1;
+        }
+
+        let means = sums
+            .iter()
+            .map(|hours| hours.map(|(sum, n)| (n > 0).then(|| (sum / n as f64) as f32)))
+            .collect();
+
+        Self { days, means }
+    }
+
+    /// Lowest and highest hourly mean
+    pub fn range(&self) -> Option<(f32, f32)> {
+        let values = self.means.iter().flatten().flatten().copied();
+        let min = values.clone().reduce(f32::min)?;
+        let max = values.reduce(f32::max)?;
+
+        Some((min, max))
+    }
+}
+
+/// Mean of each value and the `window - 1` before it, shorter at the start
+pub fn moving_average(values: &[f32], window: usize) -> Vec<f32> {
+    let window = window.max(1);
+    let mut sum = 0.0_f64;
+
+    values
+        .iter()
+        .enumerate()
+        .map(|(n, &v)| {
+            sum += v as f64;
+            if n >= window {
+                sum -= values[n - window] as f64;
+            }
+            (sum / (n + 1).min(window) as f64) as f32
+        })
+        .collect()
+}
+
+/// Mean and spread of a run of consecutive points, drawn in place of them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Envelope {
+    /// Mean x of the run
+    pub x: f64,
+    pub mean: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+/// Reduce `points` to at most `buckets` envelopes of equally many points each
+///
+/// Unlike plain subsampling, the min and max keep short spikes visible.
+pub fn decimate(points: &[(f64, f32)], buckets: usize) -> Vec<Envelope> {
+    if buckets == 0 {
+        return Vec::new();
+    }
+
+    points
+        .chunks(points.len().div_ceil(buckets).max(1))
+        .map(|chunk| {
+            let n = chunk.len() as f64;
+            Envelope {
+                x: chunk.iter().map(|(x, _)| x).sum::<f64>() / n,
+                mean: (chunk.iter().map(|(_, y)| *y as f64).sum::<f64>() / n) as f32,
+                min: chunk.iter().map(|(_, y)| *y).fold(f32::MAX, f32::min),
+                max: chunk.iter().map(|(_, y)| *y).fold(f32::MIN, f32::max),
+            }
+        })
+        .collect()
+}
+
+/// Direction a channel is heading
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trend {
+    Rising,
+    Falling,
+    Steady,
+}
+
+/// Change per minute of one channel
+#[derive(Clone, Copy, Debug)]
+pub struct RateOfChange {
+    pub per_minute: f32,
+    pub trend: Trend,
+}
+
+impl RateOfChange {
+    /// Least squares slope of channel `idx` over `samples`, which must span at least 10 s
+    ///
+    /// A change of less than 2% of the mean level per minute counts as steady.
+    pub fn of<'a>(samples: impl Iterator<Item = &'a SampleData>, idx: usize) -> Option<Self> {
+        let points = samples
+            .filter_map(|s| s.data.get(idx).map(|d| (s.timestamp, d.value as f64)))
+            .collect::<Vec<_>>();
+        let (first, last) = (points.first()?.0, points.last()?.0);
+        if (last - first).num_seconds() < 10 {
+            return None;
+        }
+
+        let xs = points
+            .iter()
+            .map(|(t, _)| (*t - first).num_milliseconds() as f64 / 60_000.0)
+            .collect::<Vec<_>>();
+        let n = points.len() as f64;
+        let mean_x = xs.iter().sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let (mut sxy, mut sxx) = (0.0, 0.0);
+        for (x, (_, y)) in xs.iter().zip(&points) {
+            sxy += (x - mean_x) * (y - mean_y);
+            sxx += (x - mean_x) * (x - mean_x);
+        }
+        let per_minute = (sxy / sxx) as f32;
+
+        let trend = if per_minute.abs() <= (mean_y.abs() as f32 * 0.02).max(f32::EPSILON) {
+            Trend::Steady
+        } else if per_minute > 0.0 {
+            Trend::Rising
+        } else {
+            Trend::Falling
+        };
+
+        Some(Self { per_minute, trend })
+    }
+}
+
+/// Agreement of a channel with the same quantity measured by a reference sensor
+#[derive(Clone, Copy, Debug)]
+pub struct Comparison {
+    /// Mean difference, positive when the channel reads higher than the reference
+    pub bias: f32,
+    pub rmse: f32,
+    /// Pearson correlation, `None` while either side is constant
+    pub correlation: Option<f32>,
+    pub count: usize,
+}
+
+impl Comparison {
+    /// Statistics of `(reference, value)` pairs
+    pub fn of(pairs: impl Iterator<Item = (f32, f32)>) -> Option<Self> {
+        let pairs = pairs.map(|(r, v)| (r as f64, v as f64)).collect::<Vec<_>>();
+        if pairs.is_empty() {
+            return None;
+        }
+
+        let n = pairs.len() as f64;
+        let mean_r = pairs.iter().map(|(r, _)| r).sum::<f64>() / n;
+        let mean_v = pairs.iter().map(|(_, v)| v).sum::<f64>() / n;
+        let (mut srv, mut srr, mut svv, mut sdd) = (0.0, 0.0, 0.0, 0.0);
+        for (r, v) in &pairs {
+            srv += (r - mean_r) * (v - mean_v);
+            srr += (r - mean_r) * (r - mean_r);
+            svv += (v - mean_v) * (v - mean_v);
+            sdd += (v - r) * (v - r);
+        }
+
+        Some(Self {
+            bias: (mean_v - mean_r) as f32,
+            rmse: (sdd / n).sqrt() as f32,
+            correlation: (srr > 0.0 && svv > 0.0).then(|| (srv / (srr * svv).sqrt()) as f32),
+            count: pairs.len(),
+        })
+    }
+
+    /// Channel `idx` of `history` since `from` against channel `ref_idx` of `reference`,
+    /// which is interpolated at the timestamps of `history`
+    pub fn between(
+        history: &History,
+        idx: usize,
+        reference: &History,
+        ref_idx: usize,
+        from: Option<DateTime<Local>>,
+    ) -> Option<Self> {
+        Self::of(history.range(from, None).filter_map(|s| {
+            let value = s.data.get(idx)?.value;
+            let r = *reference.value_at(s.timestamp)?.get(ref_idx)?;
+            Some((r, value))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::{SensorData, SensorType, Unit};
+    use chrono::TimeZone;
+
+    #[test]
+    fn running_stats() {
+        let s = ChannelStats::from_values([2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0].into_iter())
+            .unwrap();
+
+        assert_eq!(
+            (s.min, s.max, s.mean, s.stddev, s.count),
+            (2.0, 9.0, 5.0, 2.0, 8)
+        );
+        assert!(ChannelStats::from_values(std::iter::empty()).is_none());
+
+        let mut running = RunningStats::default();
+        [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]
+            .into_iter()
+            .for_each(|v| running.push(v));
+        running.scale(1000.0);
+        let s = running.summary().unwrap();
+        assert_eq!(
+            (s.min, s.max, s.mean, s.stddev),
+            (2000.0, 9000.0, 5000.0, 2000.0)
+        );
+    }
+
+    #[test]
+    fn histogram_bins() {
+        let h = Histogram::of(&[0.0, 1.0, 2.5, 9.9, 10.0], 4).unwrap();
+        assert_eq!(h.counts, vec![2, 1, 0, 2]);
+        assert_eq!(h.center(0), 1.25);
+
+        let flat = Histogram::of(&[3.0, 3.0], 10).unwrap();
+        assert_eq!(flat.counts, vec![2]);
+        assert!(Histogram::of(&[], 10).is_none());
+    }
+
+    #[test]
+    fn trailing_moving_average() {
+        assert_eq!(
+            moving_average(&[1.0, 3.0, 5.0, 7.0], 2),
+            vec![1.0, 2.0, 4.0, 6.0]
+        );
+        assert_eq!(moving_average(&[1.0, 3.0], 1), vec![1.0, 3.0]);
+    }
+
+    #[test]
+    fn decimate_keeps_spikes() {
+        let mut points = (0..100).map(|n| (n as f64, 1.0)).collect::<Vec<_>>();
+        points[42].1 = 50.0;
+
+        let envelopes = decimate(&points, 10);
+        assert_eq!(envelopes.len(), 10);
+        assert_eq!(envelopes[4].max, 50.0);
+        assert_eq!(envelopes[4].min, 1.0);
+        assert!((envelopes[4].mean - 5.9).abs() < 1e-4);
+        assert_eq!(envelopes[0].x, 4.5);
+    }
+
+    #[test]
+    fn daily_pattern() {
+        let at = |day, hour, value| SampleData {
+            timestamp: chrono::Local
+                .with_ymd_and_hms(2025, 6, day, hour, 30, 0)
+                .unwrap(),
+            data: vec![SensorData::new(SensorType::NO2, value, Unit::PPB)],
+        };
+        let samples = [at(1, 8, 10.0), at(1, 8, 20.0), at(2, 17, 40.0)];
+
+        let pattern = DailyPattern::of(samples.iter(), 0);
+        assert_eq!(pattern.days.len(), 2);
+        assert_eq!(pattern.means[0][8], Some(15.0));
+        assert_eq!(pattern.means[0][9], None);
+        assert_eq!(pattern.means[1][17], Some(40.0));
+        assert_eq!(pattern.range(), Some((15.0, 40.0)));
+    }
+
+    #[test]
+    fn falling_rate() {
+        let start = chrono::Local::now();
+        let samples = (0..=60)
+            .map(|n| {
+                SampleData::single(
+                    start,
+                    n,
+                    SensorType::PM2_5,
+                    100.0 - n as f32 / 2.0,
+                    Unit::UgPerM3,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let rate = RateOfChange::of(samples.iter(), 0).unwrap();
+        assert!((rate.per_minute + 30.0).abs() < 1e-3);
+        assert_eq!(rate.trend, Trend::Falling);
+        assert!(RateOfChange::of(samples[..5].iter(), 0).is_none());
+    }
+
+    #[test]
+    fn compare_sensors() {
+        let pairs = [(10.0, 12.0), (20.0, 22.0), (30.0, 32.0)];
+        let c = Comparison::of(pairs.into_iter()).unwrap();
+        assert_eq!((c.bias, c.rmse, c.count), (2.0, 2.0, 3));
+        assert!((c.correlation.unwrap() - 1.0).abs() < 1e-6);
+
+        let flat = Comparison::of([(1.0, 5.0), (2.0, 5.0)].into_iter()).unwrap();
+        assert_eq!(flat.correlation, None);
+        assert!(Comparison::of(std::iter::empty()).is_none());
+    }
+}
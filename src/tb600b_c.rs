@@ -1,11 +1,16 @@
-use std::{io::Cursor, thread, time::Duration};
+use std::io::{Cursor, Read, Write};
+use std::{sync::mpsc::Sender, thread, time::Duration};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use binrw::BinRead;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
-use serialport::SerialPort;
 
-use crate::sensor::{SensorChannel, SensorData, SensorDriver, SensorModel, SensorType, Unit};
+use crate::sensor::{
+    SensorChannel, SensorData, SensorDriver, SensorInfo, SensorModel, SensorType, SerialSettings,
+    Unit,
+};
+use crate::traffic::{Frame, Tap};
+use crate::transport;
 
 #[allow(dead_code)]
 #[derive(BinRead)]
@@ -66,16 +71,13 @@ impl From<ECType> for SensorType {
 }
 
 pub struct TB600BC {
-    dev: Box<dyn SerialPort>,
+    dev: Tap,
     scale: u32,
+    range: u16,
     channels: Vec<SensorChannel>,
 }
 
-fn simple_query(
-    port: &mut Box<dyn SerialPort>,
-    query: &[u8],
-    resp_len: usize,
-) -> Result<Cursor<Vec<u8>>> {
+fn simple_query(port: &mut Tap, query: &[u8], resp_len: usize) -> Result<Cursor<Vec<u8>>> {
     // Write the query command
     port.write_all(query)?;
 
@@ -89,49 +91,73 @@ fn simple_query(
     Ok(Cursor::new(serial_buf))
 }
 
+/// Channels, scale and range from a `QueryParam2` reply
+pub(crate) fn decode_params(reply: &[u8]) -> Result<(Vec<SensorChannel>, u32, u16)> {
+    let param = QueryParam2::read(&mut Cursor::new(reply))?;
+
+    let sensor_type = SensorType::from(ECType::try_from(param.ty)?);
+
+    let sensor_unit = ECUnit::try_from(param.unit)?;
+
+    // 0x30 >> 4 = 0x3 => 10^3
+    let scale = 10_u32
+        .checked_pow((param.scale >> 4) as u32)
+        .ok_or_else(|| anyhow!("Invalid scale {:#04x}", param.scale))?;
+
+    // Build channel metadata
+    let units = match sensor_unit {
+        ECUnit::PpmMg => [Unit::PPM, Unit::MgPerM3],
+        ECUnit::Ppbug => [Unit::PPB, Unit::UgPerM3],
+        ECUnit::Vol10g => [Unit::PercentVol, Unit::TenGPerM3],
+    };
+
+    let channels = vec![
+        SensorChannel::new(sensor_type, units[0]),
+        SensorChannel::new(sensor_type, units[1]),
+    ];
+
+    Ok((channels, scale, param.range))
+}
+
+/// Concentrations in both units from an auto report
+pub(crate) fn decode_auto_report(report: &[u8], scale: u32) -> Result<(f32, f32)> {
+    let data = AutoReport::read(&mut Cursor::new(report))?;
+
+    let c1 = data.concentration1 as f32 / scale as f32;
+    let c2 = data.concentration2 as f32 / scale as f32;
+
+    Ok((c1, c2))
+}
+
 impl TB600BC {
-    pub fn new(port: &str) -> Result<Self> {
+    pub fn new(
+        port: &str,
+        serial: &SerialSettings,
+        capture: Option<Sender<Frame>>,
+    ) -> Result<Self> {
         let builder = serialport::new(port, 9600)
             .stop_bits(serialport::StopBits::One)
             .data_bits(serialport::DataBits::Eight)
             .timeout(Duration::from_secs(5));
+        let builder = serial.apply(builder);
         println!("{:?}", &builder);
 
-        let mut port = builder.open().unwrap_or_else(|e| {
-            eprintln!("Failed to open \"{}\". Error: {}", port, e);
-            ::std::process::exit(1);
-        });
+        let port = transport::open(port, builder)
+            .map_err(|e| anyhow!("Failed to open \"{port}\": {e}"))?;
+        let mut port = Tap::new(port, capture);
 
         port.write_all(&[0xFF, 0x01, 0x78, 0x41, 0x00, 0x00, 0x00, 0x00, 0x46])?;
 
         thread::sleep(Duration::from_secs(1));
 
-        let mut buffer = simple_query(&mut port, &[0xD7], 9)?;
-
-        let param = QueryParam2::read(&mut buffer)?;
+        let buffer = simple_query(&mut port, &[0xD7], 9)?;
 
-        let sensor_type = SensorType::from(ECType::try_from(param.ty)?);
-
-        let sensor_unit = ECUnit::try_from(param.unit)?;
-
-        // 0x30 >> 4 = 0x3 => 10^3
-        let scale = 10_u32.pow((param.scale >> 4) as u32);
-
-        // Build channel metadata
-        let units = match sensor_unit {
-            ECUnit::PpmMg => [Unit::PPM, Unit::MgPerM3],
-            ECUnit::Ppbug => [Unit::PPB, Unit::UgPerM3],
-            ECUnit::Vol10g => [Unit::PercentVol, Unit::TenGPerM3],
-        };
-
-        let channels = vec![
-            SensorChannel::new(sensor_type, units[0]),
-            SensorChannel::new(sensor_type, units[1]),
-        ];
+        let (channels, scale, range) = decode_params(buffer.get_ref())?;
 
         Ok(TB600BC {
             dev: port,
             scale,
+            range,
             channels,
         })
     }
@@ -153,42 +179,43 @@ impl TB600BC {
         let mut buf = [0; 9];
         self.dev.read_exact(&mut buf)?;
 
-        let data = AutoReport::read(&mut Cursor::new(&buf))?;
-
-        let c1 = data.concentration1 as f32 / self.scale as f32;
-        let c2 = data.concentration2 as f32 / self.scale as f32;
-
-        Ok((c1, c2))
+        decode_auto_report(&buf, self.scale)
     }
 }
 
 impl SensorDriver for TB600BC {
-    fn new(port: &str) -> Result<Self> {
-        TB600BC::new(port)
+    fn new(port: &str, serial: &SerialSettings, capture: Option<Sender<Frame>>) -> Result<Self> {
+        TB600BC::new(port, serial, capture)
     }
 
     fn initialize(&mut self) -> Result<()> {
         self.switch_mode(true)
     }
 
+    fn info(&self) -> SensorInfo {
+        SensorInfo {
+            sensor_type: Some(self.channels[0].sensor_type),
+            range: Some(self.range as f32),
+            scale: Some(self.scale),
+            ..Default::default()
+        }
+    }
+
     fn get_metadata(&self) -> &[SensorChannel] {
         &self.channels
     }
 
+    /// The electrochemical cell settles within about a minute of powering up
+    fn warm_up(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
     fn read_data(&mut self) -> Result<Vec<SensorData>> {
         let (c1, c2) = self.read_auto_report_data()?;
 
         Ok(vec![
-            SensorData {
-                ty: self.channels[0].sensor_type,
-                value: c1,
-                unit: self.channels[0].unit,
-            },
-            SensorData {
-                ty: self.channels[1].sensor_type,
-                value: c2,
-                unit: self.channels[1].unit,
-            },
+            SensorData::new(self.channels[0].sensor_type, c1, self.channels[0].unit),
+            SensorData::new(self.channels[1].sensor_type, c2, self.channels[1].unit),
         ])
     }
 
@@ -210,4 +237,19 @@ mod tests {
         assert_eq!(auto_report.range, 0x03E8);
         assert_eq!(auto_report.concentration1, 0x20D0);
     }
+
+    #[test]
+    fn rejects_invalid_params() {
+        let (channels, scale, range) =
+            decode_params(b"\xFF\xD7\x19\x03\xE8\x02\x30\x00\x00").unwrap();
+        assert_eq!(
+            (channels[1].unit, scale, range),
+            (Unit::MgPerM3, 1000, 1000)
+        );
+
+        // 10^15 doesn't fit, nor is 0x42 a known gas
+        assert!(decode_params(b"\xFF\xD7\x19\x03\xE8\x02\xF0\x00\x00").is_err());
+        assert!(decode_params(b"\xFF\xD7\x42\x03\xE8\x02\x30\x00\x00").is_err());
+        assert!(decode_auto_report(b"\xFF\x86\x25", 1000).is_err());
+    }
 }
@@ -0,0 +1,87 @@
+use crate::sensor::{SensorChannel, SensorType};
+
+/// Red, green and blue
+pub type Rgb = [u8; 3];
+
+/// Values beyond their limits, alarm banners and errors
+pub const ALERT: Rgb = [200, 40, 40];
+
+/// Event markers
+pub const MARKER: Rgb = [150, 80, 200];
+
+/// Window and app icon
+pub const ICON_PNG: &[u8] = include_bytes!("../asset/icon.png");
+
+/// Fonts that cover Chinese, the first one found is used
+///
+/// The built-in egui fonts and the chart font have no Chinese glyphs, slint finds these on
+/// its own.
+pub const CJK_FONTS: [&str; 5] = [
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "/System/Library/Fonts/PingFang.ttc",
+];
+
+/// Stable color per sensor type, `n` picks a shade when a type has several channels
+pub fn channel_rgb(ty: SensorType, n: usize) -> Rgb {
+    let shades = match ty {
+        SensorType::CO => [[230, 85, 13], [253, 141, 60]],
+        SensorType::NO2 => [[117, 107, 177], [158, 154, 200]],
+        SensorType::PM1 => [[49, 163, 84], [116, 196, 118]],
+        SensorType::PM2_5 => [[49, 130, 189], [107, 174, 214]],
+        SensorType::PM10 => [[222, 45, 38], [251, 106, 74]],
+        SensorType::Temperature => [[166, 54, 3], [230, 85, 13]],
+        SensorType::Humidity => [[1, 133, 113], [90, 180, 172]],
+        SensorType::DewPoint => [[140, 81, 10], [191, 129, 45]],
+        SensorType::AbsoluteHumidity => [[197, 27, 125], [222, 119, 174]],
+        SensorType::PM1Dry => [[0, 109, 44], [65, 171, 93]],
+        SensorType::PM2_5Dry => [[8, 81, 156], [66, 146, 198]],
+        SensorType::PM10Dry => [[165, 15, 21], [239, 59, 44]],
+        SensorType::ParticleCount => [[99, 99, 99], [150, 150, 150]],
+    };
+
+    shades[n % shades.len()]
+}
+
+/// Color of each of `channels`, further channels of a type get the next shade
+pub fn channel_colors(channels: &[SensorChannel]) -> Vec<Rgb> {
+    channels
+        .iter()
+        .enumerate()
+        .map(|(idx, ch)| {
+            let n = channels[..idx]
+                .iter()
+                .filter(|c| c.sensor_type == ch.sensor_type)
+                .count();
+            channel_rgb(ch.sensor_type, n)
+        })
+        .collect()
+}
+
+/// First of `CJK_FONTS` on this machine
+pub fn cjk_font() -> Option<Vec<u8>> {
+    CJK_FONTS.iter().find_map(|path| std::fs::read(path).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::Unit;
+
+    #[test]
+    fn shades_per_type() {
+        let channels = [
+            SensorChannel::new(SensorType::CO, Unit::PPM),
+            SensorChannel::new(SensorType::NO2, Unit::PPM),
+            SensorChannel::new(SensorType::CO, Unit::MgPerM3),
+        ];
+        let colors = channel_colors(&channels);
+
+        assert_eq!(colors[0], channel_rgb(SensorType::CO, 0));
+        assert_eq!(colors[1], channel_rgb(SensorType::NO2, 0));
+        assert_eq!(colors[2], channel_rgb(SensorType::CO, 1));
+        assert_ne!(colors[0], colors[2]);
+    }
+}
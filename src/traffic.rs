@@ -0,0 +1,121 @@
+use std::{
+    io::{self, Read, Write},
+    sync::mpsc::Sender,
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::transport::Transport;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Direction {
+    /// Sent to the sensor
+    Tx,
+    /// Received from the sensor
+    Rx,
+}
+
+/// Bytes that went over the serial line in one direction
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Frame {
+    pub timestamp: DateTime<Local>,
+    pub direction: Direction,
+    pub bytes: Vec<u8>,
+}
+
+impl Frame {
+    /// "FF 86 25 BC"
+    pub fn hex(&self) -> String {
+        self.bytes
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Link to the sensor which reports everything written and read when capturing
+pub struct Tap {
+    port: Box<dyn Transport>,
+    capture: Option<Sender<Frame>>,
+}
+
+impl Tap {
+    pub fn new(port: Box<dyn Transport>, capture: Option<Sender<Frame>>) -> Self {
+        Self { port, capture }
+    }
+
+    fn record(&self, direction: Direction, bytes: &[u8]) {
+        if let Some(capture) = &self.capture
+            && !bytes.is_empty()
+        {
+            // Nobody listening any more is not an error of the port
+            let _ = capture.send(Frame {
+                timestamp: Local::now(),
+                direction,
+                bytes: bytes.to_vec(),
+            });
+        }
+    }
+}
+
+impl Read for Tap {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.port.read(buf)?;
+        self.record(Direction::Rx, &buf[..n]);
+        Ok(n)
+    }
+}
+
+impl Write for Tap {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.port.write(buf)?;
+        self.record(Direction::Tx, &buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()
+    }
+}
+
+/// Join consecutive frames of the same direction, a response arrives in several reads
+pub fn coalesce(frames: impl Iterator<Item = Frame>) -> Vec<Frame> {
+    let mut joined: Vec<Frame> = Vec::new();
+
+    for frame in frames {
+        match joined.last_mut() {
+            Some(last) if last.direction == frame.direction => {
+                last.bytes.extend_from_slice(&frame.bytes)
+            }
+            _ => joined.push(frame),
+        }
+    }
+
+    joined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coalesce_fragments() {
+        let frame = |direction, bytes: &[u8]| Frame {
+            timestamp: Local::now(),
+            direction,
+            bytes: bytes.to_vec(),
+        };
+        let frames = [
+            frame(Direction::Tx, &[0xD7]),
+            frame(Direction::Rx, &[0xFF, 0xD7]),
+            frame(Direction::Rx, &[0x19]),
+            frame(Direction::Tx, &[0x86]),
+        ];
+
+        let joined = coalesce(frames.into_iter());
+        assert_eq!(joined.len(), 3);
+        assert_eq!(joined[1].hex(), "FF D7 19");
+    }
+}
@@ -0,0 +1,74 @@
+use std::io::{Read, Write};
+#[cfg(feature = "serial")]
+use std::{net::TcpStream, time::Duration};
+
+#[cfg(feature = "serial")]
+use anyhow::{Context, Result};
+#[cfg(feature = "serial")]
+use serialport::SerialPortBuilder;
+
+/// Ports starting with this are reached over TCP, e.g. "tcp://192.168.1.20:4001"
+pub const TCP_PREFIX: &str = "tcp://";
+
+/// Read and write timeout of TCP bridges, serial ports use the driver's own
+#[cfg(feature = "serial")]
+const TCP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Byte stream to a sensor
+pub trait Transport: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Transport for T {}
+
+/// "host:port" of a port name like "tcp://host:port", `None` for serial ports
+///
+/// Bridges such as ser2net or a USB serial app on a phone forward the serial line over TCP.
+pub fn tcp_address(port: &str) -> Option<&str> {
+    port.strip_prefix(TCP_PREFIX)
+        .filter(|addr| !addr.is_empty())
+}
+
+/// Open `port`, a serial port set up by `builder` or a TCP bridge
+#[cfg(feature = "serial")]
+pub fn open(port: &str, builder: SerialPortBuilder) -> Result<Box<dyn Transport>> {
+    let Some(addr) = tcp_address(port) else {
+        return Ok(Box::new(builder.open()?));
+    };
+
+    let stream =
+        TcpStream::connect(addr).with_context(|| format!("Failed to connect to {addr}"))?;
+    stream.set_read_timeout(Some(TCP_TIMEOUT))?;
+    stream.set_write_timeout(Some(TCP_TIMEOUT))?;
+    // Queries are a few bytes, send them right away
+    stream.set_nodelay(true)?;
+
+    Ok(Box::new(stream))
+}
+
+#[cfg(all(test, feature = "serial"))]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn tcp_bridge() {
+        assert_eq!(tcp_address("tcp://10.0.0.2:4001"), Some("10.0.0.2:4001"));
+        assert_eq!(tcp_address("tcp://"), None);
+        assert_eq!(tcp_address("/dev/ttyUSB0"), None);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = format!("{TCP_PREFIX}{}", listener.local_addr().unwrap());
+        let mut link = open(&port, serialport::new(&port, 9600)).unwrap();
+
+        let (mut bridge, _) = listener.accept().unwrap();
+        link.write_all(&[0xD7]).unwrap();
+        let mut query = [0];
+        bridge.read_exact(&mut query).unwrap();
+        assert_eq!(query, [0xD7]);
+
+        bridge.write_all(&[0xFF, 0xD7]).unwrap();
+        let mut reply = [0; 2];
+        link.read_exact(&mut reply).unwrap();
+        assert_eq!(reply, [0xFF, 0xD7]);
+    }
+}
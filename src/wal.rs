@@ -0,0 +1,156 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+/// Append-only journal of the rows not yet flushed to a CSV log
+///
+/// Every row is synced to the journal before it goes into the buffered log, the journal is
+/// emptied whenever the log itself is synced. Whatever is left after a crash is replayed
+/// into the log by `recover`.
+pub struct Wal {
+    file: File,
+    path: PathBuf,
+}
+
+/// "x.csv" -> "x.csv.wal"
+pub fn wal_path(log: &Path) -> PathBuf {
+    let mut name = log.as_os_str().to_owned();
+    name.push(".wal");
+    PathBuf::from(name)
+}
+
+impl Wal {
+    pub fn open(log: &Path) -> Result<Self> {
+        let path = wal_path(log);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        Ok(Self { file, path })
+    }
+
+    pub fn append(&mut self, row: &str) -> Result<()> {
+        writeln!(self.file, "{row}")?;
+        self.file.sync_data()?;
+
+        Ok(())
+    }
+
+    /// Drop the journaled rows, call once the log has been synced
+    pub fn clear(&mut self) -> Result<()> {
+        self.file.set_len(0)?;
+        self.file.sync_data()?;
+
+        Ok(())
+    }
+
+    /// Remove the journal, the log is complete
+    pub fn remove(self) -> Result<()> {
+        drop(self.file);
+        fs::remove_file(&self.path)?;
+
+        Ok(())
+    }
+}
+
+/// Append the rows of `wal` that didn't make it into `log`, returns how many were added
+fn replay(wal: &Path, log: &Path) -> Result<usize> {
+    let journal = fs::read_to_string(wal)?;
+    let mut content = fs::read_to_string(log)?;
+
+    // A partially written last row is cut off, the journal has it in full
+    if !content.ends_with('\n') {
+        content.truncate(content.rfind('\n').map_or(0, |n| n + 1));
+    }
+
+    // Rows up to the last one already in the log may have been flushed by the buffer
+    let rows = journal.lines().collect::<Vec<_>>();
+    let skip = content
+        .lines()
+        .last()
+        .and_then(|last| rows.iter().rposition(|r| *r == last))
+        .map_or(0, |n| n + 1);
+
+    for row in &rows[skip..] {
+        content.push_str(row);
+        content.push('\n');
+    }
+    fs::write(log, content)?;
+
+    Ok(rows.len() - skip)
+}
+
+/// Outcome of `recover`
+#[derive(Debug, Default)]
+pub struct Recovery {
+    /// Rows added to the logs
+    pub rows: usize,
+    /// Journals whose log was gone, they are removed without replay
+    pub orphans: Vec<PathBuf>,
+}
+
+/// Replay the journals left in `dir` by interrupted sessions into their logs. Only the journals
+/// of the logs `include` accepts are touched.
+pub fn recover(dir: &Path, include: impl Fn(&Path) -> bool) -> Result<Recovery> {
+    let mut recovery = Recovery::default();
+
+    for entry in fs::read_dir(dir)? {
+        let wal = entry?.path();
+        if wal.extension().is_none_or(|e| e != "wal") {
+            continue;
+        }
+
+        let log = wal.with_extension("");
+        if !include(&log) {
+            continue;
+        }
+        if log.exists() {
+            recovery.rows += replay(&wal, &log)?;
+        } else {
+            recovery.orphans.push(wal.clone());
+        }
+        fs::remove_file(&wal)?;
+    }
+
+    Ok(recovery)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_unflushed_tail() {
+        let dir = std::env::temp_dir().join(format!("envsensor-wal-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // The buffer got "1" and half of "2" to disk before the crash
+        let log = dir.join("a.csv");
+        fs::write(&log, "Timestamp,CO(ppm)\nt1,1\nt2,").unwrap();
+        let mut wal = Wal::open(&log).unwrap();
+        for row in ["t1,1", "t2,2", "t3,3"] {
+            wal.append(row).unwrap();
+        }
+        drop(wal);
+
+        // Journals of other logs are left alone
+        let other = dir.join("b.csv");
+        fs::write(&other, "Timestamp,CO(ppm)\n").unwrap();
+        Wal::open(&other).unwrap().append("t1,1").unwrap();
+
+        let recovery = recover(&dir, |l| l == log).unwrap();
+        assert_eq!(recovery.rows, 2);
+        assert!(recovery.orphans.is_empty());
+        assert_eq!(
+            fs::read_to_string(&log).unwrap(),
+            "Timestamp,CO(ppm)\nt1,1\nt2,2\nt3,3\n"
+        );
+        assert!(!wal_path(&log).exists());
+        assert!(wal_path(&other).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
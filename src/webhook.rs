@@ -0,0 +1,183 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{RecvTimeoutError, Sender},
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use bus::BusReader;
+use serde::Serialize;
+use ureq::Agent;
+
+use crate::config::Site;
+use crate::logger::TimestampFormat;
+use crate::outbox::Outbox;
+use crate::sensor::{AppMsg, SampleData, SensorChannel, SensorModel};
+
+/// HTTP POST sink settings
+#[derive(Clone, Debug)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Extra request headers, e.g. ("X-Api-Key", "...")
+    pub headers: Vec<(String, String)>,
+    /// Sent as "Authorization: Bearer <token>"
+    pub bearer_token: Option<String>,
+    /// Samples per request, more than one posts a JSON array
+    pub batch: usize,
+    pub timestamp: TimestampFormat,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            headers: Vec::new(),
+            bearer_token: None,
+            batch: 1,
+            timestamp: TimestampFormat::Rfc3339,
+        }
+    }
+}
+
+/// JSON body of one sample, values are keyed by `SensorChannel::id()`
+#[derive(Serialize)]
+struct Payload<'a> {
+    model: &'a str,
+    timestamp: String,
+    values: BTreeMap<&'a str, f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    site: Option<&'a Site>,
+}
+
+fn payload<'a>(
+    model: &'a str,
+    site: Option<&'a Site>,
+    ids: &'a [String],
+    sample: &SampleData,
+    timestamp: &TimestampFormat,
+) -> Payload<'a> {
+    Payload {
+        model,
+        site,
+        timestamp: timestamp.format(&sample.timestamp),
+        values: ids
+            .iter()
+            .map(String::as_str)
+            .zip(sample.data.iter().map(|d| d.value))
+            .collect(),
+    }
+}
+
+fn post(agent: &Agent, config: &WebhookConfig, body: String) -> Result<()> {
+    let mut request = agent
+        .post(&config.url)
+        .header("Content-Type", "application/json");
+
+    for (key, value) in &config.headers {
+        request = request.header(key, value);
+    }
+    if let Some(token) = &config.bearer_token {
+        request = request.header("Authorization", format!("Bearer {token}"));
+    }
+
+    request.send(body)?;
+
+    Ok(())
+}
+
+/// Requests kept while the endpoint is slow or unreachable, the oldest are dropped first
+const QUEUED_REQUESTS: usize = 100;
+
+pub fn spawn_webhook_thread(
+    model: SensorModel,
+    flag: Arc<AtomicBool>,
+    mut rx: BusReader<AppMsg>,
+    channels: &[SensorChannel],
+    config: WebhookConfig,
+    site: Option<Site>,
+    status: Sender<AppMsg>,
+) {
+    let ids = channels.iter().map(|ch| ch.id()).collect::<Vec<_>>();
+    let outbox = Arc::new(Outbox::new(QUEUED_REQUESTS));
+
+    // Posting on a worker of its own, a slow endpoint must not stall the bus
+    let requests = outbox.clone();
+    let worker_config = config.clone();
+    thread::spawn(move || {
+        let agent: Agent = Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(10)))
+            .build()
+            .into();
+        // Only the first failure while the endpoint is unreachable is reported
+        let mut posted = true;
+
+        while let Some(body) = requests.pop() {
+            // A failing endpoint must not stop acquisition, the CSV log still has the data
+            match post(&agent, &worker_config, body) {
+                Ok(()) => posted = true,
+                Err(e) => {
+                    if posted {
+                        let _ = status.send(AppMsg::Status(format!(
+                            "Webhook POST to {} failed: {e}",
+                            worker_config.url
+                        )));
+                    }
+                    posted = false;
+                }
+            }
+        }
+    });
+
+    thread::spawn(move || -> Result<()> {
+        let mut pending: Vec<SampleData> = Vec::new();
+
+        let send = |pending: &mut Vec<SampleData>| -> Result<()> {
+            let mut bodies = pending
+                .iter()
+                .map(|s| payload(model.as_ref(), site.as_ref(), &ids, s, &config.timestamp))
+                .collect::<Vec<_>>();
+
+            let body = if config.batch > 1 {
+                serde_json::to_string(&bodies)?
+            } else {
+                serde_json::to_string(&bodies.remove(0))?
+            };
+            pending.clear();
+            outbox.push(body);
+
+            Ok(())
+        };
+
+        let mut read = || -> Result<()> {
+            while !flag.load(Ordering::SeqCst) {
+                match rx.recv_timeout(Duration::from_millis(500)) {
+                    Ok(AppMsg::Sample(sample)) => {
+                        pending.push(sample);
+                        if pending.len() >= config.batch.max(1) {
+                            send(&mut pending)?;
+                        }
+                    }
+                    Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+
+            if !pending.is_empty() {
+                send(&mut pending)?;
+            }
+
+            Ok(())
+        };
+
+        // The worker sends what is still queued and ends
+        let result = read();
+        outbox.close();
+
+        result
+    });
+}
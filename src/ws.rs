@@ -0,0 +1,132 @@
+use std::{
+    net::{TcpListener, TcpStream},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError, Sender},
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use bus::BusReader;
+use serde_json::json;
+use tungstenite::{Message, WebSocket};
+
+use crate::config::Site;
+use crate::sensor::{AppMsg, SensorChannel, SensorModel};
+
+/// Longest a client may take for its handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// WebSocket live-stream settings
+#[derive(Clone, Debug)]
+pub struct WsConfig {
+    pub addr: String,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        Self {
+            addr: "0.0.0.0:9001".to_string(),
+        }
+    }
+}
+
+/// Accept the pending clients, each handshake on a thread of its own so a slow client can't
+/// stall the stream, the clients greeted with `hello` are sent to `clients` and failures to
+/// `status`
+fn accept(
+    listener: &TcpListener,
+    hello: &str,
+    clients: &Sender<WebSocket<TcpStream>>,
+    status: &Sender<AppMsg>,
+) {
+    loop {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return,
+            Err(e) => {
+                let _ = status.send(AppMsg::Status(format!("WebSocket accept failed: {e}")));
+                return;
+            }
+        };
+
+        let hello = hello.to_string();
+        let clients = clients.clone();
+        let status = status.clone();
+        thread::spawn(move || {
+            let handshake = || -> Result<WebSocket<TcpStream>> {
+                stream.set_nonblocking(false)?;
+                stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+                // Kept for the stream, a client that stops reading is dropped
+                stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+                let mut ws = tungstenite::accept(stream).map_err(|e| anyhow!("{e}"))?;
+                ws.send(Message::text(hello))?;
+                Ok(ws)
+            };
+
+            match handshake() {
+                Ok(ws) => {
+                    let _ = clients.send(ws);
+                }
+                Err(e) => {
+                    let _ = status.send(AppMsg::Status(format!("WebSocket handshake failed: {e}")));
+                }
+            }
+        });
+    }
+}
+
+/// Stream every `AppMsg` as JSON to all connected WebSocket clients
+///
+/// Each client first receives a "Hello" message with the model, site and channel list,
+/// followed by `{"type": "Sample" | "Status", "data": ...}` messages.
+pub fn spawn_ws_thread(
+    model: SensorModel,
+    flag: Arc<AtomicBool>,
+    mut rx: BusReader<AppMsg>,
+    channels: &[SensorChannel],
+    config: WsConfig,
+    site: Option<Site>,
+    status: Sender<AppMsg>,
+) -> Result<()> {
+    let listener = TcpListener::bind(&config.addr)?;
+    listener.set_nonblocking(true)?;
+
+    let hello = json!({
+        "type": "Hello",
+        "data": { "model": model, "site": site, "channels": channels },
+    })
+    .to_string();
+
+    thread::spawn(move || -> Result<()> {
+        let mut clients: Vec<WebSocket<TcpStream>> = Vec::new();
+        let (greeted_tx, greeted) = mpsc::channel();
+
+        while !flag.load(Ordering::SeqCst) {
+            accept(&listener, &hello, &greeted_tx, &status);
+            clients.extend(greeted.try_iter());
+
+            let msg = match rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(msg) => msg,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            let text = serde_json::to_string(&msg)?;
+            // Clients which went away fail to send and are dropped
+            clients.retain_mut(|ws| ws.send(Message::text(text.clone())).is_ok());
+        }
+
+        for mut ws in clients {
+            let _ = ws.close(None);
+            let _ = ws.flush();
+        }
+
+        Ok(())
+    });
+
+    Ok(())
+}
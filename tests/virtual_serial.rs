@@ -0,0 +1,276 @@
+//! The real drivers against scripted devices on virtual serial port pairs, from the serial
+//! port through the sensor thread to the CSV log
+//!
+//! Unix uses pty pairs. Elsewhere give a com0com-style loopback pair as
+//! `ENVSENSOR_TEST_PORTS=<device end>,<sensor end>` and run with `--test-threads=1`, the
+//! tests are skipped without it.
+#![cfg(feature = "serial")]
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bus::Bus;
+use crc::Crc;
+use serialport::SerialPort;
+
+use envsensor_demo::{
+    config::Config,
+    logger::LogConfig,
+    sensor::{AppMsg, Sensor, SensorModel, SensorType, Unit},
+    session::Session,
+};
+
+/// Longest wait for the samples and the log
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+const CRC_16_MODBUS: Crc<u16> = Crc::<u16>::new(&crc::CRC_16_MODBUS);
+
+/// Both ends of a virtual serial line
+struct PortPair {
+    /// End the fake device is scripted on
+    device: Box<dyn SerialPort>,
+    /// Name of the end the driver opens
+    sensor: String,
+    /// Keeps the pty slave open while the driver reopens it
+    _slave: Option<Box<dyn SerialPort>>,
+}
+
+#[cfg(unix)]
+fn port_pair() -> Option<PortPair> {
+    let (device, slave) = serialport::TTYPort::pair().expect("Failed to create a pty pair");
+
+    Some(PortPair {
+        sensor: slave.name()?,
+        device: Box::new(device),
+        _slave: Some(Box::new(slave)),
+    })
+}
+
+#[cfg(not(unix))]
+fn port_pair() -> Option<PortPair> {
+    let ports = std::env::var("ENVSENSOR_TEST_PORTS").ok()?;
+    let (device, sensor) = ports.split_once(',')?;
+    let device = serialport::new(device, 9600)
+        .open()
+        .expect("Failed to open the device end");
+
+    Some(PortPair {
+        device,
+        sensor: sensor.to_string(),
+        _slave: None,
+    })
+}
+
+/// Fill `buf` from `port`, false when `done` is set first
+fn receive(port: &mut dyn SerialPort, buf: &mut [u8], done: &AtomicBool) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        if done.load(Ordering::SeqCst) {
+            return Ok(false);
+        }
+        match port.read(&mut buf[filled..]) {
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(true)
+}
+
+/// TB600B-C configured for CO in ppm and mg/m³, reporting 12.345 ppm and 14.142 mg/m³
+fn tb600b_c(port: &mut dyn SerialPort, done: &AtomicBool) -> io::Result<()> {
+    let mut command = [0; 9];
+    if !receive(port, &mut command, done)? {
+        return Ok(());
+    }
+    assert_eq!(
+        command[..4],
+        [0xFF, 0x01, 0x78, 0x41],
+        "Expected query mode"
+    );
+
+    let mut query = [0; 1];
+    if !receive(port, &mut query, done)? {
+        return Ok(());
+    }
+    assert_eq!(query, [0xD7]);
+    // CO, 0 to 1000, ppm and mg/m³, three decimals
+    port.write_all(&[0xFF, 0xD7, 0x19, 0x03, 0xE8, 0x02, 0x30, 0x00, 0x00])?;
+
+    if !receive(port, &mut command, done)? {
+        return Ok(());
+    }
+    assert_eq!(
+        command[..4],
+        [0xFF, 0x01, 0x78, 0x40],
+        "Expected auto report mode"
+    );
+
+    while !done.load(Ordering::SeqCst) {
+        port.write_all(&[0xFF, 0x86, 0x37, 0x3E, 0x03, 0xE8, 0x30, 0x39, 0x00])?;
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    Ok(())
+}
+
+/// Rydason Modbus RTU slave measuring 12.34 ppm of CO
+fn rydason(port: &mut dyn SerialPort, done: &AtomicBool) -> io::Result<()> {
+    let mut request = [0; 8];
+    while receive(port, &mut request, done)? {
+        assert_eq!(request[1], 0x03, "Expected a holding register read");
+        assert_eq!(
+            request[6..],
+            CRC_16_MODBUS.checksum(&request[..6]).to_le_bytes()
+        );
+
+        let data: &[u8] = match u16::from_be_bytes([request[2], request[3]]) {
+            // CO
+            0x0101 => &[0x00, 0x01],
+            // ppm
+            0x0102 => &[0x00, 0x02],
+            // Two decimals
+            0x0103 => &[0x00, 0x02],
+            0x0108 => &1234_u32.to_be_bytes(),
+            reg => panic!("Unexpected register {reg:#06x}"),
+        };
+        let mut response = vec![request[0], 0x03, data.len() as u8];
+        response.extend_from_slice(data);
+        response.extend_from_slice(&CRC_16_MODBUS.checksum(&response).to_le_bytes());
+        port.write_all(&response)?;
+    }
+
+    Ok(())
+}
+
+/// Empty directory for the logs of one test
+fn log_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("envsensor-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Read `samples` samples of `model` on `port` logging to `dir`, returns the logged session
+fn run_session(model: SensorModel, port: &str, dir: &Path, samples: usize) -> Session {
+    let config = Config {
+        log: LogConfig {
+            dir: dir.to_path_buf(),
+            name: Some(model.as_ref().to_string()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut bus = Bus::new(10);
+    let mut sensor = Sensor::new(&model, port, bus.add_rx(), config).unwrap();
+    sensor.start(bus).unwrap();
+
+    let deadline = Instant::now() + TIMEOUT;
+    let mut received = 0;
+    let mut log = None;
+    while received < samples {
+        assert!(
+            Instant::now() < deadline,
+            "Timed out after {received} samples"
+        );
+        log = log.or_else(|| sensor.log_path());
+        match sensor.try_recv() {
+            Some(AppMsg::Sample(_)) => received += 1,
+            Some(AppMsg::Error(e)) => panic!("The sensor thread stopped: {e}"),
+            Some(_) => {}
+            None => thread::sleep(Duration::from_millis(20)),
+        }
+    }
+    sensor.stop();
+    drop(sensor);
+    let log = log.expect("No log file was created");
+
+    // The log thread writes the rest once it sees the stop
+    loop {
+        if let Ok(session) = Session::load_csv(&log)
+            && session.samples.len() >= samples
+        {
+            return session;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "Incomplete log {}",
+            log.display()
+        );
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Script `device` on one end of a port pair while `model` runs on the other
+fn with_device(
+    name: &str,
+    model: SensorModel,
+    samples: usize,
+    device: fn(&mut dyn SerialPort, &AtomicBool) -> io::Result<()>,
+) -> Option<Session> {
+    let Some(PortPair {
+        device: mut port,
+        sensor,
+        _slave,
+    }) = port_pair()
+    else {
+        eprintln!("No virtual serial port pair, skipped");
+        return None;
+    };
+    port.set_timeout(Duration::from_millis(100)).unwrap();
+
+    let done = Arc::new(AtomicBool::new(false));
+    let fake = {
+        let done = done.clone();
+        thread::spawn(move || device(port.as_mut(), &done))
+    };
+
+    let dir = log_dir(name);
+    let session = run_session(model, &sensor, &dir, samples);
+    done.store(true, Ordering::SeqCst);
+    fake.join()
+        .expect("The fake device panicked")
+        .expect("The fake device failed");
+    std::fs::remove_dir_all(dir).unwrap();
+
+    Some(session)
+}
+
+#[test]
+fn tb600b_c_to_csv() {
+    let Some(session) = with_device("tb600b_c", SensorModel::EC_TB600BC, 5, tb600b_c) else {
+        return;
+    };
+
+    let channels = session
+        .channels
+        .iter()
+        .map(|ch| (ch.sensor_type, ch.unit))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        channels,
+        [(SensorType::CO, Unit::PPM), (SensorType::CO, Unit::MgPerM3)]
+    );
+    for sample in &session.samples {
+        assert!((sample.data[0].value - 12.345).abs() < 1e-3);
+        assert!((sample.data[1].value - 14.142).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn rydason_to_csv() {
+    let Some(session) = with_device("rydason", SensorModel::RYDASON, 2, rydason) else {
+        return;
+    };
+
+    assert_eq!(session.channels.len(), 1);
+    assert_eq!(session.channels[0].unit, Unit::PPM);
+    for sample in &session.samples {
+        assert!((sample.data[0].value - 12.34).abs() < 1e-3);
+    }
+}